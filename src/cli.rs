@@ -0,0 +1,115 @@
+use chrono::Duration;
+use std::path::PathBuf;
+use crate::export::Format;
+use crate::MessageSource;
+
+pub const USAGE: &str = "\
+Usage:
+  friend [--plain]                    Start the TUI (--plain: no colors, emoji or mouse, for screen readers)
+  friend check                        Check that each configured provider accepts its credentials
+  friend cleanup --older-than <age>   Delete cached messages older than <age> (e.g. 30d, 12h, 2w)
+  friend cleanup --all                Delete every cached message
+  friend export <source> <channel id> [--format markdown|json] [--output <path>]
+                                      Write a channel's cached messages to a file (or stdout)
+  friend import <file.json>           Add the messages of a JSON export to the cache, skipping ones already there
+  friend set-secret <service/name>    Store a secret in the OS keyring for use as keyring:<service/name>";
+
+pub enum Command {
+    /// `plain` turns off colors, emoji and mouse capture
+    Tui { plain: bool },
+    Check,
+    Cleanup(CleanupScope),
+    Export(ExportOptions),
+    Import(PathBuf),
+    /// Prompt for a secret and store it under this keyring reference
+    SetSecret(String),
+    /// Print `USAGE`
+    Help,
+}
+
+pub enum CleanupScope {
+    OlderThan(Duration),
+    All,
+}
+
+pub struct ExportOptions {
+    pub source: MessageSource,
+    pub channel_id: String,
+    /// Guessed from `output`'s extension when not given
+    pub format: Option<Format>,
+    /// Printed to stdout when not given
+    pub output: Option<PathBuf>,
+}
+
+/// Parses the command line (without the program name)
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let Some(command) = args.first() else {
+        return Ok(Command::Tui { plain: false });
+    };
+
+    match command.as_str() {
+        "--plain" if args.len() == 1 => Ok(Command::Tui { plain: true }),
+        "check" => Ok(Command::Check),
+        "cleanup" => parse_cleanup(&args[1..]),
+        "export" => parse_export(&args[1..]),
+        "import" => match &args[1..] {
+            [path] => Ok(Command::Import(PathBuf::from(path))),
+            _ => Err(USAGE.to_string()),
+        },
+        "set-secret" => match &args[1..] {
+            [reference] => Ok(Command::SetSecret(reference.clone())),
+            _ => Err(USAGE.to_string()),
+        },
+        "-h" | "--help" | "help" => Ok(Command::Help),
+        other => Err(format!("Unknown command '{}'\n\n{}", other, USAGE)),
+    }
+}
+
+fn parse_cleanup(args: &[String]) -> Result<Command, String> {
+    match args {
+        [flag] if flag == "--all" => Ok(Command::Cleanup(CleanupScope::All)),
+        [flag, age] if flag == "--older-than" => Ok(Command::Cleanup(CleanupScope::OlderThan(parse_age(age)?))),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn parse_export(args: &[String]) -> Result<Command, String> {
+    let [source, channel_id, flags @ ..] = args else {
+        return Err(USAGE.to_string());
+    };
+    let source = MessageSource::from_name(source).ok_or_else(|| format!("Unknown source '{}'", source))?;
+
+    let mut options = ExportOptions { source, channel_id: channel_id.clone(), format: None, output: None };
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        let value = flags.next().ok_or_else(|| USAGE.to_string())?;
+        match flag.as_str() {
+            "--format" => {
+                options.format = Some(Format::from_name(value).ok_or_else(|| format!("Unknown format '{}': expected markdown or json", value))?);
+            }
+            "--output" | "-o" => options.output = Some(PathBuf::from(value)),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    Ok(Command::Export(options))
+}
+
+/// Parses ages like `30d`, `12h`, `45m` or `2w`. Ages reaching back
+/// further than dates go are refused rather than overflowing.
+fn parse_age(age: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid age '{}': expected a number followed by m, h, d or w (e.g. 30d)", age);
+
+    let unit_start = age.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let amount: i64 = age[..unit_start].parse().map_err(|_| invalid())?;
+
+    let duration = match &age[unit_start..] {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    };
+    duration
+        .filter(|&duration| chrono::Utc::now().checked_sub_signed(duration).is_some())
+        .ok_or_else(invalid)
+}
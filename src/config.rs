@@ -1,13 +1,120 @@
+use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
+use crate::{secrets, MessageSource};
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub telegram: Option<TelegramConfig>,
+    /// One per account, several when `TELEGRAM_ACCOUNTS` names them
+    pub telegram: Vec<TelegramConfig>,
     pub discord: Option<DiscordConfig>,
     pub github: Option<GitHubConfig>,
-    pub jira: Option<JiraConfig>,
+    /// One per site, several when `JIRA_SITES` names them
+    pub jira: Vec<JiraConfig>,
+    pub reddit: Option<RedditConfig>,
+    pub xmpp: Option<XmppConfig>,
+    pub teams: Option<TeamsConfig>,
+    pub twilio: Option<TwilioConfig>,
+    pub rest: Vec<RestConfig>,
     pub message_limit: usize,
+    /// Per-source caps (`<SOURCE>_MESSAGE_LIMIT`) that also reserve room in
+    /// `message_limit`, so a busy source can't crowd the others out
+    pub source_limits: HashMap<MessageSource, usize>,
+    pub refresh_interval_secs: u64,
+    pub render_markdown: bool,
+    /// Show `:shortcode:`s as emoji in the content pane
+    pub render_shortcodes: bool,
+    /// Only show the author on the first of consecutive messages from them
+    pub group_by_author: bool,
+    /// Wrap long list rows onto a second line instead of truncating them
+    pub wrap_list: bool,
+    /// Mark a message read as soon as it's selected
+    pub mark_read_on_select: bool,
+    /// Ask before sending, naming where the message will go
+    pub confirm_send: bool,
     pub colors: ColorConfig,
+    pub icons: IconConfig,
+    pub time: TimeConfig,
+    pub layout: LayoutConfig,
+    pub database: DatabaseConfig,
+    pub alerts: AlertConfig,
+    pub spellcheck: Option<SpellcheckConfig>,
+    pub mutes: MuteConfig,
+    /// Lowercased authors whose messages are never cached or shown
+    pub ignored_authors: Vec<String>,
+    /// Proxy for every HTTP provider, e.g. `http://proxy.corp:3128`
+    pub proxy_url: Option<String>,
+    /// `host:port` connected to now and then to tell whether the network is
+    /// up, on top of watching for failed refreshes. Off unless set.
+    pub connectivity_probe: Option<String>,
+    /// No colors, emoji or mouse capture, for screen readers and logs
+    pub plain: bool,
+    /// `User-Agent` sent by every HTTP provider
+    pub user_agent: String,
+    /// Where attachments are saved
+    pub download_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MuteConfig {
+    /// Channels hidden from the list, as (source, channel id)
+    pub channels: Vec<(MessageSource, String)>,
+    /// Authors hidden from the list, lowercased
+    pub authors: Vec<String>,
+}
+
+/// Flagging unknown words in the compose box (`SPELLCHECK=true`)
+#[derive(Debug, Clone)]
+pub struct SpellcheckConfig {
+    /// Picks the system dictionary, e.g. `en_US` or `de_DE`
+    pub language: String,
+    /// A `.dic` file or word list to use instead
+    pub dictionary: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    /// Lowercased terms matched against message content and author
+    pub keywords: Vec<String>,
+    /// Ring the terminal bell when a new message matches a keyword
+    pub bell: bool,
+    /// Lowercased priority terms, matched like `keywords`, that still ring
+    /// while do-not-disturb or a snooze is on
+    pub bypass_dnd: Vec<String>,
+    /// How long `z` silences alerts for
+    pub snooze: std::time::Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    /// Location of the message cache
+    pub path: PathBuf,
+    /// Encrypt the cache with SQLCipher (requires the `sqlcipher` feature)
+    pub encrypt: bool,
+    /// Passphrase the key is derived from; prompted for at startup if unset
+    pub passphrase: Option<String>,
+    /// Most messages to keep cached, oldest deleted first
+    pub max_cached_messages: Option<usize>,
+    /// Per-source caps (`<SOURCE>_MAX_CACHED_MESSAGES`)
+    pub source_max_cached_messages: HashMap<MessageSource, usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeConfig {
+    /// strftime format for times, used in the message list
+    pub time_format: String,
+    /// strftime format for dates, combined with `time_format` in the content pane
+    pub date_format: String,
+    /// Convert timestamps to the local timezone instead of showing UTC
+    pub use_local_time: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutConfig {
+    /// Put the content pane beside the list instead of below it
+    pub side_by_side: bool,
+    /// Share of the screen given to the message list, 10-90
+    pub list_percent: u16,
 }
 
 #[derive(Debug, Clone)]
@@ -16,67 +123,595 @@ pub struct ColorConfig {
     pub selected_fg: Option<String>,
     pub input_active: Option<String>,
     pub input_inactive: Option<String>,
+    pub author_colors: bool,
+    pub author_palette: Option<Vec<String>>,
+    /// Background for messages matching an alert keyword
+    pub alert_bg: Option<String>,
+    /// Accent colors for each source's icon in the list
+    pub telegram_color: Option<String>,
+    pub discord_color: Option<String>,
+    pub github_color: Option<String>,
+    pub jira_color: Option<String>,
+    pub reddit_color: Option<String>,
+    pub xmpp_color: Option<String>,
+    pub teams_color: Option<String>,
+    pub sms_color: Option<String>,
+    pub rest_color: Option<String>,
+}
+
+/// Names accepted by `THEME`
+pub const THEMES: [&str; 4] = ["default", "high-contrast", "solarized-dark", "monochrome"];
+
+impl ColorConfig {
+    /// A named theme's colors (see `THEMES`); `None` fields keep the
+    /// built-in defaults. Unknown names get the default theme.
+    pub fn preset(name: &str) -> ColorConfig {
+        let color = |name: &str| Some(name.to_string());
+        let palette = |names: &[&str]| Some(names.iter().map(|name| name.to_string()).collect());
+        match name {
+            "high-contrast" => ColorConfig {
+                selected_bg: color("White"),
+                selected_fg: color("Black"),
+                input_active: color("LightYellow"),
+                input_inactive: color("White"),
+                author_colors: true,
+                author_palette: palette(&["LightCyan", "LightGreen", "LightYellow", "LightMagenta", "White"]),
+                alert_bg: color("Red"),
+                telegram_color: color("LightCyan"),
+                discord_color: color("LightMagenta"),
+                github_color: color("LightGreen"),
+                jira_color: color("LightBlue"),
+                reddit_color: color("LightRed"),
+                xmpp_color: color("LightGreen"),
+                teams_color: color("LightMagenta"),
+                sms_color: color("LightGreen"),
+                rest_color: color("LightYellow"),
+            },
+            "solarized-dark" => ColorConfig {
+                selected_bg: color("#073642"),
+                selected_fg: color("#93a1a1"),
+                input_active: color("#b58900"),
+                input_inactive: color("#586e75"),
+                author_colors: true,
+                author_palette: palette(&["#2aa198", "#859900", "#b58900", "#d33682", "#268bd2", "#cb4b16", "#6c71c4"]),
+                alert_bg: color("#dc322f"),
+                telegram_color: color("#2aa198"),
+                discord_color: color("#6c71c4"),
+                github_color: color("#859900"),
+                jira_color: color("#268bd2"),
+                reddit_color: color("#cb4b16"),
+                xmpp_color: color("#d33682"),
+                teams_color: color("#dc322f"),
+                sms_color: color("#859900"),
+                rest_color: color("#b58900"),
+            },
+            "monochrome" => ColorConfig {
+                selected_bg: color("White"),
+                selected_fg: color("Black"),
+                input_active: color("White"),
+                input_inactive: color("DarkGray"),
+                author_colors: false,
+                author_palette: None,
+                alert_bg: color("DarkGray"),
+                telegram_color: color("White"),
+                discord_color: color("White"),
+                github_color: color("White"),
+                jira_color: color("White"),
+                reddit_color: color("White"),
+                xmpp_color: color("White"),
+                teams_color: color("White"),
+                sms_color: color("White"),
+                rest_color: color("White"),
+            },
+            _ => ColorConfig {
+                selected_bg: None,
+                selected_fg: None,
+                input_active: None,
+                input_inactive: None,
+                author_colors: true,
+                author_palette: None,
+                alert_bg: None,
+                telegram_color: None,
+                discord_color: None,
+                github_color: None,
+                jira_color: None,
+                reddit_color: None,
+                xmpp_color: None,
+                teams_color: None,
+                sms_color: None,
+                rest_color: None,
+            },
+        }
+    }
+}
+
+/// Prefix shown before each message in the list, per source
+#[derive(Debug, Clone)]
+pub struct IconConfig {
+    pub telegram: String,
+    pub discord: String,
+    pub github: String,
+    pub jira: String,
+    pub reddit: String,
+    pub xmpp: String,
+    pub teams: String,
+    pub sms: String,
+    pub rest: String,
+}
+
+impl IconConfig {
+    /// Labels any font can draw
+    pub fn ascii() -> Self {
+        Self {
+            telegram: "[T]".to_string(),
+            discord: "[D]".to_string(),
+            github: "[G]".to_string(),
+            jira: "[J]".to_string(),
+            reddit: "[Rd]".to_string(),
+            xmpp: "[X]".to_string(),
+            teams: "[MT]".to_string(),
+            sms: "[S]".to_string(),
+            rest: "[R]".to_string(),
+        }
+    }
+
+    pub fn for_source(&self, source: MessageSource) -> &str {
+        match source {
+            MessageSource::Telegram => &self.telegram,
+            MessageSource::Discord => &self.discord,
+            MessageSource::Github => &self.github,
+            MessageSource::Jira => &self.jira,
+            MessageSource::Reddit => &self.reddit,
+            MessageSource::Xmpp => &self.xmpp,
+            MessageSource::Teams => &self.teams,
+            MessageSource::Sms => &self.sms,
+            MessageSource::Rest => &self.rest,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct TelegramConfig {
+    /// The account's name in `TELEGRAM_ACCOUNTS`, if several are configured
+    pub name: Option<String>,
     pub api_id: i32,
     pub api_hash: String,
     pub phone: String,
     pub session_file: Option<String>,
+    pub fetch_limits: TelegramFetchLimits,
+    /// Receive new messages live via `next_update` instead of only polling
+    pub live_updates: bool,
+    pub fetch_timeout_secs: u64,
+    /// SOCKS5 proxy for the MTProto connection
+    pub proxy_url: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelegramFetchLimits {
+    /// Number of most recent dialogs to read, `None` for all of them
+    pub dialog_limit: Option<usize>,
+    pub messages_per_chat: usize,
+    /// Broadcast channels are skipped unless explicitly enabled
+    pub include_channels: bool,
+    pub messages_per_channel: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct DiscordConfig {
     pub user_token: String,
     pub channel_ids: Vec<String>,
+    /// Follow every DM and group DM of the account
+    pub include_dms: bool,
+    /// Guilds whose text channels are all followed
+    pub guild_ids: Vec<String>,
+    /// Receive new messages live over the gateway websocket
+    pub gateway: bool,
+    pub fetch_timeout_secs: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct GitHubConfig {
     pub token: String,
     pub username: String,
+    pub fetch_timeout_secs: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct JiraConfig {
+    /// The site's name in `JIRA_SITES`, if several are configured
+    pub name: Option<String>,
     pub base_url: String,
     pub email: String,
     pub api_token: String,
     pub project_keys: Vec<String>,
+    pub fetch_timeout_secs: u64,
+}
+
+/// An app registered at reddit.com/prefs/apps, authorized once for the
+/// `privatemessages`, `read`, `identity` and `submit` scopes
+#[derive(Debug, Clone)]
+pub struct RedditConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Long-lived token traded for hour-long access tokens as needed
+    pub refresh_token: String,
+    pub fetch_timeout_secs: u64,
+}
+
+/// An XMPP account, connected over WebSocket (RFC 7395) since that's what
+/// the already-bundled websocket client speaks. The server must offer the
+/// WebSocket endpoint and SASL PLAIN sign-in; servers that only take
+/// direct TCP connections aren't reachable.
+#[derive(Debug, Clone)]
+pub struct XmppConfig {
+    /// The account, e.g. `me@example.org`
+    pub jid: String,
+    /// Sent as-is with SASL PLAIN, so only ever over TLS
+    pub password: String,
+    /// WebSocket endpoint, e.g. `wss://example.org/xmpp-websocket`.
+    /// Must be `wss://`. Derived from the JID's domain when unset.
+    pub server: Option<String>,
+    /// Group chats (MUC room JIDs) to join
+    pub rooms: Vec<String>,
+    /// People whose direct messages are shown; everyone's when empty
+    pub contacts: Vec<String>,
+    /// Name used in group chats
+    pub nickname: String,
+    pub fetch_timeout_secs: u64,
+}
+
+/// An app registered in Microsoft Entra ID with the delegated
+/// `ChannelMessage.Read.All`, `ChannelMessage.Send`, `Chat.ReadWrite`,
+/// `User.Read` and `offline_access` permissions, authorized once
+#[derive(Debug, Clone)]
+pub struct TeamsConfig {
+    /// Directory (tenant) id, or `common`/`organizations`
+    pub tenant_id: String,
+    pub client_id: String,
+    /// Left out for public clients
+    pub client_secret: Option<String>,
+    pub refresh_token: String,
+    /// Followed channels, each as `team id/channel id`
+    pub channels: Vec<String>,
+    /// Also read the most recently active chats
+    pub include_chats: bool,
+    pub fetch_timeout_secs: u64,
+}
+
+/// A Twilio number whose text messages are shown and answered
+#[derive(Debug, Clone)]
+pub struct TwilioConfig {
+    pub account_sid: String,
+    pub auth_token: String,
+    /// The Twilio number, in E.164 form (e.g. `+15551234567`)
+    pub from_number: String,
+    pub fetch_timeout_secs: u64,
+}
+
+/// A bespoke service polled over HTTP, described in the JSON file named by
+/// `REST_PROVIDERS_FILE`. Paths are dotted JSONPath-style selectors such as
+/// `$.data.items` or `user.name`.
+#[derive(Debug, Clone)]
+pub struct RestConfig {
+    pub name: String,
+    pub fetch_url: String,
+    /// Sent verbatim as the `Authorization` header (may be a `keyring:` reference)
+    pub auth_header: Option<String>,
+    /// Where the array of messages is in the response (empty for the root)
+    pub items_path: String,
+    pub id_path: String,
+    pub content_path: String,
+    /// The content is HTML and should be shown as plain text
+    pub content_html: bool,
+    pub author_path: Option<String>,
+    pub timestamp_path: String,
+    pub send_url: Option<String>,
+    /// JSON body to POST, with `{{content}}` replaced by the message text
+    pub send_body: Option<String>,
+    pub fetch_timeout_secs: u64,
+}
+
+impl RestConfig {
+    fn from_json(value: &serde_json::Value) -> Result<Self, String> {
+        let name = value["name"].as_str().ok_or("REST provider is missing \"name\"")?.to_string();
+        let required = |key: &str| {
+            value[key]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("REST provider {:?} is missing {:?}", name, key))
+        };
+        let optional = |key: &str| value[key].as_str().map(str::to_string);
+
+        Ok(RestConfig {
+            fetch_url: required("fetch_url")?,
+            auth_header: optional("auth_header")
+                .map(secrets::resolve)
+                .transpose()
+                .map_err(|e| format!("REST provider {:?}: {}", name, e))?,
+            items_path: optional("items_path").unwrap_or_default(),
+            id_path: required("id_path")?,
+            content_path: required("content_path")?,
+            content_html: value["content_html"].as_bool().unwrap_or(false),
+            author_path: optional("author_path"),
+            timestamp_path: required("timestamp_path")?,
+            send_url: optional("send_url"),
+            send_body: optional("send_body"),
+            fetch_timeout_secs: value["fetch_timeout_secs"].as_u64().unwrap_or_else(|| fetch_timeout_secs("REST")),
+            name,
+        })
+    }
+}
+
+fn load_rest_providers(path: &str) -> Result<Vec<RestConfig>, Box<dyn std::error::Error + Send + Sync>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read REST_PROVIDERS_FILE {}: {}", path, e))?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("REST_PROVIDERS_FILE {} is not valid JSON: {}", path, e))?;
+    let providers = value
+        .as_array()
+        .ok_or_else(|| format!("REST_PROVIDERS_FILE {} should contain a JSON array", path))?;
+
+    Ok(providers.iter().map(RestConfig::from_json).collect::<Result<_, _>>()?)
+}
+
+/// Where friend keeps its files by default: `$XDG_DATA_HOME/friend`, or
+/// `~/.local/share/friend` when that isn't set
+pub fn data_dir() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("friend")
+}
+
+/// Where attachments are saved by default: the desktop's download folder
+/// (`$XDG_DOWNLOAD_DIR`, from the environment or `user-dirs.dirs`), or
+/// `~/Downloads/friend`
+fn default_download_dir() -> PathBuf {
+    let from_user_dirs = || {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        let contents = std::fs::read_to_string(config_home.join("user-dirs.dirs")).ok()?;
+        contents.lines().find_map(|line| {
+            let value = line.trim().strip_prefix("XDG_DOWNLOAD_DIR=")?;
+            Some(value.trim_matches('"').to_string())
+        })
+    };
+    env::var("XDG_DOWNLOAD_DIR")
+        .ok()
+        .or_else(from_user_dirs)
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| expand_path(&dir))
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| expand_path("~/Downloads/friend"))
+}
+
+/// Expands a leading `~` and any `$VAR` or `${VAR}` in a configured path.
+/// Unset variables are left as written, so the mistake shows in the path.
+pub fn expand_path(path: &str) -> PathBuf {
+    let home = env::var("HOME").unwrap_or_default();
+    let path = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => path.to_string(),
+    };
+
+    let mut expanded = String::new();
+    let mut rest = path.as_str();
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{')
+            && let Some(end) = braced.find('}')
+        {
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        match env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => expanded.push_str(&rest[dollar..rest.len() - remainder.len()]),
+        }
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    PathBuf::from(expanded)
+}
+
+/// The prefix of each source's per-source variables, e.g. `GITHUB_MESSAGE_LIMIT`
+const SOURCE_PREFIXES: [(&str, MessageSource); 9] = [
+    ("TELEGRAM", MessageSource::Telegram),
+    ("DISCORD", MessageSource::Discord),
+    ("GITHUB", MessageSource::Github),
+    ("JIRA", MessageSource::Jira),
+    ("REDDIT", MessageSource::Reddit),
+    ("XMPP", MessageSource::Xmpp),
+    ("TEAMS", MessageSource::Teams),
+    ("SMS", MessageSource::Sms),
+    ("REST", MessageSource::Rest),
+];
+
+/// Reads a boolean environment variable, accepting the usual spellings
+/// (`1`/`0`, `true`/`false`, `yes`/`no`, `on`/`off`).
+fn env_flag(name: &str, default: bool) -> bool {
+    match env::var(name).map(|s| s.trim().to_lowercase()) {
+        Ok(value) if matches!(value.as_str(), "1" | "true" | "yes" | "on") => true,
+        Ok(value) if matches!(value.as_str(), "0" | "false" | "no" | "off") => false,
+        _ => default,
+    }
+}
+
+/// How long one provider's fetch may take before it's abandoned:
+/// `<PREFIX>_FETCH_TIMEOUT_SECS`, falling back to `FETCH_TIMEOUT_SECS`.
+fn fetch_timeout_secs(prefix: &str) -> u64 {
+    let parse = |name: String| env::var(name).ok().and_then(|s| s.parse::<u64>().ok()).filter(|&secs| secs > 0);
+    parse(format!("{}_FETCH_TIMEOUT_SECS", prefix))
+        .or_else(|| parse("FETCH_TIMEOUT_SECS".to_string()))
+        .unwrap_or(30)
+}
+
+/// Reads one Telegram account. A named account from `TELEGRAM_ACCOUNTS`
+/// takes its settings from `TELEGRAM_<NAME>_*`, falling back to the plain
+/// `TELEGRAM_API_ID`/`TELEGRAM_API_HASH` since accounts usually share an app.
+/// Fetch limits, live updates, timeout and proxy are shared by every account.
+fn telegram_account(name: Option<&str>) -> Result<Option<TelegramConfig>, Box<dyn std::error::Error + Send + Sync>> {
+    let prefix = match name {
+        Some(name) => format!("TELEGRAM_{}", name.to_uppercase()),
+        None => "TELEGRAM".to_string(),
+    };
+    let var = |key: &str| env::var(format!("{}_{}", prefix, key)).or_else(|_| env::var(format!("TELEGRAM_{}", key)));
+
+    let (Ok(api_id_str), Ok(api_hash), Ok(phone)) = (var("API_ID"), var("API_HASH"), env::var(format!("{}_PHONE", prefix))) else {
+        return Ok(None);
+    };
+    let Ok(api_id) = api_id_str.parse::<i32>() else {
+        return Ok(None);
+    };
+    // Each named account needs a session of its own
+    let session_file = env::var(format!("{}_SESSION_FILE", prefix)).ok().or_else(|| {
+        name.map(|name| data_dir().join(format!("telegram_session_{}.session", name.to_lowercase())).to_string_lossy().to_string())
+    });
+    let fetch_limits = TelegramFetchLimits {
+        // 0 means "no limit", i.e. read every dialog
+        dialog_limit: match env::var("TELEGRAM_DIALOG_LIMIT").ok().and_then(|s| s.parse::<usize>().ok()) {
+            Some(0) => None,
+            Some(limit) => Some(limit),
+            None => Some(20),
+        },
+        messages_per_chat: env::var("TELEGRAM_MESSAGES_PER_CHAT")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(10),
+        include_channels: env_flag("TELEGRAM_INCLUDE_CHANNELS", false),
+        messages_per_channel: env::var("TELEGRAM_MESSAGES_PER_CHANNEL")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(3),
+    };
+    let live_updates = env_flag("TELEGRAM_LIVE_UPDATES", false);
+    let fetch_timeout_secs = fetch_timeout_secs("TELEGRAM");
+    // Telegram only speaks SOCKS5, so only reuse PROXY_URL if it's one
+    let proxy_url = env::var("TELEGRAM_PROXY_URL")
+        .ok()
+        .or_else(|| env::var("PROXY_URL").ok().filter(|url| url.starts_with("socks5://")));
+    let api_hash = secrets::resolve(api_hash)?;
+    Ok(Some(TelegramConfig {
+        name: name.map(str::to_string),
+        api_id,
+        api_hash,
+        phone,
+        session_file,
+        fetch_limits,
+        live_updates,
+        fetch_timeout_secs,
+        proxy_url,
+    }))
+}
+
+/// Reads one Jira site. A named site from `JIRA_SITES` takes its settings
+/// from `JIRA_<NAME>_*`, falling back to the plain `JIRA_EMAIL` and
+/// `JIRA_API_TOKEN` since one Atlassian account often spans sites.
+fn jira_site(name: Option<&str>) -> Result<Option<JiraConfig>, Box<dyn std::error::Error + Send + Sync>> {
+    let prefix = match name {
+        Some(name) => format!("JIRA_{}", name.to_uppercase()),
+        None => "JIRA".to_string(),
+    };
+    let var = |key: &str| env::var(format!("{}_{}", prefix, key));
+    let shared = |key: &str| var(key).or_else(|_| env::var(format!("JIRA_{}", key)));
+
+    let (Ok(base_url), Ok(email), Ok(api_token), Ok(project_keys_str)) =
+        (var("BASE_URL"), shared("EMAIL"), shared("API_TOKEN"), var("PROJECT_KEY"))
+    else {
+        return Ok(None);
+    };
+    let project_keys: Vec<String> = project_keys_str
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if project_keys.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(JiraConfig {
+        name: name.map(str::to_string),
+        base_url,
+        email,
+        api_token: secrets::resolve(api_token)?,
+        project_keys,
+        fetch_timeout_secs: fetch_timeout_secs("JIRA"),
+    }))
+}
+
+/// A comma-separated list of names, like `TELEGRAM_ACCOUNTS`
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reports the missing variables of a provider that has only some of its
+/// required variables set
+fn check_group(problems: &mut Vec<String>, provider: &str, names: &[&str]) {
+    let (set, missing): (Vec<&str>, Vec<&str>) = names.iter().partition(|name| env::var(name).is_ok());
+    if !set.is_empty() && !missing.is_empty() {
+        problems.push(format!(
+            "{} is partly configured: {} set but {} missing",
+            provider,
+            set.join(", "),
+            missing.join(", ")
+        ));
+    }
+}
+
+/// Rejects strftime strings chrono can't format, which would otherwise
+/// panic the first time a timestamp is rendered.
+fn validate_time_format(name: &str, format: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use chrono::format::{Item, StrftimeItems};
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("{} is not a valid strftime format: {:?}", name, format).into());
+    }
+    Ok(())
 }
 
 impl Config {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         dotenv::dotenv().ok();
 
-        let telegram = if let (Ok(api_id_str), Ok(api_hash), Ok(phone)) = (
-            env::var("TELEGRAM_API_ID"),
-            env::var("TELEGRAM_API_HASH"),
-            env::var("TELEGRAM_PHONE"),
-        ) {
-            if let Ok(api_id) = api_id_str.parse::<i32>() {
-                let session_file = env::var("TELEGRAM_SESSION_FILE").ok();
-                Some(TelegramConfig { api_id, api_hash, phone, session_file })
-            } else {
-                None
-            }
+        let account_names = env_list("TELEGRAM_ACCOUNTS");
+        let telegram = if account_names.is_empty() {
+            telegram_account(None)?.into_iter().collect()
         } else {
-            None
+            let mut accounts = Vec::new();
+            for name in &account_names {
+                accounts.extend(telegram_account(Some(name))?);
+            }
+            accounts
         };
 
-        let discord = if let (Ok(user_token), Ok(channel_ids_str)) = (
-            env::var("DISCORD_USER_TOKEN"),
-            env::var("DISCORD_CHANNEL_IDS"),
-        ) {
-            let channel_ids: Vec<String> = channel_ids_str
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
+        let discord = if let Ok(user_token) = env::var("DISCORD_USER_TOKEN") {
+            let id_list = |name: &str| -> Vec<String> {
+                env::var(name)
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            };
+            let channel_ids = id_list("DISCORD_CHANNEL_IDS");
+            let guild_ids = id_list("DISCORD_GUILD_IDS");
+            let include_dms = env_flag("DISCORD_DMS", false);
             
-            if !channel_ids.is_empty() {
-                Some(DiscordConfig { user_token, channel_ids })
+            if !channel_ids.is_empty() || !guild_ids.is_empty() || include_dms {
+                let gateway = env_flag("DISCORD_GATEWAY", false);
+                let fetch_timeout_secs = fetch_timeout_secs("DISCORD");
+                let user_token = secrets::resolve(user_token)?;
+                Some(DiscordConfig { user_token, channel_ids, include_dms, guild_ids, gateway, fetch_timeout_secs })
             } else {
                 None
             }
@@ -88,60 +723,496 @@ impl Config {
             env::var("GITHUB_TOKEN"),
             env::var("GITHUB_USERNAME"),
         ) {
-            Some(GitHubConfig { token, username })
+            Some(GitHubConfig { token: secrets::resolve(token)?, username, fetch_timeout_secs: fetch_timeout_secs("GITHUB") })
         } else {
             None
         };
 
-        let jira = if let (Ok(base_url), Ok(email), Ok(api_token), Ok(project_keys_str)) = (
-            env::var("JIRA_BASE_URL"),
-            env::var("JIRA_EMAIL"),
-            env::var("JIRA_API_TOKEN"),
-            env::var("JIRA_PROJECT_KEY"),
-        ) {
-            let project_keys: Vec<String> = project_keys_str
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            
-            if !project_keys.is_empty() {
-                Some(JiraConfig {
-                    base_url,
-                    email,
-                    api_token,
-                    project_keys,
-                })
-            } else {
-                None
+        let site_names = env_list("JIRA_SITES");
+        let jira = if site_names.is_empty() {
+            jira_site(None)?.into_iter().collect()
+        } else {
+            let mut sites = Vec::new();
+            for name in &site_names {
+                sites.extend(jira_site(Some(name))?);
             }
+            sites
+        };
+
+        let reddit = if let (Ok(client_id), Ok(client_secret), Ok(refresh_token)) = (
+            env::var("REDDIT_CLIENT_ID"),
+            env::var("REDDIT_CLIENT_SECRET"),
+            env::var("REDDIT_REFRESH_TOKEN"),
+        ) {
+            Some(RedditConfig {
+                client_id,
+                client_secret: secrets::resolve(client_secret)?,
+                refresh_token: secrets::resolve(refresh_token)?,
+                fetch_timeout_secs: fetch_timeout_secs("REDDIT"),
+            })
         } else {
             None
         };
 
+        let xmpp = if let (Ok(jid), Ok(password)) = (env::var("XMPP_JID"), env::var("XMPP_PASSWORD")) {
+            let jid = jid.trim().to_string();
+            let nickname = env::var("XMPP_NICKNAME")
+                .ok()
+                .filter(|nick| !nick.trim().is_empty())
+                .unwrap_or_else(|| jid.split('@').next().unwrap_or(&jid).to_string());
+            Some(XmppConfig {
+                password: secrets::resolve(password)?,
+                server: env::var("XMPP_SERVER").ok().filter(|url| !url.is_empty()),
+                rooms: env_list("XMPP_ROOMS"),
+                contacts: env_list("XMPP_CONTACTS"),
+                nickname,
+                fetch_timeout_secs: fetch_timeout_secs("XMPP"),
+                jid,
+            })
+        } else {
+            None
+        };
+
+        let teams = if let (Ok(client_id), Ok(refresh_token)) = (env::var("TEAMS_CLIENT_ID"), env::var("TEAMS_REFRESH_TOKEN")) {
+            let client_secret = match env::var("TEAMS_CLIENT_SECRET").ok().filter(|secret| !secret.is_empty()) {
+                Some(secret) => Some(secrets::resolve(secret)?),
+                None => None,
+            };
+            Some(TeamsConfig {
+                tenant_id: env::var("TEAMS_TENANT_ID").ok().filter(|id| !id.is_empty()).unwrap_or_else(|| "common".to_string()),
+                client_id,
+                client_secret,
+                refresh_token: secrets::resolve(refresh_token)?,
+                channels: env_list("TEAMS_CHANNELS"),
+                include_chats: env_flag("TEAMS_CHATS", true),
+                fetch_timeout_secs: fetch_timeout_secs("TEAMS"),
+            })
+        } else {
+            None
+        };
+
+        let twilio = if let (Ok(account_sid), Ok(auth_token), Ok(from_number)) = (
+            env::var("TWILIO_ACCOUNT_SID"),
+            env::var("TWILIO_AUTH_TOKEN"),
+            env::var("TWILIO_FROM_NUMBER"),
+        ) {
+            Some(TwilioConfig {
+                account_sid,
+                auth_token: secrets::resolve(auth_token)?,
+                from_number: from_number.trim().to_string(),
+                fetch_timeout_secs: fetch_timeout_secs("SMS"),
+            })
+        } else {
+            None
+        };
+
+        let rest = match env::var("REST_PROVIDERS_FILE") {
+            Ok(path) if !path.is_empty() => load_rest_providers(&path)?,
+            _ => Vec::new(),
+        };
+
         let message_limit = env::var("MESSAGE_LIMIT")
             .ok()
             .and_then(|s| s.parse::<usize>().ok())
             .unwrap_or(100); // Default to 100 messages
 
+        let source_limits = SOURCE_PREFIXES
+            .into_iter()
+            .filter_map(|(prefix, source)| {
+                let limit = env::var(format!("{}_MESSAGE_LIMIT", prefix)).ok()?.trim().parse::<usize>().ok()?;
+                Some((source, limit))
+            })
+            .collect();
+
+        let refresh_interval_secs = env::var("REFRESH_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|&secs| secs > 0)
+            .unwrap_or(30); // Default to refreshing every 30 seconds
+
+        let render_markdown = env_flag("RENDER_MARKDOWN", true);
+        let render_shortcodes = env_flag("EMOJI_SHORTCODES", true);
+        let group_by_author = env_flag("GROUP_BY_AUTHOR", false);
+        let wrap_list = env_flag("LIST_WRAP", false);
+        let mark_read_on_select = env_flag("MARK_READ_ON_SELECT", true);
+        let confirm_send = env_flag("CONFIRM_SEND", false);
+
+        // THEME fills in every color; the individual variables still win
+        let theme = ColorConfig::preset(&env::var("THEME").unwrap_or_default().trim().to_lowercase());
         let colors = ColorConfig {
-            selected_bg: env::var("SELECTED_BG_COLOR").ok(),
-            selected_fg: env::var("SELECTED_FG_COLOR").ok(),
-            input_active: env::var("INPUT_ACTIVE_COLOR").ok(),
-            input_inactive: env::var("INPUT_INACTIVE_COLOR").ok(),
+            selected_bg: env::var("SELECTED_BG_COLOR").ok().or(theme.selected_bg),
+            selected_fg: env::var("SELECTED_FG_COLOR").ok().or(theme.selected_fg),
+            input_active: env::var("INPUT_ACTIVE_COLOR").ok().or(theme.input_active),
+            input_inactive: env::var("INPUT_INACTIVE_COLOR").ok().or(theme.input_inactive),
+            author_colors: env_flag("AUTHOR_COLORS", theme.author_colors),
+            author_palette: env::var("AUTHOR_PALETTE")
+                .ok()
+                .map(|s| {
+                    s.split(',')
+                        .map(|c| c.trim().to_string())
+                        .filter(|c| !c.is_empty())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|palette| !palette.is_empty())
+                .or(theme.author_palette),
+            alert_bg: env::var("ALERT_BG_COLOR").ok().or(theme.alert_bg),
+            telegram_color: env::var("TELEGRAM_COLOR").ok().or(theme.telegram_color),
+            discord_color: env::var("DISCORD_COLOR").ok().or(theme.discord_color),
+            github_color: env::var("GITHUB_COLOR").ok().or(theme.github_color),
+            jira_color: env::var("JIRA_COLOR").ok().or(theme.jira_color),
+            reddit_color: env::var("REDDIT_COLOR").ok().or(theme.reddit_color),
+            xmpp_color: env::var("XMPP_COLOR").ok().or(theme.xmpp_color),
+            teams_color: env::var("TEAMS_COLOR").ok().or(theme.teams_color),
+            sms_color: env::var("SMS_COLOR").ok().or(theme.sms_color),
+            rest_color: env::var("REST_COLOR").ok().or(theme.rest_color),
+        };
+
+        // ASCII_ICONS swaps the emoji defaults for labels any font can draw
+        let ascii_icons = env_flag("ASCII_ICONS", false);
+        let icon = |name: &str, emoji: &str, ascii: String| {
+            env::var(name).unwrap_or_else(|_| if ascii_icons { ascii } else { emoji.to_string() })
+        };
+        let ascii = IconConfig::ascii();
+        let icons = IconConfig {
+            telegram: icon("TELEGRAM_ICON", "✈️", ascii.telegram),
+            discord: icon("DISCORD_ICON", "🎮", ascii.discord),
+            github: icon("GITHUB_ICON", "🐙", ascii.github),
+            jira: icon("JIRA_ICON", "📋", ascii.jira),
+            reddit: icon("REDDIT_ICON", "👽", ascii.reddit),
+            xmpp: icon("XMPP_ICON", "💬", ascii.xmpp),
+            teams: icon("TEAMS_ICON", "👥", ascii.teams),
+            sms: icon("SMS_ICON", "📱", ascii.sms),
+            rest: icon("REST_ICON", "🔗", ascii.rest),
+        };
+
+        let time_format = env::var("TIME_FORMAT").unwrap_or_else(|_| {
+            if env_flag("CLOCK_12H", false) { "%-I:%M %p" } else { "%H:%M" }.to_string()
+        });
+        let date_format = env::var("DATE_FORMAT").unwrap_or_else(|_| "%Y-%m-%d".to_string());
+        validate_time_format("TIME_FORMAT", &time_format)?;
+        validate_time_format("DATE_FORMAT", &date_format)?;
+
+        let time = TimeConfig {
+            time_format,
+            date_format,
+            use_local_time: env_flag("USE_LOCAL_TIME", false),
+        };
+
+        let layout = LayoutConfig {
+            side_by_side: env::var("LAYOUT").is_ok_and(|l| l.trim().eq_ignore_ascii_case("side-by-side")),
+            list_percent: env::var("LIST_PERCENT")
+                .ok()
+                .and_then(|s| s.trim().parse::<u16>().ok())
+                .unwrap_or(50)
+                .clamp(10, 90),
         };
 
-        Ok(Config {
+        let passphrase = env::var("DB_PASSPHRASE").ok().filter(|p| !p.is_empty()).map(secrets::resolve).transpose()?;
+        let database = DatabaseConfig {
+            path: env::var("DATABASE_PATH")
+                .ok()
+                .filter(|p| !p.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(crate::database::default_database_path),
+            encrypt: env_flag("DB_ENCRYPT", passphrase.is_some()),
+            passphrase,
+            max_cached_messages: env::var("MAX_CACHED_MESSAGES").ok().and_then(|s| s.trim().parse::<usize>().ok()),
+            source_max_cached_messages: SOURCE_PREFIXES
+                .into_iter()
+                .filter_map(|(prefix, source)| {
+                    let max = env::var(format!("{}_MAX_CACHED_MESSAGES", prefix)).ok()?.trim().parse::<usize>().ok()?;
+                    Some((source, max))
+                })
+                .collect(),
+        };
+
+        let spellcheck = env_flag("SPELLCHECK", false).then(|| SpellcheckConfig {
+            language: env::var("SPELLCHECK_LANGUAGE").ok().filter(|l| !l.is_empty()).unwrap_or_else(|| "en_US".to_string()),
+            dictionary: env::var("SPELLCHECK_DICTIONARY").ok().filter(|p| !p.is_empty()).map(PathBuf::from),
+        });
+
+        let alerts = AlertConfig {
+            keywords: env::var("ALERT_KEYWORDS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|k| k.trim().to_lowercase())
+                        .filter(|k| !k.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            bell: env_flag("ALERT_BELL", true),
+            bypass_dnd: env_list("ALERT_BYPASS_DND").into_iter().map(|k| k.to_lowercase()).collect(),
+            snooze: std::time::Duration::from_secs(
+                60 * env::var("SNOOZE_MINUTES").ok().and_then(|s| s.trim().parse::<u64>().ok()).filter(|&m| m > 0).unwrap_or(60),
+            ),
+        };
+
+        // MUTED_CHANNELS entries look like `discord:123456`
+        let mut mutes = MuteConfig::default();
+        for entry in env::var("MUTED_CHANNELS").unwrap_or_default().split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (source, channel) = entry
+                .split_once(':')
+                .and_then(|(source, channel)| Some((MessageSource::from_name(source)?, channel.trim())))
+                .ok_or_else(|| format!("MUTED_CHANNELS entry {:?} should look like discord:<channel id>", entry))?;
+            mutes.channels.push((source, channel.to_string()));
+        }
+        mutes.authors = env::var("MUTED_AUTHORS")
+            .map(|s| {
+                s.split(',')
+                    .map(|a| a.trim().to_lowercase())
+                    .filter(|a| !a.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let ignored_authors = env::var("IGNORED_AUTHORS")
+            .map(|s| {
+                s.split(',')
+                    .map(|a| a.trim().to_lowercase())
+                    .filter(|a| !a.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut config = Config {
             telegram,
             discord,
             github,
             jira,
+            reddit,
+            xmpp,
+            teams,
+            twilio,
+            rest,
             message_limit,
+            source_limits,
+            refresh_interval_secs,
+            render_markdown,
+            render_shortcodes,
+            group_by_author,
+            wrap_list,
+            mark_read_on_select,
+            confirm_send,
             colors,
-        })
+            icons,
+            time,
+            layout,
+            database,
+            alerts,
+            spellcheck,
+            mutes,
+            ignored_authors,
+            proxy_url: env::var("PROXY_URL").ok().filter(|url| !url.is_empty()),
+            connectivity_probe: env::var("CONNECTIVITY_PROBE")
+                .ok()
+                .map(|address| address.trim().to_string())
+                .filter(|address| !matches!(address.as_str(), "" | "off" | "false")),
+            plain: false,
+            user_agent: env::var("USER_AGENT")
+                .ok()
+                .filter(|agent| !agent.is_empty())
+                .unwrap_or_else(|| format!("friend/{}", env!("CARGO_PKG_VERSION"))),
+            download_dir: env::var("DOWNLOAD_DIR")
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| expand_path(&dir))
+                .unwrap_or_else(default_download_dir),
+        };
+        if env_flag("PLAIN", false) {
+            config.use_plain_output();
+        }
+        Ok(config)
+    }
+
+    /// Switches to plain output (`PLAIN=true` or `--plain`): ASCII labels
+    /// instead of emoji icons and shortcodes, and no styling when drawn
+    pub fn use_plain_output(&mut self) {
+        self.plain = true;
+        self.icons = IconConfig::ascii();
+        self.render_shortcodes = false;
+    }
+
+    /// Explains why a provider the user started configuring was left out,
+    /// and flags settings that were ignored because they didn't parse.
+    /// `from_env` is lenient, so without this a typo just looks like the
+    /// provider isn't configured.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let account_names = env_list("TELEGRAM_ACCOUNTS");
+        if self.telegram.is_empty() && account_names.is_empty() {
+            check_group(&mut problems, "Telegram", &["TELEGRAM_API_ID", "TELEGRAM_API_HASH", "TELEGRAM_PHONE"]);
+        }
+        for name in &account_names {
+            if !self.telegram.iter().any(|account| account.name.as_ref() == Some(name)) {
+                let prefix = format!("TELEGRAM_{}", name.to_uppercase());
+                problems.push(format!(
+                    "Telegram account {:?} is missing settings: it needs {}_PHONE, plus {}_API_ID and {}_API_HASH unless TELEGRAM_API_ID and TELEGRAM_API_HASH are set",
+                    name, prefix, prefix, prefix
+                ));
+            }
+        }
+        if self.telegram.is_empty()
+            && let Ok(api_id) = env::var("TELEGRAM_API_ID")
+            && api_id.parse::<i32>().is_err()
+        {
+            problems.push(format!("TELEGRAM_API_ID is set but not a valid integer: {:?}", api_id));
+        }
+
+        if self.discord.is_none() {
+            if env::var("DISCORD_USER_TOKEN").is_ok() {
+                problems.push("DISCORD_USER_TOKEN is set but there is nothing to follow: set DISCORD_CHANNEL_IDS, DISCORD_GUILD_IDS or DISCORD_DMS=true".to_string());
+            } else if let Some(name) = ["DISCORD_CHANNEL_IDS", "DISCORD_GUILD_IDS", "DISCORD_DMS"].into_iter().find(|name| env::var(name).is_ok()) {
+                problems.push(format!("Discord is partly configured: {} set but DISCORD_USER_TOKEN missing", name));
+            }
+        }
+
+        if self.github.is_none() {
+            check_group(&mut problems, "GitHub", &["GITHUB_TOKEN", "GITHUB_USERNAME"]);
+        }
+
+        if self.reddit.is_none() {
+            check_group(&mut problems, "Reddit", &["REDDIT_CLIENT_ID", "REDDIT_CLIENT_SECRET", "REDDIT_REFRESH_TOKEN"]);
+        }
+
+        if self.xmpp.is_none() {
+            check_group(&mut problems, "XMPP", &["XMPP_JID", "XMPP_PASSWORD"]);
+        }
+        if let Some(xmpp) = &self.xmpp {
+            if !xmpp.jid.contains('@') {
+                problems.push(format!("XMPP_JID should look like name@example.org: {:?}", xmpp.jid));
+            }
+            if let Some(server) = &xmpp.server
+                && !server.starts_with("wss://")
+            {
+                problems.push(format!("XMPP_SERVER must be a secure WebSocket URL (wss://...), the password is sent in the clear otherwise: {:?}", server));
+            }
+        }
+
+        if self.teams.is_none() {
+            check_group(&mut problems, "Teams", &["TEAMS_CLIENT_ID", "TEAMS_REFRESH_TOKEN"]);
+        }
+        if let Some(teams) = &self.teams {
+            for channel in teams.channels.iter().filter(|channel| !channel.contains('/')) {
+                problems.push(format!("TEAMS_CHANNELS entries must be <team id>/<channel id>: {:?}", channel));
+            }
+            if teams.channels.is_empty() && !teams.include_chats {
+                problems.push("Teams has nothing to follow: set TEAMS_CHANNELS or TEAMS_CHATS=true".to_string());
+            }
+        }
+
+        if self.twilio.is_none() {
+            check_group(&mut problems, "Twilio", &["TWILIO_ACCOUNT_SID", "TWILIO_AUTH_TOKEN", "TWILIO_FROM_NUMBER"]);
+        }
+        if let Some(twilio) = &self.twilio
+            && !twilio.from_number.starts_with('+')
+        {
+            problems.push(format!("TWILIO_FROM_NUMBER should be in +<country code><number> form: {:?}", twilio.from_number));
+        }
+
+        let site_names = env_list("JIRA_SITES");
+        if self.jira.is_empty() && site_names.is_empty() {
+            let jira_vars = ["JIRA_BASE_URL", "JIRA_EMAIL", "JIRA_API_TOKEN", "JIRA_PROJECT_KEY"];
+            check_group(&mut problems, "Jira", &jira_vars);
+            if jira_vars.iter().all(|name| env::var(name).is_ok()) {
+                problems.push("JIRA_BASE_URL is set but JIRA_PROJECT_KEY has no project keys".to_string());
+            }
+        }
+        for name in &site_names {
+            if !self.jira.iter().any(|site| site.name.as_ref() == Some(name)) {
+                let prefix = format!("JIRA_{}", name.to_uppercase());
+                problems.push(format!(
+                    "Jira site {:?} is missing settings: it needs {}_BASE_URL and {}_PROJECT_KEY, plus {}_EMAIL and {}_API_TOKEN unless JIRA_EMAIL and JIRA_API_TOKEN are set",
+                    name, prefix, prefix, prefix, prefix
+                ));
+            }
+        }
+
+        if let Ok(theme) = env::var("THEME")
+            && !theme.trim().is_empty()
+            && !THEMES.contains(&theme.trim().to_lowercase().as_str())
+        {
+            problems.push(format!("THEME must be one of {}: {:?}", THEMES.join(", "), theme));
+        }
+
+        if let Some(url) = &self.proxy_url
+            && let Err(e) = reqwest::Proxy::all(url)
+        {
+            problems.push(format!("PROXY_URL is not a valid proxy URL ({}): {:?}", e, url));
+        }
+        if let Ok(url) = env::var("TELEGRAM_PROXY_URL")
+            && !url.starts_with("socks5://")
+        {
+            problems.push(format!("TELEGRAM_PROXY_URL must be a socks5:// URL: {:?}", url));
+        }
+
+        if let Some(address) = &self.connectivity_probe
+            && address.rsplit_once(':').is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err())
+        {
+            problems.push(format!("CONNECTIVITY_PROBE should be host:port or off: {:?}", address));
+        }
+
+        if self.download_dir.exists() && !self.download_dir.is_dir() {
+            problems.push(format!("DOWNLOAD_DIR is not a directory: {}", self.download_dir.display()));
+        }
+
+        if let Ok(layout) = env::var("LAYOUT")
+            && !matches!(layout.trim().to_lowercase().as_str(), "stacked" | "side-by-side")
+        {
+            problems.push(format!("LAYOUT should be stacked or side-by-side: {:?}", layout));
+        }
+
+        let per_source = SOURCE_PREFIXES.iter().map(|(prefix, _)| format!("{}_MAX_CACHED_MESSAGES", prefix));
+        let numeric = ["MESSAGE_LIMIT", "TELEGRAM_MESSAGE_LIMIT", "DISCORD_MESSAGE_LIMIT", "GITHUB_MESSAGE_LIMIT", "JIRA_MESSAGE_LIMIT", "REDDIT_MESSAGE_LIMIT", "XMPP_MESSAGE_LIMIT", "TEAMS_MESSAGE_LIMIT", "SMS_MESSAGE_LIMIT", "REST_MESSAGE_LIMIT", "MAX_CACHED_MESSAGES", "REFRESH_INTERVAL_SECS", "SNOOZE_MINUTES", "LIST_PERCENT", "TELEGRAM_DIALOG_LIMIT", "TELEGRAM_MESSAGES_PER_CHAT", "TELEGRAM_MESSAGES_PER_CHANNEL", "FETCH_TIMEOUT_SECS"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(per_source);
+        for name in numeric {
+            if let Ok(value) = env::var(&name)
+                && value.trim().parse::<u64>().is_err()
+            {
+                problems.push(format!("{} is set but not a valid number: {:?}", name, value));
+            }
+        }
+
+        problems
+    }
+
+    /// Settings that are wrong but have a safe fallback, so startup goes on.
+    /// Unknown colors are shown in the terminal's default color.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        let color_vars = ["SELECTED_BG_COLOR", "SELECTED_FG_COLOR", "INPUT_ACTIVE_COLOR", "INPUT_INACTIVE_COLOR", "ALERT_BG_COLOR"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(SOURCE_PREFIXES.iter().map(|(prefix, _)| format!("{}_COLOR", prefix)));
+        let mut unknown_colors: Vec<String> = color_vars
+            .filter_map(|name| env::var(&name).ok().map(|value| (name, value)))
+            .filter(|(_, value)| crate::try_parse_color(value).is_none())
+            .map(|(name, value)| format!("{}={:?}", name, value))
+            .collect();
+        if let Ok(palette) = env::var("AUTHOR_PALETTE") {
+            unknown_colors.extend(
+                palette
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|color| !color.is_empty() && crate::try_parse_color(color).is_none())
+                    .map(|color| format!("AUTHOR_PALETTE {:?}", color)),
+            );
+        }
+        if !unknown_colors.is_empty() {
+            warnings.push(format!(
+                "Unknown colors: {}, using the default instead. Use one of {}, #rrggbb or an xterm-256 index (0-255)",
+                unknown_colors.join(", "),
+                crate::COLOR_NAMES.join(", ")
+            ));
+        }
+
+        warnings
     }
 
     pub fn has_any_provider(&self) -> bool {
-        self.telegram.is_some() || self.discord.is_some() || self.github.is_some() || self.jira.is_some()
+        !self.telegram.is_empty() || self.discord.is_some() || self.github.is_some() || !self.jira.is_empty() || self.reddit.is_some() || self.xmpp.is_some() || self.teams.is_some() || self.twilio.is_some() || !self.rest.is_empty()
     }
-}
\ No newline at end of file
+}
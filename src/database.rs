@@ -1,176 +1,658 @@
-use sqlx::{SqlitePool, Row};
+use sqlx::sqlite::{SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous};
+use sqlx::{SqliteConnection, SqlitePool, Row};
+use futures::future::BoxFuture;
+use futures::TryStreamExt;
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use crate::{Message, MessageSource, Attachment, AttachmentType};
+use crate::{Message, MessageSource, Attachment, AttachmentType, Reaction, SendStatus};
+use crate::search::{Query, Term};
 
+/// Columns `message_from_row` expects when reading from `messages`
+const MESSAGE_COLUMNS: &str = "id, source, content, timestamp, author, channel_id, reply_to_id, edited, send_status";
+
+/// A message from a row selected with `MESSAGE_COLUMNS`, without its
+/// attachments and reactions. `None` for a source this build doesn't know.
+fn message_from_row(row: &SqliteRow) -> Option<Message> {
+    let send_status = match row.get::<Option<String>, _>("send_status").as_deref() {
+        Some("Pending") => Some(SendStatus::Pending),
+        Some("Sent") => Some(SendStatus::Sent),
+        Some("Failed") => Some(SendStatus::Failed),
+        _ => None,
+    };
+
+    Some(Message {
+        id: row.get::<i64, _>("id") as u64,
+        source: MessageSource::from_name(&row.get::<String, _>("source"))?,
+        content: row.get("content"),
+        timestamp: row.get("timestamp"),
+        author: row.get("author"),
+        attachments: vec![],
+        reactions: vec![],
+        channel_id: row.get("channel_id"),
+        reply_to_id: row.get::<Option<i64>, _>("reply_to_id").map(|id| id as u64),
+        edited: row.get("edited"),
+        send_status,
+    })
+}
+
+#[derive(Clone)]
 pub struct MessageCache {
     pool: SqlitePool,
+    /// Most messages to keep in total, if capped
+    max_messages: Option<usize>,
+    /// Most messages to keep per source
+    source_max_messages: HashMap<MessageSource, usize>,
 }
 
-impl MessageCache {
-    pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
-        // Connect to SQLite database (will create file if it doesn't exist)
-        let options = sqlx::sqlite::SqliteConnectOptions::from_str(database_url)?
-            .create_if_missing(true);
-        let pool = SqlitePool::connect_with(options).await?;
-        
-        // Create tables if they don't exist
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS messages (
-                id INTEGER PRIMARY KEY,
-                source TEXT NOT NULL,
-                content TEXT NOT NULL,
-                timestamp DATETIME NOT NULL,
-                author TEXT NOT NULL,
-                channel_id TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            )
-            "#,
-        )
-        .execute(&pool)
+/// An outgoing message waiting in the send queue
+#[derive(Debug, Clone)]
+pub struct QueuedSend {
+    pub id: i64,
+    pub provider_key: String,
+    pub channel_id: Option<String>,
+    pub content: String,
+    pub attempts: i64,
+}
+
+pub fn default_database_path() -> PathBuf {
+    crate::config::data_dir().join("messages.db")
+}
+
+/// Creates the cache's parent directories and returns its connection URL.
+/// A path of `:memory:` gives a throwaway cache that is never written to disk.
+pub fn database_url(path: &Path) -> std::io::Result<String> {
+    if path == Path::new(":memory:") {
+        return Ok("sqlite::memory:".to_string());
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(format!("sqlite://{}", path.to_string_lossy()))
+}
+
+/// Adds a column to an existing table. Databases from before schema
+/// versioning may already have it, so this checks first.
+async fn add_column_if_missing(conn: &mut SqliteConnection, table: &str, column: &str, definition: &str) -> Result<(), sqlx::Error> {
+    let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(&mut *conn)
         .await?;
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS attachments (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                message_id INTEGER NOT NULL,
-                filename TEXT NOT NULL,
-                url TEXT NOT NULL,
-                file_type TEXT NOT NULL,
-                size INTEGER,
-                FOREIGN KEY (message_id) REFERENCES messages (id)
-            )
-            "#,
+    if !columns.iter().any(|row| row.get::<String, _>("name") == column) {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// One step of the schema's history. Steps run in order, each in its own
+/// transaction, and the database's `user_version` records how many have
+/// been applied. Databases from before versioning start at 0 with some
+/// steps already in place, so every step must be safe to repeat.
+type Migration = for<'c> fn(&'c mut SqliteConnection) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+
+/// Append new steps at the end; never reorder or edit released ones
+const MIGRATIONS: &[Migration] = &[
+    |conn| Box::pin(create_initial_schema(conn)),
+    |conn| Box::pin(add_column_if_missing(conn, "messages", "reply_to_id", "INTEGER")),
+    |conn| Box::pin(add_column_if_missing(conn, "attachments", "local_path", "TEXT")),
+    |conn| Box::pin(add_column_if_missing(conn, "messages", "edited", "INTEGER NOT NULL DEFAULT 0")),
+    |conn| Box::pin(create_drafts(conn)),
+    |conn| Box::pin(add_column_if_missing(conn, "messages", "send_status", "TEXT")),
+    |conn| Box::pin(forget_messages(conn, "Telegram", "telegram")),
+    |conn| Box::pin(forget_messages(conn, "Jira", "jira")),
+];
+
+/// Brings the schema up to date, applying the steps the database hasn't
+/// seen yet
+async fn migrate(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(pool).await?;
+    let applied = usize::try_from(version).unwrap_or(0);
+    if applied > MIGRATIONS.len() {
+        return Err(sqlx::Error::Configuration(
+            format!("the message cache was created by a newer version of friend (schema {}, this build knows {}); delete it or upgrade", version, MIGRATIONS.len()).into(),
+        ));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let mut tx = pool.begin().await?;
+        migration(&mut tx).await?;
+        sqlx::query(&format!("PRAGMA user_version = {}", index + 1))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+/// Half-typed messages, one per destination. A missing channel is stored
+/// as '' so it can be part of the key.
+async fn create_drafts(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS drafts (
+            provider_key TEXT NOT NULL,
+            channel_id TEXT NOT NULL DEFAULT '',
+            content TEXT NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (provider_key, channel_id)
         )
-        .execute(&pool)
-        .await?;
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS sync_state (
-                provider_key TEXT PRIMARY KEY,
-                last_message_id INTEGER,
-                last_sync DATETIME DEFAULT CURRENT_TIMESTAMP
-            )
-            "#,
+/// Drops what's cached for `source` after its ids changed shape (Telegram
+/// ids became scoped to the account and chat, Jira ids to the site), along
+/// with what's keyed by them, so it's fetched again. Bookmarks keep their
+/// own copy and stay.
+async fn forget_messages(conn: &mut SqliteConnection, source: &str, provider_prefix: &str) -> Result<(), sqlx::Error> {
+    for statement in [
+        "DELETE FROM attachments WHERE message_id IN (SELECT id FROM messages WHERE source = ?)",
+        "DELETE FROM reactions WHERE message_id IN (SELECT id FROM messages WHERE source = ?)",
+        "DELETE FROM messages WHERE source = ?",
+        "DELETE FROM read_messages WHERE source = ?",
+        "DELETE FROM thread_replies WHERE source = ?",
+    ] {
+        sqlx::query(statement).bind(source).execute(&mut *conn).await?;
+    }
+    for statement in [
+        "DELETE FROM sync_state WHERE provider_key LIKE ?",
+        "DELETE FROM sync_timestamps WHERE provider_key LIKE ?",
+    ] {
+        sqlx::query(statement).bind(format!("{}_%", provider_prefix)).execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+/// Every table as of when versioning was introduced. Columns added since
+/// then are later steps, so fresh and old databases take the same path.
+async fn create_initial_schema(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS messages (
+            id INTEGER PRIMARY KEY,
+            source TEXT NOT NULL,
+            content TEXT NOT NULL,
+            timestamp DATETIME NOT NULL,
+            author TEXT NOT NULL,
+            channel_id TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS attachments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            message_id INTEGER NOT NULL,
+            filename TEXT NOT NULL,
+            url TEXT NOT NULL,
+            file_type TEXT NOT NULL,
+            size INTEGER,
+            FOREIGN KEY (message_id) REFERENCES messages (id)
         )
-        .execute(&pool)
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS reactions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            message_id INTEGER NOT NULL,
+            emoji TEXT NOT NULL,
+            count INTEGER NOT NULL,
+            FOREIGN KEY (message_id) REFERENCES messages (id)
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS sync_state (
+            provider_key TEXT PRIMARY KEY,
+            last_message_id INTEGER,
+            last_sync DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS sync_timestamps (
+            provider_key TEXT PRIMARY KEY,
+            last_timestamp DATETIME NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS pending_sends (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            provider_key TEXT NOT NULL,
+            source TEXT NOT NULL,
+            channel_id TEXT,
+            content TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS mutes (
+            kind TEXT NOT NULL,
+            source TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (kind, source, value)
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Bookmarks keep their own copy of the message so they outlive cache pruning
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS bookmarks (
+            source TEXT NOT NULL,
+            message_id INTEGER NOT NULL,
+            channel_id TEXT,
+            content TEXT NOT NULL,
+            author TEXT NOT NULL,
+            timestamp DATETIME NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (source, message_id)
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Kept apart from `messages` so refetching a message doesn't reset it
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS read_messages (
+            source TEXT NOT NULL,
+            message_id INTEGER NOT NULL,
+            PRIMARY KEY (source, message_id)
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Replies fetched on demand for a message (e.g. Jira comments), kept
+    // apart so they don't show up as top-level messages
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS thread_replies (
+            source TEXT NOT NULL,
+            parent_id INTEGER NOT NULL,
+            id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            author TEXT NOT NULL,
+            timestamp DATETIME NOT NULL,
+            channel_id TEXT,
+            PRIMARY KEY (source, parent_id, id)
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_timestamp ON messages(timestamp)")
+        .execute(&mut *conn)
+        .await?;
+    
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_source ON messages(source)")
+        .execute(&mut *conn)
         .await?;
 
-        // Create indexes for better query performance
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_timestamp ON messages(timestamp)")
-            .execute(&pool)
-            .await?;
+    Ok(())
+}
+
+impl MessageCache {
+    /// Opens (creating if needed) the cache. With a passphrase the database
+    /// is keyed through SQLCipher before any table is touched.
+    pub async fn new(database_url: &str, passphrase: Option<&str>) -> Result<Self, sqlx::Error> {
+        // Connect to SQLite database (will create file if it doesn't exist)
+        let mut options = sqlx::sqlite::SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            // WAL lets the UI keep reading while a refresh writes, and NORMAL
+            // sync is safe under WAL without an fsync per transaction
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal);
+
+        if let Some(passphrase) = passphrase {
+            // Plain SQLite silently ignores `PRAGMA key`, which would leave
+            // the cache unencrypted while the user believes otherwise
+            if !cfg!(feature = "sqlcipher") {
+                return Err(sqlx::Error::Configuration(
+                    "database encryption requires building with --features sqlcipher".into(),
+                ));
+            }
+            // sqlx always issues `key` before any other pragma
+            options = options.pragma("key", format!("'{}'", passphrase.replace('\'', "''")));
+        }
+        // An in-memory database lives only as long as its connections, so
+        // keep a single one open for the lifetime of the pool
+        let pool = if database_url.contains(":memory:") || database_url.contains("mode=memory") {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect_with(options)
+                .await?
+        } else {
+            SqlitePool::connect_with(options).await?
+        };
         
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_source ON messages(source)")
-            .execute(&pool)
-            .await?;
+        migrate(&pool).await?;
+
+        Ok(Self {
+            pool,
+            max_messages: None,
+            source_max_messages: HashMap::new(),
+        })
+    }
 
-        Ok(Self { pool })
+    /// Caps how many messages are kept. Every `cache_messages` afterwards
+    /// deletes the oldest beyond the caps.
+    pub fn set_retention(&mut self, max_messages: Option<usize>, source_max_messages: HashMap<MessageSource, usize>) {
+        self.max_messages = max_messages;
+        self.source_max_messages = source_max_messages;
+    }
+
+    /// Deletes the oldest messages beyond the retention caps, along with
+    /// what hangs off them. Bookmarks keep their own copy and stay.
+    async fn enforce_retention(&self, conn: &mut SqliteConnection) -> Result<u64, sqlx::Error> {
+        // Per-source caps first, so the global cap counts what they left
+        let caps = self.source_max_messages
+            .iter()
+            .map(|(source, max)| (Some(format!("{:?}", source)), *max))
+            .chain(self.max_messages.map(|max| (None, max)));
+
+        let mut deleted = 0;
+        for (source, max) in caps {
+            let beyond_cap = match source {
+                Some(_) => "SELECT id FROM messages WHERE source = ? AND id NOT IN (SELECT id FROM messages WHERE source = ? ORDER BY timestamp DESC LIMIT ?)",
+                None => "SELECT id FROM messages WHERE id NOT IN (SELECT id FROM messages ORDER BY timestamp DESC LIMIT ?)",
+            };
+            // Attachments and reactions reference the message, so they go first
+            for table in ["attachments", "reactions", "messages"] {
+                let column = if table == "messages" { "id" } else { "message_id" };
+                let statement = format!("DELETE FROM {} WHERE {} IN ({})", table, column, beyond_cap);
+                let mut query = sqlx::query(&statement);
+                if let Some(source) = &source {
+                    query = query.bind(source).bind(source);
+                }
+                let removed = query.bind(max as i64).execute(&mut *conn).await?.rows_affected();
+                if table == "messages" {
+                    deleted += removed;
+                }
+            }
+        }
+
+        if deleted > 0 {
+            sqlx::query("DELETE FROM thread_replies WHERE parent_id NOT IN (SELECT id FROM messages)")
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query("DELETE FROM read_messages WHERE message_id NOT IN (SELECT id FROM messages)")
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(deleted)
     }
 
     pub async fn get_cached_messages(&self, limit: Option<usize>) -> Result<Vec<Message>, sqlx::Error> {
         let limit_clause = limit.map(|l| format!("LIMIT {}", l)).unwrap_or_default();
         
         let query = format!(
-            "SELECT id, source, content, timestamp, author, channel_id FROM messages ORDER BY timestamp DESC {}",
-            limit_clause
+            "SELECT {} FROM messages ORDER BY timestamp DESC {}",
+            MESSAGE_COLUMNS, limit_clause
         );
         
         let rows = sqlx::query(&query).fetch_all(&self.pool).await?;
-        
-        let mut messages = Vec::new();
-        for row in rows {
-            let message_id: i64 = row.get("id");
-            let source_str: String = row.get("source");
-            let content: String = row.get("content");
-            let timestamp: DateTime<Utc> = row.get("timestamp");
-            let author: String = row.get("author");
-            let channel_id: Option<String> = row.get("channel_id");
-
-            let source = match source_str.as_str() {
-                "Telegram" => MessageSource::Telegram,
-                "Discord" => MessageSource::Discord,
-                "Github" => MessageSource::Github,
-                "Jira" => MessageSource::Jira,
-                _ => continue,
-            };
+        self.hydrate_messages(rows).await
+    }
 
-            // Get attachments for this message
-            let attachment_rows = sqlx::query(
-                "SELECT filename, url, file_type, size FROM attachments WHERE message_id = ?"
-            )
-            .bind(message_id)
-            .fetch_all(&self.pool)
-            .await?;
+    /// Newest-first page of cached messages, skipping the first `offset`
+    pub async fn get_cached_messages_paged(&self, limit: usize, offset: usize) -> Result<Vec<Message>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM messages ORDER BY timestamp DESC LIMIT ? OFFSET ?",
+            MESSAGE_COLUMNS
+        ))
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.pool)
+        .await?;
 
-            let attachments: Vec<Attachment> = attachment_rows
-                .into_iter()
-                .map(|row| {
-                    let file_type_str: String = row.get("file_type");
-                    let file_type = match file_type_str.as_str() {
-                        "Image" => AttachmentType::Image,
-                        "Video" => AttachmentType::Video,
-                        "Audio" => AttachmentType::Audio,
-                        "Document" => AttachmentType::Document,
-                        _ => AttachmentType::Other,
-                    };
-
-                    Attachment {
-                        filename: row.get("filename"),
-                        url: row.get("url"),
-                        file_type,
-                        size: row.get("size"),
-                    }
-                })
-                .collect();
+        self.hydrate_messages(rows).await
+    }
 
-            messages.push(Message {
-                id: message_id as u64,
-                source,
-                content,
-                timestamp,
-                author,
-                attachments,
-                channel_id,
-            });
+    /// Number of cached messages, from one source or all of them
+    pub async fn count_messages(&self, source: Option<MessageSource>) -> Result<usize, sqlx::Error> {
+        let row = match source {
+            Some(source) => {
+                sqlx::query("SELECT COUNT(*) AS count FROM messages WHERE source = ?")
+                    .bind(format!("{:?}", source))
+                    .fetch_one(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query("SELECT COUNT(*) AS count FROM messages")
+                    .fetch_one(&self.pool)
+                    .await?
+            }
+        };
+
+        Ok(row.get::<i64, _>("count") as usize)
+    }
+
+    /// Newest-first cached messages from one channel of a source, all of
+    /// them when `limit` is `None`
+    pub async fn get_messages_by_channel(&self, source: MessageSource, channel_id: &str, limit: Option<usize>) -> Result<Vec<Message>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM messages WHERE source = ? AND channel_id = ? ORDER BY timestamp DESC LIMIT ?",
+            MESSAGE_COLUMNS
+        ))
+        .bind(format!("{:?}", source))
+        .bind(channel_id)
+        // SQLite treats a negative limit as none
+        .bind(limit.map_or(-1, |l| l as i64))
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.hydrate_messages(rows).await
+    }
+
+    /// Newest-first cached messages matching a search. SQLite has no regex
+    /// support, so only source filters narrow the SQL query and the rest is
+    /// checked here, on the bare rows; only the first `limit` hits get their
+    /// attachments and reactions loaded.
+    pub async fn search_messages(&self, query: &Query, limit: usize) -> Result<Vec<Message>, sqlx::Error> {
+        let sources: Vec<String> = query
+            .terms
+            .iter()
+            .filter_map(|term| match term {
+                Term::Source(source) => Some(format!("{:?}", source)),
+                _ => None,
+            })
+            .collect();
+        let where_clause = if sources.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE source IN ({})", vec!["?"; sources.len()].join(", "))
+        };
+
+        let sql = format!("SELECT {} FROM messages {} ORDER BY timestamp DESC", MESSAGE_COLUMNS, where_clause);
+        let mut select = sqlx::query(&sql);
+        for source in &sources {
+            select = select.bind(source);
         }
 
+        let mut hits = Vec::new();
+        let mut rows = select.fetch(&self.pool);
+        while hits.len() < limit
+            && let Some(row) = rows.try_next().await?
+        {
+            if let Some(message) = message_from_row(&row)
+                && query.matches(&message)
+            {
+                hits.push(message);
+            }
+        }
+        // The in-memory pool has a single connection, held by the stream
+        drop(rows);
+
+        for message in &mut hits {
+            self.load_extras(message).await?;
+        }
+        Ok(hits)
+    }
+
+    /// Builds full messages, attachments and reactions included, from rows
+    /// selected with `MESSAGE_COLUMNS`
+    async fn hydrate_messages(&self, rows: Vec<SqliteRow>) -> Result<Vec<Message>, sqlx::Error> {
+        let mut messages: Vec<Message> = rows.iter().filter_map(message_from_row).collect();
+        for message in &mut messages {
+            self.load_extras(message).await?;
+        }
         Ok(messages)
     }
 
+    /// Fills in the attachments and reactions of a message read with
+    /// `message_from_row`
+    async fn load_extras(&self, message: &mut Message) -> Result<(), sqlx::Error> {
+        let attachment_rows = sqlx::query(
+            "SELECT filename, url, file_type, size, local_path FROM attachments WHERE message_id = ?"
+        )
+        .bind(message.id as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        message.attachments = attachment_rows
+            .into_iter()
+            .map(|row| {
+                let file_type_str: String = row.get("file_type");
+                let file_type = match file_type_str.as_str() {
+                    "Image" => AttachmentType::Image,
+                    "Video" => AttachmentType::Video,
+                    "Audio" => AttachmentType::Audio,
+                    "Document" => AttachmentType::Document,
+                    "Archive" => AttachmentType::Archive,
+                    _ => AttachmentType::Other,
+                };
+
+                Attachment {
+                    filename: row.get("filename"),
+                    url: row.get("url"),
+                    file_type,
+                    size: row.get("size"),
+                    local_path: row.get("local_path"),
+                }
+            })
+            .collect();
+
+        message.reactions = sqlx::query("SELECT emoji, count FROM reactions WHERE message_id = ? ORDER BY id")
+            .bind(message.id as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|row| Reaction {
+                emoji: row.get("emoji"),
+                count: row.get::<i64, _>("count") as u32,
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    /// Which source the cached message with this id came from, if any.
+    /// Ids are unique across sources, so another source's message with the
+    /// same id would be overwritten by caching this one.
+    pub async fn cached_source(&self, id: u64) -> Result<Option<MessageSource>, sqlx::Error> {
+        let row = sqlx::query("SELECT source FROM messages WHERE id = ?")
+            .bind(id as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.and_then(|row| MessageSource::from_name(&row.get::<String, _>("source"))))
+    }
+
     pub async fn cache_messages(&self, messages: &[Message]) -> Result<(), sqlx::Error> {
+        // One transaction for the whole batch instead of autocommitting every row
+        let mut tx = self.pool.begin().await?;
+
         for message in messages {
-            // Insert or replace message
+            // Insert or replace message. Services don't know what was sent
+            // from here, so a fetched copy keeps the status already recorded.
             sqlx::query(
                 r#"
-                INSERT OR REPLACE INTO messages (id, source, content, timestamp, author, channel_id)
-                VALUES (?, ?, ?, ?, ?, ?)
+                INSERT OR REPLACE INTO messages (id, source, content, timestamp, author, channel_id, reply_to_id, edited, send_status)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, (SELECT send_status FROM messages WHERE id = ? AND source = ?)))
                 "#,
             )
             .bind(message.id as i64)
             .bind(format!("{:?}", message.source))
             .bind(&message.content)
-            .bind(&message.timestamp)
+            .bind(message.timestamp)
             .bind(&message.author)
             .bind(&message.channel_id)
-            .execute(&self.pool)
+            .bind(message.reply_to_id.map(|id| id as i64))
+            .bind(message.edited)
+            .bind(message.send_status.map(|status| format!("{:?}", status)))
+            .bind(message.id as i64)
+            .bind(format!("{:?}", message.source))
+            .execute(&mut *tx)
             .await?;
 
+            // Fetched copies don't know about earlier downloads, so carry
+            // those over by filename (download URLs may be re-signed)
+            let downloaded: HashMap<String, String> = sqlx::query(
+                "SELECT filename, local_path FROM attachments WHERE message_id = ? AND local_path IS NOT NULL"
+            )
+            .bind(message.id as i64)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|row| (row.get("filename"), row.get("local_path")))
+            .collect();
+
             // Delete existing attachments for this message
             sqlx::query("DELETE FROM attachments WHERE message_id = ?")
                 .bind(message.id as i64)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await?;
 
             // Insert new attachments
             for attachment in &message.attachments {
                 sqlx::query(
                     r#"
-                    INSERT INTO attachments (message_id, filename, url, file_type, size)
-                    VALUES (?, ?, ?, ?, ?)
+                    INSERT INTO attachments (message_id, filename, url, file_type, size, local_path)
+                    VALUES (?, ?, ?, ?, ?, ?)
                     "#,
                 )
                 .bind(message.id as i64)
@@ -178,11 +660,50 @@ impl MessageCache {
                 .bind(&attachment.url)
                 .bind(format!("{:?}", attachment.file_type))
                 .bind(attachment.size.map(|s| s as i64))
-                .execute(&self.pool)
+                .bind(attachment.local_path.as_ref().or(downloaded.get(&attachment.filename)))
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            sqlx::query("DELETE FROM reactions WHERE message_id = ?")
+                .bind(message.id as i64)
+                .execute(&mut *tx)
                 .await?;
+
+            for reaction in &message.reactions {
+                sqlx::query("INSERT INTO reactions (message_id, emoji, count) VALUES (?, ?, ?)")
+                    .bind(message.id as i64)
+                    .bind(&reaction.emoji)
+                    .bind(reaction.count as i64)
+                    .execute(&mut *tx)
+                    .await?;
             }
         }
 
+        self.enforce_retention(&mut tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Where an attachment was downloaded to, if it has been
+    pub async fn get_attachment_local_path(&self, message_id: u64, filename: &str) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query("SELECT local_path FROM attachments WHERE message_id = ? AND filename = ? AND local_path IS NOT NULL")
+            .bind(message_id as i64)
+            .bind(filename)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get("local_path")))
+    }
+
+    pub async fn set_attachment_local_path(&self, message_id: u64, filename: &str, local_path: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE attachments SET local_path = ? WHERE message_id = ? AND filename = ?")
+            .bind(local_path)
+            .bind(message_id as i64)
+            .bind(filename)
+            .execute(&self.pool)
+            .await?;
+
         Ok(())
     }
 
@@ -212,12 +733,301 @@ impl MessageCache {
         Ok(())
     }
 
+    /// Watermark for providers that sync by modification time rather than id
+    pub async fn get_sync_timestamp(&self, provider_key: &str) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT last_timestamp FROM sync_timestamps WHERE provider_key = ?"
+        )
+        .bind(provider_key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| r.get::<DateTime<Utc>, _>("last_timestamp")))
+    }
+
+    pub async fn update_sync_timestamp(&self, provider_key: &str, last_timestamp: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO sync_timestamps (provider_key, last_timestamp)
+            VALUES (?, ?)
+            "#,
+        )
+        .bind(provider_key)
+        .bind(last_timestamp)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn enqueue_send(&self, provider_key: &str, source: MessageSource, channel_id: Option<&str>, content: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO pending_sends (provider_key, source, channel_id, content)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(provider_key)
+        .bind(format!("{:?}", source))
+        .bind(channel_id)
+        .bind(content)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Queued sends, oldest first so they're delivered in the order typed
+    pub async fn get_pending_sends(&self) -> Result<Vec<QueuedSend>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, provider_key, channel_id, content, attempts FROM pending_sends ORDER BY id"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| QueuedSend {
+                id: row.get("id"),
+                provider_key: row.get("provider_key"),
+                channel_id: row.get("channel_id"),
+                content: row.get("content"),
+                attempts: row.get("attempts"),
+            })
+            .collect())
+    }
+
+    pub async fn count_pending_sends(&self) -> Result<usize, sqlx::Error> {
+        let row = sqlx::query("SELECT COUNT(*) AS count FROM pending_sends")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get::<i64, _>("count") as usize)
+    }
+
+    pub async fn delete_pending_send(&self, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM pending_sends WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn record_send_failure(&self, id: i64, error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE pending_sends SET attempts = attempts + 1, last_error = ? WHERE id = ?")
+            .bind(error)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The unsent text saved for a destination
+    pub async fn get_draft(&self, provider_key: &str, channel_id: Option<&str>) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query("SELECT content FROM drafts WHERE provider_key = ? AND channel_id = ?")
+            .bind(provider_key)
+            .bind(channel_id.unwrap_or(""))
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.get("content")))
+    }
+
+    /// Saves the unsent text for a destination; empty text removes it
+    pub async fn save_draft(&self, provider_key: &str, channel_id: Option<&str>, content: &str) -> Result<(), sqlx::Error> {
+        if content.is_empty() {
+            return self.delete_draft(provider_key, channel_id).await;
+        }
+        sqlx::query("INSERT OR REPLACE INTO drafts (provider_key, channel_id, content, updated_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)")
+            .bind(provider_key)
+            .bind(channel_id.unwrap_or(""))
+            .bind(content)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_draft(&self, provider_key: &str, channel_id: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM drafts WHERE provider_key = ? AND channel_id = ?")
+            .bind(provider_key)
+            .bind(channel_id.unwrap_or(""))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn add_bookmark(&self, message: &Message) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO bookmarks (source, message_id, channel_id, content, author, timestamp)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(format!("{:?}", message.source))
+        .bind(message.id as i64)
+        .bind(&message.channel_id)
+        .bind(&message.content)
+        .bind(&message.author)
+        .bind(message.timestamp)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_bookmark(&self, source: MessageSource, message_id: u64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM bookmarks WHERE source = ? AND message_id = ?")
+            .bind(format!("{:?}", source))
+            .bind(message_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_bookmarks(&self) -> Result<Vec<Message>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT source, message_id, channel_id, content, author, timestamp FROM bookmarks ORDER BY timestamp DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(Message {
+                    id: row.get::<i64, _>("message_id") as u64,
+                    source: MessageSource::from_name(&row.get::<String, _>("source"))?,
+                    content: row.get("content"),
+                    timestamp: row.get("timestamp"),
+                    author: row.get("author"),
+                    attachments: vec![],
+                    reactions: vec![],
+                    channel_id: row.get("channel_id"),
+                    reply_to_id: None,
+                    edited: false,
+                    send_status: None,
+                })
+            })
+            .collect())
+    }
+
+    /// Replaces the cached replies of a message with a freshly fetched thread
+    pub async fn cache_thread(&self, parent: &Message, replies: &[Message]) -> Result<(), sqlx::Error> {
+        let source = format!("{:?}", parent.source);
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM thread_replies WHERE source = ? AND parent_id = ?")
+            .bind(&source)
+            .bind(parent.id as i64)
+            .execute(&mut *tx)
+            .await?;
+
+        for reply in replies {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO thread_replies (source, parent_id, id, content, author, timestamp, channel_id)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&source)
+            .bind(parent.id as i64)
+            .bind(reply.id as i64)
+            .bind(&reply.content)
+            .bind(&reply.author)
+            .bind(reply.timestamp)
+            .bind(&reply.channel_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Cached replies of a message, oldest first
+    pub async fn get_thread(&self, source: MessageSource, parent_id: u64) -> Result<Vec<Message>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, content, author, timestamp, channel_id FROM thread_replies WHERE source = ? AND parent_id = ? ORDER BY timestamp"
+        )
+        .bind(format!("{:?}", source))
+        .bind(parent_id as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Message {
+                id: row.get::<i64, _>("id") as u64,
+                source,
+                content: row.get("content"),
+                timestamp: row.get("timestamp"),
+                author: row.get("author"),
+                attachments: vec![],
+                reactions: vec![],
+                channel_id: row.get("channel_id"),
+                reply_to_id: Some(parent_id),
+                edited: false,
+                send_status: None,
+            })
+            .collect())
+    }
+
+    /// Mutes added at runtime, as (source, channel id) pairs and author names
+    pub async fn get_mutes(&self) -> Result<(Vec<(MessageSource, String)>, Vec<String>), sqlx::Error> {
+        let rows = sqlx::query("SELECT kind, source, value FROM mutes")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut channels = Vec::new();
+        let mut authors = Vec::new();
+        for row in rows {
+            let kind: String = row.get("kind");
+            let value: String = row.get("value");
+            match kind.as_str() {
+                "author" => authors.push(value),
+                "channel" => {
+                    if let Some(source) = MessageSource::from_name(&row.get::<String, _>("source")) {
+                        channels.push((source, value));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok((channels, authors))
+    }
+
+    pub async fn mute_channel(&self, source: MessageSource, channel_id: &str) -> Result<(), sqlx::Error> {
+        self.add_mute("channel", &format!("{:?}", source), channel_id).await
+    }
+
+    /// Author mutes apply across every source
+    pub async fn mute_author(&self, author: &str) -> Result<(), sqlx::Error> {
+        self.add_mute("author", "", author).await
+    }
+
+    async fn add_mute(&self, kind: &str, source: &str, value: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT OR IGNORE INTO mutes (kind, source, value) VALUES (?, ?, ?)")
+            .bind(kind)
+            .bind(source)
+            .bind(value)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Newest-first cached messages after `since`, with their attachments
+    /// and reactions like `get_cached_messages`
+    #[allow(dead_code)]
     pub async fn get_messages_since(&self, since: DateTime<Utc>, limit: Option<usize>) -> Result<Vec<Message>, sqlx::Error> {
         let limit_clause = limit.map(|l| format!("LIMIT {}", l)).unwrap_or_default();
         
         let query = format!(
-            "SELECT id, source, content, timestamp, author, channel_id FROM messages WHERE timestamp > ? ORDER BY timestamp DESC {}",
-            limit_clause
+            "SELECT {} FROM messages WHERE timestamp > ? ORDER BY timestamp DESC {}",
+            MESSAGE_COLUMNS, limit_clause
         );
         
         let rows = sqlx::query(&query)
@@ -225,38 +1035,161 @@ impl MessageCache {
             .fetch_all(&self.pool)
             .await?;
         
-        let mut messages = Vec::new();
-        for row in rows {
-            let message_id: i64 = row.get("id");
-            let source_str: String = row.get("source");
-            let source = match source_str.as_str() {
-                "Telegram" => MessageSource::Telegram,
-                "Discord" => MessageSource::Discord,
-                "Github" => MessageSource::Github,
-                "Jira" => MessageSource::Jira,
-                _ => continue,
-            };
+        self.hydrate_messages(rows).await
+    }
 
-            messages.push(Message {
-                id: message_id as u64,
-                source,
-                content: row.get("content"),
-                timestamp: row.get("timestamp"),
-                author: row.get("author"),
-                attachments: vec![], // Skip attachments for incremental updates for now
-                channel_id: row.get("channel_id"),
-            });
+    pub async fn get_read_messages(&self) -> Result<Vec<(MessageSource, u64)>, sqlx::Error> {
+        let rows = sqlx::query("SELECT source, message_id FROM read_messages")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let source = MessageSource::from_name(&row.get::<String, _>("source"))?;
+                Some((source, row.get::<i64, _>("message_id") as u64))
+            })
+            .collect())
+    }
+
+    pub async fn mark_read(&self, messages: &[(MessageSource, u64)]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for (source, message_id) in messages {
+            sqlx::query("INSERT OR IGNORE INTO read_messages (source, message_id) VALUES (?, ?)")
+                .bind(format!("{:?}", source))
+                .bind(*message_id as i64)
+                .execute(&mut *tx)
+                .await?;
         }
+        tx.commit().await?;
+        Ok(())
+    }
 
-        Ok(messages)
+    /// Number of cached messages not yet read, per source. Sources with
+    /// nothing unread are left out.
+    pub async fn unread_counts(&self) -> Result<HashMap<MessageSource, usize>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT m.source, COUNT(*) AS count FROM messages m
+             LEFT JOIN read_messages r ON r.source = m.source AND r.message_id = m.id
+             WHERE r.message_id IS NULL
+             GROUP BY m.source",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let source = MessageSource::from_name(&row.get::<String, _>("source"))?;
+                Some((source, row.get::<i64, _>("count") as usize))
+            })
+            .collect())
+    }
+
+    /// Marks every cached message read, from one source or all of them
+    pub async fn mark_all_read(&self, source: Option<MessageSource>) -> Result<(), sqlx::Error> {
+        match source {
+            Some(source) => {
+                sqlx::query("INSERT OR IGNORE INTO read_messages (source, message_id) SELECT source, id FROM messages WHERE source = ?")
+                    .bind(format!("{:?}", source))
+                    .execute(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query("INSERT OR IGNORE INTO read_messages (source, message_id) SELECT source, id FROM messages")
+                    .execute(&self.pool)
+                    .await?
+            }
+        };
+        Ok(())
+    }
+
+    /// Removes every cached message and resets sync state so the next
+    /// refresh starts from scratch. Queued sends are kept.
+    pub async fn clear_all(&self) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM attachments").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM reactions").execute(&mut *tx).await?;
+        let deleted = sqlx::query("DELETE FROM messages").execute(&mut *tx).await?.rows_affected();
+        sqlx::query("DELETE FROM thread_replies").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM read_messages").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM sync_state").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM sync_timestamps").execute(&mut *tx).await?;
+
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Removes cached messages (and their attachments) older than `cutoff`
+    pub async fn delete_older_than(&self, cutoff: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM attachments WHERE message_id IN (SELECT id FROM messages WHERE timestamp < ?)")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM reactions WHERE message_id IN (SELECT id FROM messages WHERE timestamp < ?)")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM messages WHERE timestamp < ?")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM thread_replies WHERE parent_id NOT IN (SELECT id FROM messages)")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM read_messages WHERE message_id NOT IN (SELECT id FROM messages)")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Removes cached messages (and their attachments) from one source
+    pub async fn clear_source(&self, source: MessageSource) -> Result<u64, sqlx::Error> {
+        let source = format!("{:?}", source);
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM attachments WHERE message_id IN (SELECT id FROM messages WHERE source = ?)")
+            .bind(&source)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM reactions WHERE message_id IN (SELECT id FROM messages WHERE source = ?)")
+            .bind(&source)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM messages WHERE source = ?")
+            .bind(&source)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM thread_replies WHERE source = ?")
+            .bind(&source)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM read_messages WHERE source = ?")
+            .bind(&source)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(deleted)
     }
 
     pub async fn delete_message(&self, message_id: u64) -> Result<(), sqlx::Error> {
-        // Delete attachments first (foreign key constraint)
+        // Delete attachments and reactions first (foreign key constraint)
         sqlx::query("DELETE FROM attachments WHERE message_id = ?")
             .bind(message_id as i64)
             .execute(&self.pool)
             .await?;
+        sqlx::query("DELETE FROM reactions WHERE message_id = ?")
+            .bind(message_id as i64)
+            .execute(&self.pool)
+            .await?;
         
         // Delete the message
         sqlx::query("DELETE FROM messages WHERE id = ?")
@@ -266,4 +1199,320 @@ impl MessageCache {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: u64, source: MessageSource, content: &str, minutes_ago: i64) -> Message {
+        Message {
+            id,
+            source,
+            content: content.to_string(),
+            timestamp: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            author: "alice".to_string(),
+            attachments: vec![],
+            reactions: vec![],
+            channel_id: Some("general".to_string()),
+            reply_to_id: None,
+            edited: false,
+            send_status: None,
+        }
+    }
+
+    async fn memory_cache() -> MessageCache {
+        MessageCache::new("sqlite::memory:", None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn drafts_are_kept_per_destination() {
+        let cache = memory_cache().await;
+        cache.save_draft("discord_1", Some("general"), "half a thought").await.unwrap();
+        cache.save_draft("discord_1", None, "elsewhere").await.unwrap();
+        assert_eq!(cache.get_draft("discord_1", Some("general")).await.unwrap().as_deref(), Some("half a thought"));
+        assert_eq!(cache.get_draft("discord_1", None).await.unwrap().as_deref(), Some("elsewhere"));
+
+        cache.save_draft("discord_1", Some("general"), "").await.unwrap();
+        assert_eq!(cache.get_draft("discord_1", Some("general")).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn migrates_an_unversioned_cache_without_losing_messages() {
+        let path = std::env::temp_dir().join(format!("friend-migrate-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+
+        // The schema before reply_to_id and edited existed, at user_version 0
+        let legacy = SqlitePool::connect(&url).await.unwrap();
+        sqlx::query("CREATE TABLE messages (id INTEGER PRIMARY KEY, source TEXT NOT NULL, content TEXT NOT NULL, timestamp DATETIME NOT NULL, author TEXT NOT NULL, channel_id TEXT, created_at DATETIME DEFAULT CURRENT_TIMESTAMP)")
+            .execute(&legacy)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO messages (id, source, content, timestamp, author) VALUES (1, 'Discord', 'kept', ?, 'alice')")
+            .bind(Utc::now())
+            .execute(&legacy)
+            .await
+            .unwrap();
+        legacy.close().await;
+
+        let cache = MessageCache::new(&url, None).await.unwrap();
+        let cached = cache.get_cached_messages(None).await.unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].content, "kept");
+        assert!(!cached[0].edited);
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&cache.pool).await.unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+        cache.pool.close().await;
+
+        // Reopening an up-to-date cache applies nothing
+        MessageCache::new(&url, None).await.unwrap().pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn round_trips_messages_newest_first() {
+        let cache = memory_cache().await;
+        let mut reply = message(2, MessageSource::Discord, "second", 1);
+        reply.reply_to_id = Some(1);
+        reply.edited = true;
+        reply.attachments.push(Attachment {
+            filename: "cat.png".to_string(),
+            url: "https://example.com/cat.png".to_string(),
+            file_type: AttachmentType::Image,
+            size: Some(1024),
+            local_path: None,
+        });
+        reply.reactions.push(Reaction { emoji: "👍".to_string(), count: 3 });
+
+        cache.cache_messages(&[message(1, MessageSource::Discord, "first", 5), reply]).await.unwrap();
+
+        let cached = cache.get_cached_messages(None).await.unwrap();
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached[0].content, "second");
+        assert_eq!(cached[0].source, MessageSource::Discord);
+        assert_eq!(cached[0].channel_id.as_deref(), Some("general"));
+        assert_eq!(cached[0].reply_to_id, Some(1));
+        assert!(cached[0].edited && !cached[1].edited);
+        assert_eq!(cached[0].attachments.len(), 1);
+        assert_eq!(cached[0].attachments[0].url, "https://example.com/cat.png");
+        assert_eq!(cached[0].reactions.len(), 1);
+        assert_eq!(cached[0].reactions[0].count, 3);
+        assert_eq!(cached[1].content, "first");
+    }
+
+    #[tokio::test]
+    async fn reports_which_source_an_id_belongs_to() {
+        let cache = memory_cache().await;
+        cache.cache_messages(&[message(7, MessageSource::Jira, "ticket", 1)]).await.unwrap();
+        assert_eq!(cache.cached_source(7).await.unwrap(), Some(MessageSource::Jira));
+        assert_eq!(cache.cached_source(8).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn caching_again_updates_instead_of_duplicating() {
+        let cache = memory_cache().await;
+        cache.cache_messages(&[message(1, MessageSource::Github, "draft", 1)]).await.unwrap();
+        cache.cache_messages(&[message(1, MessageSource::Github, "edited", 1)]).await.unwrap();
+
+        let cached = cache.get_cached_messages(None).await.unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].content, "edited");
+    }
+
+    #[tokio::test]
+    async fn fetched_copies_keep_the_send_status() {
+        let cache = memory_cache().await;
+        let mut sent = message(1, MessageSource::Discord, "hello", 1);
+        sent.send_status = Some(SendStatus::Sent);
+        cache.cache_messages(&[sent, message(2, MessageSource::Discord, "hi", 1)]).await.unwrap();
+        // The service's copy of the same message knows nothing of it
+        cache.cache_messages(&[message(1, MessageSource::Discord, "hello", 1)]).await.unwrap();
+
+        let cached = cache.get_cached_messages(None).await.unwrap();
+        let status = |id| cached.iter().find(|m| m.id == id).unwrap().send_status;
+        assert_eq!(status(1), Some(SendStatus::Sent));
+        assert_eq!(status(2), None);
+
+        // Another source's message with the same id is a different message
+        cache.cache_messages(&[message(1, MessageSource::Github, "unrelated", 1)]).await.unwrap();
+        let cached = cache.get_cached_messages(None).await.unwrap();
+        assert_eq!(cached.iter().find(|m| m.id == 1).unwrap().send_status, None);
+    }
+
+    #[tokio::test]
+    async fn respects_limit_and_source_counts() {
+        let cache = memory_cache().await;
+        cache.cache_messages(&[
+            message(1, MessageSource::Telegram, "a", 3),
+            message(2, MessageSource::Telegram, "b", 2),
+            message(3, MessageSource::Jira, "c", 1),
+        ]).await.unwrap();
+
+        let latest = cache.get_cached_messages(Some(2)).await.unwrap();
+        assert_eq!(latest.iter().map(|m| m.id).collect::<Vec<_>>(), vec![3, 2]);
+        let older = cache.get_cached_messages_paged(2, 2).await.unwrap();
+        assert_eq!(older.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(cache.count_messages(Some(MessageSource::Telegram)).await.unwrap(), 2);
+        assert_eq!(cache.count_messages(None).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn download_paths_survive_recaching() {
+        let cache = memory_cache().await;
+        let mut msg = message(1, MessageSource::Discord, "see attached", 1);
+        msg.attachments.push(Attachment {
+            filename: "report.pdf".to_string(),
+            url: "https://cdn.example.com/report.pdf?ex=1".to_string(),
+            file_type: AttachmentType::Document,
+            size: None,
+            local_path: None,
+        });
+        cache.cache_messages(std::slice::from_ref(&msg)).await.unwrap();
+        cache.set_attachment_local_path(1, "report.pdf", "/tmp/report.pdf").await.unwrap();
+
+        // A later fetch re-signs the URL and knows nothing about the download
+        msg.attachments[0].url = "https://cdn.example.com/report.pdf?ex=2".to_string();
+        cache.cache_messages(&[msg]).await.unwrap();
+
+        assert_eq!(cache.get_attachment_local_path(1, "report.pdf").await.unwrap().as_deref(), Some("/tmp/report.pdf"));
+        let cached = cache.get_cached_messages(None).await.unwrap();
+        assert_eq!(cached[0].attachments[0].local_path.as_deref(), Some("/tmp/report.pdf"));
+    }
+
+    #[tokio::test]
+    async fn messages_since_keep_their_attachments() {
+        let cache = memory_cache().await;
+        let mut recent = message(2, MessageSource::Discord, "screenshot", 1);
+        recent.attachments.push(Attachment {
+            filename: "screen.png".to_string(),
+            url: "https://cdn.example.com/screen.png".to_string(),
+            file_type: AttachmentType::Image,
+            size: Some(2048),
+            local_path: None,
+        });
+        cache.cache_messages(&[recent, message(1, MessageSource::Discord, "old", 60)]).await.unwrap();
+
+        let since = cache.get_messages_since(Utc::now() - chrono::Duration::minutes(30), None).await.unwrap();
+        assert_eq!(since.len(), 1);
+        assert_eq!(since[0].attachments.len(), 1);
+        assert_eq!(since[0].attachments[0].filename, "screen.png");
+    }
+
+    #[tokio::test]
+    async fn threads_replace_previous_replies_and_stay_out_of_the_list() {
+        let cache = memory_cache().await;
+        let parent = message(1, MessageSource::Jira, "PROJ-1: Broken build", 10);
+        cache.cache_messages(std::slice::from_ref(&parent)).await.unwrap();
+
+        cache.cache_thread(&parent, &[message(7, MessageSource::Jira, "stale", 5)]).await.unwrap();
+        cache.cache_thread(&parent, &[
+            message(9, MessageSource::Jira, "fixed now", 1),
+            message(8, MessageSource::Jira, "looking into it", 3),
+        ]).await.unwrap();
+
+        let thread = cache.get_thread(MessageSource::Jira, 1).await.unwrap();
+        assert_eq!(thread.iter().map(|m| m.content.as_str()).collect::<Vec<_>>(), vec!["looking into it", "fixed now"]);
+        assert!(thread.iter().all(|m| m.reply_to_id == Some(1)));
+        assert_eq!(cache.get_cached_messages(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn searches_by_field_and_pattern() {
+        let cache = memory_cache().await;
+        let mut from_bot = message(1, MessageSource::Discord, "deploy error: timeout", 2);
+        from_bot.author = "ci-bot".to_string();
+        from_bot.reactions = vec![Reaction { emoji: "👀".to_string(), count: 2 }];
+        cache.cache_messages(&[
+            from_bot,
+            message(2, MessageSource::Discord, "any error here?", 1),
+            message(3, MessageSource::Github, "error in ci-bot config", 3),
+        ]).await.unwrap();
+
+        let query = crate::search::parse_query("author:bot source:discord error").unwrap();
+        let found = cache.search_messages(&query, 10).await.unwrap();
+        assert_eq!(found.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1]);
+
+        let query = crate::search::parse_query("/re ^(any|deploy) error").unwrap();
+        let found = cache.search_messages(&query, 10).await.unwrap();
+        assert_eq!(found.iter().map(|m| m.id).collect::<Vec<_>>(), vec![2, 1]);
+
+        // Stops at the limit, newest first, with the hits fully loaded
+        let query = crate::search::parse_query("error").unwrap();
+        let found = cache.search_messages(&query, 2).await.unwrap();
+        assert_eq!(found.iter().map(|m| m.id).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(found[1].reactions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn read_state_survives_recaching_and_marks_per_source() {
+        let cache = memory_cache().await;
+        cache.cache_messages(&[
+            message(1, MessageSource::Discord, "a", 3),
+            message(2, MessageSource::Discord, "b", 2),
+            message(3, MessageSource::Github, "c", 1),
+        ]).await.unwrap();
+
+        cache.mark_read(&[(MessageSource::Github, 3)]).await.unwrap();
+        cache.cache_messages(&[message(3, MessageSource::Github, "c, refetched", 1)]).await.unwrap();
+        assert_eq!(cache.get_read_messages().await.unwrap(), vec![(MessageSource::Github, 3)]);
+
+        cache.mark_all_read(Some(MessageSource::Discord)).await.unwrap();
+        let mut read = cache.get_read_messages().await.unwrap();
+        read.sort_by_key(|&(_, id)| id);
+        assert_eq!(read.iter().map(|&(_, id)| id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn retention_drops_the_oldest_beyond_the_caps() {
+        let mut cache = memory_cache().await;
+        cache.set_retention(Some(3), HashMap::from([(MessageSource::Github, 1)]));
+        let mut old = message(1, MessageSource::Discord, "oldest", 50);
+        old.attachments.push(Attachment {
+            filename: "a.txt".to_string(),
+            url: "https://cdn.example/a.txt".to_string(),
+            file_type: AttachmentType::Document,
+            size: None,
+            local_path: None,
+        });
+        cache.cache_messages(&[
+            old,
+            message(2, MessageSource::Discord, "b", 40),
+            message(3, MessageSource::Github, "c", 30),
+            message(4, MessageSource::Github, "d", 20),
+            message(5, MessageSource::Discord, "e", 10),
+        ]).await.unwrap();
+
+        let kept: Vec<u64> = cache.get_cached_messages(None).await.unwrap().iter().map(|m| m.id).collect();
+        assert_eq!(kept, vec![5, 4, 2]);
+        let attachments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments").fetch_one(&cache.pool).await.unwrap();
+        assert_eq!(attachments, 0);
+    }
+
+    #[tokio::test]
+    async fn unread_counts_are_per_source() {
+        let cache = memory_cache().await;
+        cache.cache_messages(&[
+            message(1, MessageSource::Discord, "a", 3),
+            message(2, MessageSource::Discord, "b", 2),
+            message(3, MessageSource::Github, "c", 1),
+        ]).await.unwrap();
+        cache.mark_read(&[(MessageSource::Discord, 1)]).await.unwrap();
+
+        let counts = cache.unread_counts().await.unwrap();
+        assert_eq!(counts.get(&MessageSource::Discord), Some(&1));
+        assert_eq!(counts.get(&MessageSource::Github), Some(&1));
+
+        cache.mark_all_read(Some(MessageSource::Github)).await.unwrap();
+        assert_eq!(cache.unread_counts().await.unwrap().get(&MessageSource::Github), None);
+    }
+
+    #[tokio::test]
+    async fn separate_memory_caches_do_not_share_data() {
+        let first = memory_cache().await;
+        let second = memory_cache().await;
+        first.cache_messages(&[message(1, MessageSource::Discord, "only here", 1)]).await.unwrap();
+
+        assert!(second.get_cached_messages(None).await.unwrap().is_empty());
+    }
+}
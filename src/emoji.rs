@@ -0,0 +1,391 @@
+/// Common shortcodes as used by GitHub, Slack and Discord, sorted by name
+/// for binary search
+const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("airplane", "✈️"),
+    ("alarm_clock", "⏰"),
+    ("alien", "👽"),
+    ("ambulance", "🚑"),
+    ("angry", "😠"),
+    ("anguished", "😧"),
+    ("apple", "🍎"),
+    ("arrow_down", "⬇️"),
+    ("arrow_forward", "▶️"),
+    ("arrow_left", "⬅️"),
+    ("arrow_right", "➡️"),
+    ("arrow_up", "⬆️"),
+    ("arrows_counterclockwise", "🔄"),
+    ("art", "🎨"),
+    ("astonished", "😲"),
+    ("baby", "👶"),
+    ("balloon", "🎈"),
+    ("banana", "🍌"),
+    ("bar_chart", "📊"),
+    ("basketball", "🏀"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("bell", "🔔"),
+    ("bikini", "👙"),
+    ("birthday", "🎂"),
+    ("black_circle", "⚫"),
+    ("blue_heart", "💙"),
+    ("blush", "😊"),
+    ("bomb", "💣"),
+    ("bookmark", "🔖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("bow", "🙇"),
+    ("brain", "🧠"),
+    ("briefcase", "💼"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("cactus", "🌵"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("calendar_spiral", "🗓️"),
+    ("call_me_hand", "🤙"),
+    ("camera", "📷"),
+    ("car", "🚗"),
+    ("cat", "🐱"),
+    ("champagne", "🍾"),
+    ("chart", "💹"),
+    ("chart_with_downwards_trend", "📉"),
+    ("chart_with_upwards_trend", "📈"),
+    ("checkered_flag", "🏁"),
+    ("cherry_blossom", "🌸"),
+    ("chess_pawn", "♟️"),
+    ("christmas_tree", "🎄"),
+    ("clap", "👏"),
+    ("clipboard", "📋"),
+    ("cloud", "☁️"),
+    ("clown_face", "🤡"),
+    ("cocktail", "🍸"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("confetti_ball", "🎊"),
+    ("confused", "😕"),
+    ("construction", "🚧"),
+    ("cool", "🆒"),
+    ("cop", "👮"),
+    ("cowboy_hat_face", "🤠"),
+    ("crossed_fingers", "🤞"),
+    ("crown", "👑"),
+    ("cry", "😢"),
+    ("crying_cat_face", "😿"),
+    ("cupid", "💘"),
+    ("dart", "🎯"),
+    ("disappointed", "😞"),
+    ("dizzy", "💫"),
+    ("dna", "🧬"),
+    ("dog", "🐶"),
+    ("dress", "👗"),
+    ("drooling_face", "🤤"),
+    ("ear", "👂"),
+    ("earth_americas", "🌎"),
+    ("email", "📧"),
+    ("envelope", "✉️"),
+    ("evergreen_tree", "🌲"),
+    ("exclamation", "❗"),
+    ("eyeglasses", "👓"),
+    ("eyes", "👀"),
+    ("face_with_rolling_eyes", "🙄"),
+    ("facepalm", "🤦"),
+    ("fearful", "😨"),
+    ("file_folder", "📁"),
+    ("fire", "🔥"),
+    ("fireworks", "🎆"),
+    ("fist", "✊"),
+    ("flushed", "😳"),
+    ("free", "🆓"),
+    ("frowning", "😦"),
+    ("game_die", "🎲"),
+    ("gear", "⚙️"),
+    ("gem", "💎"),
+    ("ghost", "👻"),
+    ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"),
+    ("green_circle", "🟢"),
+    ("green_heart", "💚"),
+    ("grimacing", "😬"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("hammer", "🔨"),
+    ("hand", "✋"),
+    ("handbag", "👜"),
+    ("handshake", "🤝"),
+    ("hankey", "💩"),
+    ("headphones", "🎧"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("heartpulse", "💗"),
+    ("hearts", "♥️"),
+    ("heavy_check_mark", "✔️"),
+    ("heavy_dollar_sign", "💲"),
+    ("heavy_heart_exclamation", "❣️"),
+    ("heavy_minus_sign", "➖"),
+    ("heavy_multiplication_x", "✖️"),
+    ("heavy_plus_sign", "➕"),
+    ("hospital", "🏥"),
+    ("hourglass", "⌛"),
+    ("hourglass_flowing_sand", "⏳"),
+    ("house", "🏠"),
+    ("hugs", "🤗"),
+    ("hushed", "😯"),
+    ("imp", "👿"),
+    ("inbox_tray", "📥"),
+    ("infinity", "♾️"),
+    ("information_source", "ℹ️"),
+    ("innocent", "😇"),
+    ("jack_o_lantern", "🎃"),
+    ("jeans", "👖"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("kiss", "😘"),
+    ("label", "🏷️"),
+    ("large_blue_circle", "🔵"),
+    ("laughing", "😆"),
+    ("link", "🔗"),
+    ("lips", "👄"),
+    ("lipstick", "💄"),
+    ("lock", "🔒"),
+    ("lock_with_ink_pen", "🔏"),
+    ("loudspeaker", "📢"),
+    ("lying_face", "🤥"),
+    ("mag", "🔍"),
+    ("mailbox", "📫"),
+    ("man", "👨"),
+    ("mask", "😷"),
+    ("medal", "🏅"),
+    ("mega", "📣"),
+    ("memo", "📝"),
+    ("metal", "🤘"),
+    ("microscope", "🔬"),
+    ("money_mouth_face", "🤑"),
+    ("money_with_wings", "💸"),
+    ("moneybag", "💰"),
+    ("mortar_board", "🎓"),
+    ("muscle", "💪"),
+    ("musical_note", "🎵"),
+    ("nail_care", "💅"),
+    ("necktie", "👔"),
+    ("nerd_face", "🤓"),
+    ("neutral_face", "😐"),
+    ("new", "🆕"),
+    ("no_entry", "⛔"),
+    ("no_entry_sign", "🚫"),
+    ("nose", "👃"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("open_file_folder", "📂"),
+    ("open_mouth", "😮"),
+    ("outbox_tray", "📤"),
+    ("package", "📦"),
+    ("partying_face", "🥳"),
+    ("pause_button", "⏸️"),
+    ("pencil", "📝"),
+    ("pensive", "😔"),
+    ("persevere", "😣"),
+    ("pill", "💊"),
+    ("pin", "📌"),
+    ("pizza", "🍕"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "☝️"),
+    ("poop", "💩"),
+    ("popcorn", "🍿"),
+    ("pray", "🙏"),
+    ("punch", "👊"),
+    ("purple_heart", "💜"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rage", "😡"),
+    ("rainbow", "🌈"),
+    ("raised_hand", "✋"),
+    ("raised_hands", "🙌"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("relaxed", "☺️"),
+    ("relieved", "😌"),
+    ("repeat", "🔁"),
+    ("ring", "💍"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("roll_eyes", "🙄"),
+    ("rose", "🌹"),
+    ("rotating_light", "🚨"),
+    ("santa", "🎅"),
+    ("satisfied", "😆"),
+    ("school_satchel", "🎒"),
+    ("scream", "😱"),
+    ("see_no_evil", "🙈"),
+    ("seedling", "🌱"),
+    ("selfie", "🤳"),
+    ("shield", "🛡️"),
+    ("ship", "🚢"),
+    ("shipit", "🐿️"),
+    ("shirt", "👕"),
+    ("shrug", "🤷"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("sleepy", "😪"),
+    ("slight_smile", "🙂"),
+    ("slightly_frowning_face", "🙁"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smiling_imp", "😈"),
+    ("smirk", "😏"),
+    ("sneezing_face", "🤧"),
+    ("snowflake", "❄️"),
+    ("sob", "😭"),
+    ("soccer", "⚽"),
+    ("sos", "🆘"),
+    ("space_invader", "👾"),
+    ("sparkles", "✨"),
+    ("sparkling_heart", "💖"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star2", "🌟"),
+    ("star_struck", "🤩"),
+    ("stop_button", "⏹️"),
+    ("stopwatch", "⏱️"),
+    ("stuck_out_tongue", "😛"),
+    ("stuck_out_tongue_winking_eye", "😜"),
+    ("sunflower", "🌻"),
+    ("sunglasses", "😎"),
+    ("sunny", "☀️"),
+    ("sweat", "😓"),
+    ("sweat_smile", "😅"),
+    ("syringe", "💉"),
+    ("tada", "🎉"),
+    ("tea", "🍵"),
+    ("thermometer", "🌡️"),
+    ("thinking", "🤔"),
+    ("thumbs_down", "👎"),
+    ("thumbs_up", "👍"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tired_face", "😫"),
+    ("tongue", "👅"),
+    ("tools", "🛠️"),
+    ("tophat", "🎩"),
+    ("triangular_flag_on_post", "🚩"),
+    ("triumph", "😤"),
+    ("trophy", "🏆"),
+    ("tulip", "🌷"),
+    ("two_hearts", "💕"),
+    ("umbrella", "☔"),
+    ("unamused", "😒"),
+    ("unlock", "🔓"),
+    ("up", "🆙"),
+    ("upside_down_face", "🙃"),
+    ("v", "✌️"),
+    ("video_game", "🎮"),
+    ("vs", "🆚"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("weary", "😩"),
+    ("white_check_mark", "✅"),
+    ("white_circle", "⚪"),
+    ("white_flag", "🏳️"),
+    ("wine_glass", "🍷"),
+    ("wink", "😉"),
+    ("woman", "👩"),
+    ("worried", "😟"),
+    ("wrench", "🔧"),
+    ("writing_hand", "✍️"),
+    ("x", "❌"),
+    ("yellow_heart", "💛"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zipper_mouth_face", "🤐"),
+    ("zzz", "💤"),
+];
+
+fn lookup(name: &str) -> Option<&'static str> {
+    SHORTCODES
+        .binary_search_by_key(&name, |&(code, _)| code)
+        .ok()
+        .map(|index| SHORTCODES[index].1)
+}
+
+/// Emoji whose shortcode contains `query` (case-insensitive, spaces as
+/// underscores), as (shortcode, emoji). Codes starting with the query come
+/// first; an empty query lists them all.
+pub fn search(query: &str) -> Vec<(&'static str, &'static str)> {
+    let query = query.trim().trim_matches(':').to_lowercase().replace(' ', "_");
+    let (mut matches, contains): (Vec<_>, Vec<_>) = SHORTCODES
+        .iter()
+        .filter(|(code, _)| code.contains(query.as_str()))
+        .partition(|(code, _)| code.starts_with(query.as_str()));
+    matches.extend(contains);
+    matches
+}
+
+/// Replaces known `:shortcode:`s with their emoji. Unknown codes, and
+/// anything between backticks, are left as written.
+pub fn render_shortcodes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_code = false;
+    let mut rest = text;
+
+    while let Some(position) = rest.find([':', '`']) {
+        out.push_str(&rest[..position]);
+        rest = &rest[position..];
+        if let Some(after) = rest.strip_prefix('`') {
+            in_code = !in_code;
+            out.push('`');
+            rest = after;
+            continue;
+        }
+
+        let name_len = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-')))
+            .unwrap_or(rest.len() - 1);
+        if !in_code
+            && rest[1 + name_len..].starts_with(':')
+            && let Some(emoji) = lookup(&rest[1..1 + name_len])
+        {
+            out.push_str(emoji);
+            rest = &rest[name_len + 2..];
+        } else {
+            // The closing colon may open the next shortcode
+            out.push(':');
+            rest = &rest[1..];
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_is_sorted_for_lookup() {
+        assert!(SHORTCODES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn search_ranks_prefix_matches_first() {
+        let codes: Vec<&str> = search("Heart").into_iter().map(|(code, _)| code).collect();
+        assert_eq!(codes.first(), Some(&"heart"));
+        assert!(codes.contains(&"yellow_heart"));
+        assert_eq!(search(":yellow heart"), vec![("yellow_heart", "💛")]);
+        assert_eq!(search("").len(), SHORTCODES.len());
+    }
+
+    #[test]
+    fn replaces_known_codes_only() {
+        assert_eq!(render_shortcodes("ship it :rocket: :+1:"), "ship it 🚀 👍");
+        assert_eq!(render_shortcodes(":not_a_code: at 10:30:tada:"), ":not_a_code: at 10:30🎉");
+        assert_eq!(render_shortcodes("`:rocket:` :rocket:"), "`:rocket:` 🚀");
+    }
+}
@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::path::Path;
+use crate::{Attachment, AttachmentType, Message, MessageSource, Reaction};
+
+/// File formats a conversation can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "markdown" | "md" => Some(Format::Markdown),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from an output path, defaulting to Markdown
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Format::Json,
+            _ => Format::Markdown,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Json => "json",
+        }
+    }
+}
+
+/// Renders `messages` (oldest first) as a log titled `title`
+pub fn render(messages: &[Message], title: &str, format: Format) -> String {
+    match format {
+        Format::Markdown => to_markdown(messages, title),
+        Format::Json => {
+            let messages: Vec<Value> = messages.iter().map(to_json).collect();
+            serde_json::to_string_pretty(&messages).unwrap_or_default() + "\n"
+        }
+    }
+}
+
+fn to_markdown(messages: &[Message], title: &str) -> String {
+    let mut out = format!("# {}\n", title);
+    let mut current_day = None;
+
+    for msg in messages {
+        let day = msg.timestamp.date_naive();
+        if current_day != Some(day) {
+            out.push_str(&format!("\n## {}\n", day.format("%Y-%m-%d")));
+            current_day = Some(day);
+        }
+
+        out.push_str(&format!(
+            "\n**{}** · {} UTC{}\n\n",
+            msg.author,
+            msg.timestamp.format("%H:%M:%S"),
+            if msg.edited { " (edited)" } else { "" },
+        ));
+        out.push_str(msg.content.trim_end());
+        out.push('\n');
+        for attachment in &msg.attachments {
+            out.push_str(&format!("\n- Attachment: [{}]({})\n", attachment.filename, attachment.url));
+        }
+    }
+
+    out
+}
+
+/// One message as a JSON object. `timestamp` is RFC 3339 and `source` is
+/// the name config uses, so the file reads back without the app.
+fn to_json(msg: &Message) -> Value {
+    json!({
+        "id": msg.id,
+        "source": format!("{:?}", msg.source).to_lowercase(),
+        "channel_id": msg.channel_id,
+        "author": msg.author,
+        "timestamp": msg.timestamp.to_rfc3339(),
+        "content": msg.content,
+        "reply_to_id": msg.reply_to_id,
+        "edited": msg.edited,
+        "attachments": msg.attachments.iter().map(|a| json!({
+            "filename": a.filename,
+            "url": a.url,
+            "file_type": format!("{:?}", a.file_type),
+            "size": a.size,
+        })).collect::<Vec<_>>(),
+        "reactions": msg.reactions.iter().map(|r| json!({ "emoji": r.emoji, "count": r.count })).collect::<Vec<_>>(),
+    })
+}
+
+/// Reads back a JSON export, naming the offending message and field when
+/// the file doesn't have the expected shape
+pub fn from_json(contents: &str) -> Result<Vec<Message>, String> {
+    let value: Value = serde_json::from_str(contents).map_err(|e| format!("Not valid JSON: {}", e))?;
+    let items = value.as_array().ok_or("Expected a JSON array of messages, as written by `friend export --format json`")?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| from_json_message(item).map_err(|e| format!("Message {}: {}", index + 1, e)))
+        .collect()
+}
+
+fn from_json_message(item: &Value) -> Result<Message, String> {
+    let string = |key: &str| item[key].as_str().ok_or_else(|| format!("missing string \"{}\"", key));
+    let source_name = string("source")?;
+    let timestamp = string("timestamp")?;
+
+    let attachments = item["attachments"]
+        .as_array()
+        .map(|attachments| {
+            attachments
+                .iter()
+                .map(|a| {
+                    Ok(Attachment {
+                        filename: a["filename"].as_str().ok_or("attachment is missing \"filename\"")?.to_string(),
+                        url: a["url"].as_str().ok_or("attachment is missing \"url\"")?.to_string(),
+                        file_type: match a["file_type"].as_str() {
+                            Some("Image") => AttachmentType::Image,
+                            Some("Video") => AttachmentType::Video,
+                            Some("Audio") => AttachmentType::Audio,
+                            Some("Document") => AttachmentType::Document,
+                            Some("Archive") => AttachmentType::Archive,
+                            _ => AttachmentType::Other,
+                        },
+                        size: a["size"].as_u64(),
+                        // Downloads are per machine
+                        local_path: None,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(Message {
+        id: item["id"].as_u64().ok_or("missing numeric \"id\"")?,
+        source: MessageSource::from_name(source_name).ok_or_else(|| format!("unknown source {:?}", source_name))?,
+        content: string("content")?.to_string(),
+        timestamp: DateTime::parse_from_rfc3339(timestamp)
+            .map_err(|e| format!("invalid timestamp {:?}: {}", timestamp, e))?
+            .with_timezone(&Utc),
+        author: string("author")?.to_string(),
+        attachments,
+        reactions: item["reactions"]
+            .as_array()
+            .map(|reactions| {
+                reactions
+                    .iter()
+                    .filter_map(|r| Some(Reaction { emoji: r["emoji"].as_str()?.to_string(), count: r["count"].as_u64()? as u32 }))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        channel_id: item["channel_id"].as_str().map(str::to_string),
+        reply_to_id: item["reply_to_id"].as_u64(),
+        edited: item["edited"].as_bool().unwrap_or(false),
+        send_status: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_export_reads_back() {
+        let message = Message {
+            id: u64::MAX - 1,
+            source: MessageSource::Discord,
+            content: "line one\nline two".to_string(),
+            timestamp: DateTime::parse_from_rfc3339("2024-05-01T14:02:05Z").unwrap().with_timezone(&Utc),
+            author: "alice".to_string(),
+            attachments: vec![Attachment {
+                filename: "log.txt".to_string(),
+                url: "https://cdn.example/log.txt".to_string(),
+                file_type: AttachmentType::Document,
+                size: Some(12),
+                local_path: Some("/tmp/log.txt".to_string()),
+            }],
+            reactions: vec![Reaction { emoji: "👍".to_string(), count: 2 }],
+            channel_id: Some("123".to_string()),
+            reply_to_id: Some(7),
+            edited: true,
+            send_status: None,
+        };
+
+        let imported = from_json(&render(std::slice::from_ref(&message), "test", Format::Json)).unwrap();
+        assert_eq!(imported.len(), 1);
+        let imported = &imported[0];
+        assert_eq!((imported.id, imported.source), (message.id, message.source));
+        assert_eq!(imported.content, message.content);
+        assert_eq!(imported.timestamp, message.timestamp);
+        assert_eq!(imported.channel_id, message.channel_id);
+        assert_eq!(imported.reply_to_id, Some(7));
+        assert!(imported.edited);
+        assert_eq!(imported.attachments[0].size, Some(12));
+        assert_eq!(imported.attachments[0].local_path, None);
+        assert_eq!(imported.reactions[0].count, 2);
+    }
+
+    #[test]
+    fn rejects_malformed_messages_with_their_position() {
+        let error = from_json(r#"[{"id": 1, "source": "discord", "content": "hi", "author": "a"}]"#).unwrap_err();
+        assert_eq!(error, "Message 1: missing string \"timestamp\"");
+        assert!(from_json(r#"{"messages": []}"#).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+/// Turns an HTML message body (e.g. a toot or a web hook's rendered text)
+/// into plain text: line breaks and paragraphs become newlines, list items
+/// get bullets, entities are decoded and every other tag is dropped, which
+/// keeps link text
+pub fn strip_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    // Text inside <script> and <style> isn't meant to be read
+    let mut skipping: Option<String> = None;
+
+    while let Some(start) = rest.find('<') {
+        if skipping.is_none() {
+            push_text(&mut out, &rest[..start]);
+        }
+        let Some(end) = rest[start..].find('>') else {
+            // A stray `<` rather than a tag
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+
+        if let Some(skipped) = &skipping {
+            if closing && *skipped == name {
+                skipping = None;
+            }
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("script" | "style", false) => skipping = Some(name),
+            ("br", _) => out.push('\n'),
+            ("p" | "div" | "blockquote" | "pre" | "ul" | "ol" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => out.push_str("\n\n"),
+            ("li", false) => {
+                end_line(&mut out);
+                out.push_str("• ");
+            }
+            ("li" | "tr", true) => out.push('\n'),
+            _ => {}
+        }
+    }
+    if skipping.is_none() {
+        push_text(&mut out, rest);
+    }
+
+    // Tidy up what the block tags left behind
+    let lines: Vec<&str> = out.lines().map(str::trim).collect();
+    let mut text = String::new();
+    let mut blank_run = 0;
+    for line in lines {
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run > 1 {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+    text.trim().to_string()
+}
+
+/// Appends text between tags. Like a browser, runs of whitespace
+/// (including source line breaks) collapse to one space.
+fn push_text(out: &mut String, text: &str) {
+    for c in decode_entities(text).chars() {
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !out.ends_with([' ', '\n']) && !out.is_empty() {
+                out.push(' ');
+            }
+        } else {
+            out.push(if c == '\u{a0}' { ' ' } else { c });
+        }
+    }
+}
+
+fn end_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Decodes named entities common in chat content and every numeric one.
+/// Unknown entities are kept as written.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..].find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end + 1];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end + 2))
+        });
+
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_a_toot_to_readable_text() {
+        let toot = "<p>Deploy is <strong>done</strong> &amp; green<br>see <a href=\"https://ci.example/1\"><span>ci.example/1</span></a></p><p>Thanks &#x1F680;</p>";
+        assert_eq!(strip_html(toot), "Deploy is done & green\nsee ci.example/1\n\nThanks 🚀");
+    }
+
+    #[test]
+    fn keeps_lists_and_ignores_scripts_and_stray_brackets() {
+        let html = "<ul>\n  <li>one</li>\n  <li>two &lt;3</li>\n</ul><script>alert(1)</script>a < b &bogus;";
+        assert_eq!(strip_html(html), "• one\n• two <3\n\na < b &bogus;");
+    }
+}
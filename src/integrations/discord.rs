@@ -1,32 +1,270 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use reqwest::Client;
+use futures::{SinkExt, StreamExt};
+use reqwest::{Client, StatusCode};
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use std::path::Path;
+use std::time::Duration;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
-use crate::{Message, MessageSource, Attachment, AttachmentType};
-use super::MessageProvider;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use crate::{Message, MessageSource, Attachment, Reaction};
+use super::{detect_attachment_type, ensure_success, LiveEvent, MessageProvider};
+
+const GATEWAY_URL: &str = "wss://gateway.discord.gg/?v=10&encoding=json";
+// GUILD_MESSAGES | DIRECT_MESSAGES | MESSAGE_CONTENT
+const GATEWAY_INTENTS: u64 = (1 << 9) | (1 << 12) | (1 << 15);
+const MAX_GATEWAY_BACKOFF: Duration = Duration::from_secs(60);
+/// Times a rate-limited request is retried before giving up
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
 
 pub struct DiscordProvider {
     user_token: String,
     channel_id: String,
+    channel_name: Option<String>,
+    /// Set for server channels, where authors may have a nickname
+    guild_id: Option<String>,
+    /// Server nicknames by user id (`None` if they have none), so each
+    /// author is only looked up once
+    nicknames: Mutex<HashMap<String, Option<String>>>,
     client: Client,
 }
 
+/// How a channel is shown: `#name` for server channels, `@name` for DMs and
+/// the member list for unnamed group DMs
+fn channel_label(channel: &Value) -> Option<String> {
+    if let Some(name) = channel["name"].as_str().filter(|name| !name.is_empty()) {
+        return Some(match channel["type"].as_u64() {
+            Some(1 | 3) => format!("@{}", name),
+            _ => format!("#{}", name),
+        });
+    }
+
+    let recipients: Vec<&str> = channel["recipients"]
+        .as_array()?
+        .iter()
+        .filter_map(|user| user["global_name"].as_str().or_else(|| user["username"].as_str()))
+        .collect();
+    if recipients.is_empty() {
+        None
+    } else {
+        Some(format!("@{}", recipients.join(", ")))
+    }
+}
+
+/// Finds the channels to follow beyond the configured ids: the account's
+/// DMs and/or every text channel of the given guilds. A guild that can't be
+/// listed is reported and skipped.
+pub async fn discover_channels(
+    client: &Client,
+    user_token: &str,
+    include_dms: bool,
+    guild_ids: &[String],
+) -> Vec<DiscordProvider> {
+    let get = |url: String| async move {
+        let response = client.get(&url).header("Authorization", user_token).send().await?;
+        let channels: Vec<Value> = ensure_success("Discord", response).await?.json().await?;
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(channels)
+    };
+
+    let mut channels = Vec::new();
+    if include_dms {
+        match get("https://discord.com/api/v10/users/@me/channels".to_string()).await {
+            Ok(dms) => channels.extend(dms),
+            Err(e) => eprintln!("Warning: Failed to list Discord DMs: {}", e),
+        }
+    }
+    for guild_id in guild_ids {
+        match get(format!("https://discord.com/api/v10/guilds/{}/channels", guild_id)).await {
+            Ok(guild_channels) => channels.extend(
+                guild_channels
+                    .into_iter()
+                    .filter(|channel| matches!(channel["type"].as_u64(), Some(0 | 5))),
+            ),
+            Err(e) => eprintln!("Warning: Failed to list channels of Discord guild {}: {}", guild_id, e),
+        }
+    }
+
+    channels
+        .iter()
+        .filter_map(|channel| DiscordProvider::from_channel(user_token.to_string(), channel, client.clone()))
+        .collect()
+}
+
 impl DiscordProvider {
-    pub fn new(user_token: String, channel_id: String) -> Self {
+    pub fn new(user_token: String, channel_id: String, client: Client) -> Self {
         Self {
             user_token,
             channel_id,
-            client: Client::new(),
+            channel_name: None,
+            guild_id: None,
+            nicknames: Mutex::default(),
+            client,
+        }
+    }
+
+    /// For a channel object already fetched from the API, e.g. by discovery
+    fn from_channel(user_token: String, channel: &Value, client: Client) -> Option<Self> {
+        let mut provider = Self::new(user_token, channel["id"].as_str()?.to_string(), client);
+        provider.channel_name = channel_label(channel);
+        provider.guild_id = channel["guild_id"].as_str().map(str::to_string);
+        Some(provider)
+    }
+
+    /// Sends an authorized API request. A 429 is waited out for as long as
+    /// its `Retry-After` says and retried; any other failure status becomes
+    /// an error naming it.
+    async fn request(
+        &self,
+        build: impl Fn(&Client) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.send_throttled(build).await?;
+        ensure_success("Discord", response).await
+    }
+
+    /// Like `request`, but hands back the final response whatever its
+    /// status, for callers that explain some statuses themselves
+    async fn send_throttled(
+        &self,
+        build: impl Fn(&Client) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+        let mut retries = 0;
+        loop {
+            let response = build(&self.client)
+                .header("Authorization", &self.user_token)
+                .send()
+                .await?;
+            let status = response.status();
+
+            if status == StatusCode::TOO_MANY_REQUESTS && retries < MAX_RATE_LIMIT_RETRIES {
+                retries += 1;
+                // Seconds, possibly fractional
+                let retry_after = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .unwrap_or(1.0);
+                tokio::time::sleep(Duration::from_secs_f64(retry_after.clamp(0.0, 60.0))).await;
+                continue;
+            }
+            return Ok(response);
+        }
+    }
+
+    /// Looks up the channel's name for display. Failures are ignored and the
+    /// channel id is shown instead.
+    pub async fn resolve_channel_name(&mut self) {
+        let url = format!("https://discord.com/api/v10/channels/{}", self.channel_id);
+        let response = self.client
+            .get(&url)
+            .header("Authorization", &self.user_token)
+            .send()
+            .await;
+
+        if let Ok(response) = response
+            && let Ok(channel) = response.json::<Value>().await
+        {
+            self.channel_name = channel_label(&channel);
+            self.guild_id = channel["guild_id"].as_str().map(str::to_string);
         }
     }
 
-    fn parse_message(&self, msg: &Value) -> Option<Message> {
+    /// Looks up the server nicknames of authors not seen before. Members who
+    /// left and failed lookups are cached as having none, so no author is
+    /// asked about twice.
+    async fn resolve_nicknames(&self, messages: &[Value]) {
+        let Some(guild_id) = &self.guild_id else {
+            return;
+        };
+
+        let unknown: HashSet<&str> = {
+            let nicknames = self.nicknames.lock().unwrap();
+            messages
+                .iter()
+                // Gateway payloads already carry the member
+                .filter(|msg| msg["member"].is_null())
+                .filter_map(|msg| msg["author"]["id"].as_str())
+                .filter(|id| !nicknames.contains_key(*id))
+                .collect()
+        };
+
+        for user_id in unknown {
+            let url = format!("https://discord.com/api/v10/guilds/{}/members/{}", guild_id, user_id);
+            let nick = match self.request(|client| client.get(&url)).await {
+                Ok(response) => response
+                    .json::<Value>()
+                    .await
+                    .ok()
+                    .and_then(|member| member["nick"].as_str().map(str::to_string)),
+                Err(_) => None,
+            };
+            self.nicknames.lock().unwrap().insert(user_id.to_string(), nick);
+        }
+    }
+
+    /// Condenses embeds (bot posts, link previews) into plain text: title,
+    /// description and `name: value` fields, one block per embed.
+    fn summarize_embeds(embeds: &[Value]) -> String {
+        embeds
+            .iter()
+            .filter_map(|embed| {
+                let mut lines = Vec::new();
+                if let Some(author) = embed["author"]["name"].as_str() {
+                    lines.push(author.to_string());
+                }
+                if let Some(title) = embed["title"].as_str() {
+                    lines.push(title.to_string());
+                }
+                if let Some(description) = embed["description"].as_str() {
+                    lines.push(description.to_string());
+                }
+                if let Some(fields) = embed["fields"].as_array() {
+                    for field in fields {
+                        let name = field["name"].as_str().unwrap_or("").trim();
+                        let value = field["value"].as_str().unwrap_or("").trim();
+                        match (name.is_empty(), value.is_empty()) {
+                            (false, false) => lines.push(format!("{}: {}", name, value)),
+                            (false, true) => lines.push(name.to_string()),
+                            (true, false) => lines.push(value.to_string()),
+                            (true, true) => {}
+                        }
+                    }
+                }
+                // Link previews may carry nothing but a url
+                if lines.is_empty()
+                    && let Some(url) = embed["url"].as_str()
+                {
+                    lines.push(url.to_string());
+                }
+                if lines.is_empty() { None } else { Some(lines.join("\n")) }
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn parse_message(msg: &Value, channel_id: &str) -> Option<Message> {
         let id = msg["id"].as_str()?.parse::<u64>().ok()?;
-        let content = msg["content"].as_str().unwrap_or("").to_string();
-        let author = msg["author"]["username"].as_str().unwrap_or("Unknown");
+        let mut content = msg["content"].as_str().unwrap_or("").to_string();
+        
+        if let Some(embeds) = msg["embeds"].as_array() {
+            let summary = Self::summarize_embeds(embeds);
+            if !summary.is_empty() {
+                if !content.is_empty() {
+                    content.push_str("\n\n");
+                }
+                content.push_str(&summary);
+            }
+        }
+        // Server nickname, then the account's display name, then its handle
+        let author = msg["member"]["nick"]
+            .as_str()
+            .or_else(|| msg["author"]["global_name"].as_str())
+            .or_else(|| msg["author"]["username"].as_str())
+            .unwrap_or("Unknown");
         let timestamp_str = msg["timestamp"].as_str()?;
         
         let timestamp = DateTime::parse_from_rfc3339(timestamp_str)
@@ -41,34 +279,37 @@ impl DiscordProvider {
                     let filename = attachment["filename"].as_str().unwrap_or("attachment").to_string();
                     let size = attachment["size"].as_u64();
                     
-                    let file_type = if let Some(content_type) = attachment["content_type"].as_str() {
-                        match content_type.split('/').next().unwrap_or("") {
-                            "image" => AttachmentType::Image,
-                            "video" => AttachmentType::Video,
-                            "audio" => AttachmentType::Audio,
-                            "text" | "application" => AttachmentType::Document,
-                            _ => AttachmentType::Other,
-                        }
-                    } else {
-                        match filename.split('.').last().unwrap_or("") {
-                            "jpg" | "jpeg" | "png" | "gif" | "webp" => AttachmentType::Image,
-                            "mp4" | "avi" | "mov" | "mkv" => AttachmentType::Video,
-                            "mp3" | "wav" | "ogg" => AttachmentType::Audio,
-                            "pdf" | "doc" | "docx" | "txt" => AttachmentType::Document,
-                            _ => AttachmentType::Other,
-                        }
-                    };
+                    let file_type = detect_attachment_type(&filename, attachment["content_type"].as_str());
                     
                     attachments.push(Attachment {
                         filename,
                         url: url.to_string(),
                         file_type,
                         size,
+                        local_path: None,
                     });
                 }
             }
         }
         
+        let reactions = msg["reactions"]
+            .as_array()
+            .map(|reactions| {
+                reactions
+                    .iter()
+                    .filter_map(|reaction| {
+                        let emoji = &reaction["emoji"];
+                        let name = emoji["name"].as_str()?;
+                        Some(Reaction {
+                            // Custom server emoji only have a name, not a glyph
+                            emoji: if emoji["id"].is_string() { format!(":{}:", name) } else { name.to_string() },
+                            count: reaction["count"].as_u64().unwrap_or(0) as u32,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        
         Some(Message {
             id,
             source: MessageSource::Discord,
@@ -76,7 +317,11 @@ impl DiscordProvider {
             timestamp,
             author: author.to_string(),
             attachments,
-            channel_id: Some(self.channel_id.clone()),
+            reactions,
+            channel_id: Some(channel_id.to_string()),
+            reply_to_id: msg["message_reference"]["message_id"].as_str().and_then(|id| id.parse().ok()),
+            edited: msg["edited_timestamp"].is_string(),
+            send_status: None,
         })
     }
 }
@@ -91,41 +336,53 @@ impl MessageProvider for DiscordProvider {
             query_params.push(("after", since_time.timestamp().to_string()));
         }
         
-        let response = self.client
-            .get(&url)
-            .header("Authorization", &self.user_token)
-            .query(&query_params)
-            .send()
-            .await?;
-            
+        let response = self.request(|client| client.get(&url).query(&query_params)).await?;
         let messages_data: Vec<Value> = response.json().await?;
         
+        self.resolve_nicknames(&messages_data).await;
+        let nicknames = self.nicknames.lock().unwrap().clone();
+        
         let mut messages = Vec::new();
         for msg_data in messages_data {
-            if let Some(parsed_msg) = self.parse_message(&msg_data) {
+            if let Some(mut parsed_msg) = Self::parse_message(&msg_data, &self.channel_id) {
+                if let Some(Some(nick)) = msg_data["author"]["id"].as_str().and_then(|id| nicknames.get(id)) {
+                    parsed_msg.author = nick.clone();
+                }
                 messages.push(parsed_msg);
             }
         }
         
-        messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)); // Newest first
+        messages.sort_by_key(|m| std::cmp::Reverse(m.timestamp)); // Newest first
         Ok(messages)
     }
 
-    async fn send_message(&self, content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn send_message(&self, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
         let url = format!("https://discord.com/api/v10/channels/{}/messages", self.channel_id);
         
         let payload = serde_json::json!({
             "content": content
         });
         
-        self.client
-            .post(&url)
-            .header("Authorization", &self.user_token)
-            .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await?;
-            
+        let sent: Value = self.request(|client| client.post(&url).json(&payload)).await?.json().await?;
+
+        Ok(sent["id"].as_str().and_then(|id| id.parse().ok()))
+    }
+
+    async fn send_reply(&self, _channel_id: Option<&str>, message_id: u64, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let payload = serde_json::json!({
+            "content": content,
+            "message_reference": { "message_id": message_id.to_string() },
+        });
+
+        let url = format!("https://discord.com/api/v10/channels/{}/messages", self.channel_id);
+        let sent: Value = self.request(|client| client.post(&url).json(&payload)).await?.json().await?;
+        Ok(sent["id"].as_str().and_then(|id| id.parse().ok()))
+    }
+
+    /// Discord shows the indicator for 10 seconds, hence the default 8s cadence
+    async fn send_typing(&self, _channel_id: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("https://discord.com/api/v10/channels/{}/typing", self.channel_id);
+        self.request(|client| client.post(&url)).await?;
         Ok(())
     }
 
@@ -150,12 +407,13 @@ impl MessageProvider for DiscordProvider {
             .text("payload_json", payload_json.to_string())
             .part("files[0]", file_part);
         
-        self.client
+        let response = self.client
             .post(&url)
             .header("Authorization", &self.user_token)
             .multipart(form)
             .send()
             .await?;
+        ensure_success("Discord", response).await?;
             
         Ok(())
     }
@@ -165,6 +423,7 @@ impl MessageProvider for DiscordProvider {
             .get(&attachment.url)
             .send()
             .await?;
+        let response = ensure_success("Discord", response).await?;
             
         let bytes = response.bytes().await?;
         
@@ -174,19 +433,37 @@ impl MessageProvider for DiscordProvider {
         Ok(())
     }
 
-    async fn delete_message(&self, message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn delete_message(&self, _channel_id: Option<&str>, message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let url = format!("https://discord.com/api/v10/channels/{}/messages/{}", self.channel_id, message_id);
         
-        let response = self.client
-            .delete(&url)
-            .header("Authorization", &self.user_token)
-            .send()
+        self.request(|client| client.delete(&url)).await?;
+        Ok(())
+    }
+
+    fn supports_delete(&self) -> bool {
+        true
+    }
+
+    async fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.request(|client| client.get("https://discord.com/api/v10/users/@me")).await?;
+        Ok(())
+    }
+
+    fn supports_edit(&self) -> bool {
+        true
+    }
+
+    async fn edit_message(&self, _channel_id: Option<&str>, message_id: u64, new_content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("https://discord.com/api/v10/channels/{}/messages/{}", self.channel_id, message_id);
+
+        let response = self
+            .send_throttled(|client| client.patch(&url).json(&serde_json::json!({ "content": new_content })))
             .await?;
-            
-        if !response.status().is_success() {
-            return Err(format!("Failed to delete message: {}", response.status()).into());
+
+        if response.status() == StatusCode::FORBIDDEN {
+            return Err("Only your own messages can be edited".into());
         }
-        
+        ensure_success("Discord", response).await?;
         Ok(())
     }
 
@@ -201,10 +478,139 @@ impl MessageProvider for DiscordProvider {
     fn provider_key(&self) -> String {
         format!("discord_{}", self.channel_id)
     }
+
+    fn display_name(&self) -> String {
+        match &self.channel_name {
+            Some(label) => format!("Discord {}", label),
+            None => format!("Discord #{}", self.channel_id),
+        }
+    }
     
     async fn fetch_messages_since_id(&self, _last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
         // For now, just use the regular fetch method
         // TODO: Implement proper incremental fetch using Discord's after parameter
         self.fetch_messages(None).await
     }
-}
\ No newline at end of file
+}
+
+/// Listens on the Discord gateway and forwards MESSAGE_CREATE events for the
+/// given channels as they happen. Reconnects with exponential backoff when
+/// the connection drops; REST polling keeps running regardless, so a dead
+/// gateway only costs latency.
+pub fn spawn_gateway(user_token: String, channel_ids: Vec<String>, events: UnboundedSender<LiveEvent>) {
+    tokio::spawn(async move {
+        let channel_ids: HashSet<String> = channel_ids.into_iter().collect();
+        let mut backoff = Duration::from_secs(1);
+
+        loop {
+            let result = run_gateway(&user_token, &channel_ids, &events, &mut backoff).await;
+            if events.is_closed() {
+                return; // The app has shut down
+            }
+
+            let reason = match result {
+                Ok(()) => "connection closed".to_string(),
+                Err(e) => e.to_string(),
+            };
+            let _ = events.send(LiveEvent::Disconnected(MessageSource::Discord, reason));
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_GATEWAY_BACKOFF);
+        }
+    });
+}
+
+async fn run_gateway(
+    user_token: &str,
+    channel_ids: &HashSet<String>,
+    events: &UnboundedSender<LiveEvent>,
+    backoff: &mut Duration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (socket, _) = tokio_tungstenite::connect_async(GATEWAY_URL).await?;
+    let (mut write, mut read) = socket.split();
+
+    // The first payload is always Hello (op 10) with the heartbeat interval
+    let heartbeat_interval = loop {
+        match read.next().await.ok_or("gateway closed before hello")?? {
+            WsMessage::Text(text) => {
+                let payload: Value = serde_json::from_str(text.as_str())?;
+                if payload["op"].as_u64() != Some(10) {
+                    return Err(format!("expected gateway hello, got op {}", payload["op"]).into());
+                }
+                break Duration::from_millis(payload["d"]["heartbeat_interval"].as_u64().unwrap_or(41_250));
+            }
+            WsMessage::Close(frame) => return Err(format!("gateway closed: {:?}", frame).into()),
+            _ => continue,
+        }
+    };
+
+    let identify = serde_json::json!({
+        "op": 2,
+        "d": {
+            "token": user_token,
+            "intents": GATEWAY_INTENTS,
+            "properties": {
+                "os": std::env::consts::OS,
+                "browser": "friend",
+                "device": "friend"
+            }
+        }
+    });
+    write.send(WsMessage::Text(identify.to_string().into())).await?;
+
+    let mut heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + heartbeat_interval, heartbeat_interval);
+    let mut sequence: Option<u64> = None;
+
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                let payload = serde_json::json!({ "op": 1, "d": sequence });
+                write.send(WsMessage::Text(payload.to_string().into())).await?;
+            }
+            frame = read.next() => {
+                let text = match frame {
+                    None => return Ok(()),
+                    Some(frame) => match frame? {
+                        WsMessage::Text(text) => text,
+                        WsMessage::Close(frame) => return Err(format!("gateway closed: {:?}", frame).into()),
+                        _ => continue,
+                    },
+                };
+
+                let payload: Value = serde_json::from_str(text.as_str())?;
+                if let Some(s) = payload["s"].as_u64() {
+                    sequence = Some(s);
+                }
+
+                match payload["op"].as_u64() {
+                    // Dispatch
+                    Some(0) => match payload["t"].as_str() {
+                        Some("READY") => {
+                            *backoff = Duration::from_secs(1);
+                            let _ = events.send(LiveEvent::Connected(MessageSource::Discord, None));
+                        }
+                        Some("MESSAGE_CREATE") => {
+                            let data = &payload["d"];
+                            if let Some(channel_id) = data["channel_id"].as_str()
+                                && channel_ids.contains(channel_id)
+                                && let Some(message) = DiscordProvider::parse_message(data, channel_id)
+                                && events.send(LiveEvent::Message(message)).is_err()
+                            {
+                                return Ok(());
+                            }
+                        }
+                        _ => {}
+                    },
+                    // Heartbeat requested by the server
+                    Some(1) => {
+                        let payload = serde_json::json!({ "op": 1, "d": sequence });
+                        write.send(WsMessage::Text(payload.to_string().into())).await?;
+                    }
+                    // Reconnect / invalid session
+                    Some(7) | Some(9) => return Err("gateway requested a reconnect".into()),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use reqwest::Client;
 use serde_json::Value;
-use crate::{Message, MessageSource};
-use super::MessageProvider;
+use crate::{stable_hash, Message, MessageSource};
+use super::{ensure_success, MessageProvider};
 
 pub struct GitHubProvider {
     token: String,
@@ -12,11 +12,11 @@ pub struct GitHubProvider {
 }
 
 impl GitHubProvider {
-    pub fn new(token: String, username: String) -> Self {
+    pub fn new(token: String, username: String, client: Client) -> Self {
         Self {
             token,
             username,
-            client: Client::new(),
+            client,
         }
     }
 
@@ -40,7 +40,60 @@ impl GitHubProvider {
             timestamp,
             author: "GitHub".to_string(),
             attachments: vec![],
-            channel_id: None,
+            reactions: vec![],
+            // API URL of the issue or PR, used to load its discussion
+            channel_id: notif["subject"]["url"].as_str().map(str::to_string),
+            reply_to_id: None,
+            edited: false,
+            send_status: None,
+        })
+    }
+
+    /// GETs an API URL, turning rate limiting into a readable error
+    async fn get_json(&self, url: &str, query: &[(&str, String)]) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.client
+            .get(url)
+            .header("Authorization", format!("token {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .query(query)
+            .send()
+            .await?;
+
+        let status = response.status().as_u16();
+        let header = |name: &str| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        if status == 429 || (status == 403 && header("x-ratelimit-remaining").as_deref() == Some("0")) {
+            let resets = header("retry-after")
+                .and_then(|secs| secs.parse::<i64>().ok())
+                .map(|secs| Utc::now().timestamp() + secs)
+                .or_else(|| header("x-ratelimit-reset").and_then(|at| at.parse::<i64>().ok()))
+                .and_then(|at| DateTime::from_timestamp(at, 0));
+            return Err(match resets {
+                Some(at) => format!("GitHub rate limit reached, try again after {}", at.with_timezone(&Local).format("%H:%M")),
+                None => "GitHub rate limit reached".to_string(),
+            }
+            .into());
+        }
+
+        Ok(ensure_success("GitHub", response).await?.json().await?)
+    }
+
+    fn parse_comment(&self, comment: &Value, parent_id: u64, url: &str) -> Option<Message> {
+        let timestamp = DateTime::parse_from_rfc3339(comment["created_at"].as_str()?)
+            .ok()?
+            .with_timezone(&Utc);
+
+        Some(Message {
+            id: comment["id"].as_u64()?,
+            source: MessageSource::Github,
+            content: comment["body"].as_str().unwrap_or("").trim_end().to_string(),
+            timestamp,
+            author: comment["user"]["login"].as_str().unwrap_or("Unknown").to_string(),
+            attachments: vec![],
+            reactions: vec![],
+            channel_id: Some(url.to_string()),
+            reply_to_id: Some(parent_id),
+            edited: false,
+            send_status: None,
         })
     }
 
@@ -80,7 +133,11 @@ impl GitHubProvider {
             timestamp,
             author: actor.to_string(),
             attachments: vec![],
+            reactions: vec![],
             channel_id: None,
+            reply_to_id: None,
+            edited: false,
+            send_status: None,
         })
     }
 }
@@ -93,43 +150,29 @@ impl MessageProvider for GitHubProvider {
         let notifications_url = "https://api.github.com/notifications";
         let events_url = format!("https://api.github.com/users/{}/events", self.username);
         
-        let auth_header = format!("token {}", self.token);
-        
-        let notifications_response = self.client
-            .get(notifications_url)
-            .header("Authorization", &auth_header)
-            .header("User-Agent", "friend-tui")
-            .send()
-            .await?;
-            
-        if let Ok(notifications) = notifications_response.json::<Vec<Value>>().await {
+        let notifications = self.get_json(notifications_url, &[]).await?;
+        if let Some(notifications) = notifications.as_array() {
             for notif in notifications {
-                if let Some(msg) = self.parse_notification(&notif) {
+                if let Some(msg) = self.parse_notification(notif) {
                     all_messages.push(msg);
                 }
             }
         }
         
-        let events_response = self.client
-            .get(&events_url)
-            .header("Authorization", &auth_header)
-            .header("User-Agent", "friend-tui")
-            .send()
-            .await?;
-            
-        if let Ok(events) = events_response.json::<Vec<Value>>().await {
+        let events = self.get_json(&events_url, &[]).await?;
+        if let Some(events) = events.as_array() {
             for event in events {
-                if let Some(msg) = self.parse_event(&event) {
+                if let Some(msg) = self.parse_event(event) {
                     all_messages.push(msg);
                 }
             }
         }
         
-        all_messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)); // Already newest first - keep it
+        all_messages.sort_by_key(|m| std::cmp::Reverse(m.timestamp)); // Already newest first - keep it
         Ok(all_messages)
     }
 
-    async fn send_message(&self, _content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn send_message(&self, _content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
         Err("GitHub does not support sending messages through this interface".into())
     }
 
@@ -141,7 +184,7 @@ impl MessageProvider for GitHubProvider {
         Err("GitHub attachments are not downloadable through this interface".into())
     }
 
-    async fn delete_message(&self, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn delete_message(&self, _channel_id: Option<&str>, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         Err("GitHub does not support deleting messages through this interface".into())
     }
 
@@ -156,9 +199,60 @@ impl MessageProvider for GitHubProvider {
     fn provider_key(&self) -> String {
         format!("github_{}", self.username)
     }
+
+    fn display_name(&self) -> String {
+        format!("GitHub ({})", self.username)
+    }
     
     async fn fetch_messages_since_id(&self, _last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
         // For now, just use the regular fetch method
         self.fetch_messages(None).await
     }
+
+    async fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.get_json("https://api.github.com/user", &[]).await.map(|_| ())
+    }
+
+    /// The issue or PR body followed by its most recent comments
+    async fn fetch_thread(&self, parent: &Message) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        // Only issue and PR notifications have a discussion to show
+        let Some(url) = parent.channel_id.as_deref().filter(|url| url.contains("/issues/") || url.contains("/pulls/")) else {
+            return Ok(Vec::new());
+        };
+
+        let item = self.get_json(url, &[]).await?;
+        let mut thread = Vec::new();
+        if let Some(created) = item["created_at"].as_str().and_then(|t| DateTime::parse_from_rfc3339(t).ok()) {
+            thread.push(Message {
+                id: stable_hash(url),
+                source: MessageSource::Github,
+                content: item["body"].as_str().unwrap_or("(no description)").trim_end().to_string(),
+                timestamp: created.with_timezone(&Utc),
+                author: item["user"]["login"].as_str().unwrap_or("Unknown").to_string(),
+                attachments: vec![],
+                reactions: vec![],
+                channel_id: Some(url.to_string()),
+                reply_to_id: Some(parent.id),
+                edited: false,
+                send_status: None,
+            });
+        }
+
+        // PR conversation comments live on the matching issue. Comments come
+        // oldest first, so jump to the last page for the recent ones.
+        const PER_PAGE: u64 = 30;
+        let comment_count = item["comments"].as_u64().unwrap_or(0);
+        if comment_count > 0 {
+            let comments_url = format!("{}/comments", url.replace("/pulls/", "/issues/"));
+            let last_page = comment_count.div_ceil(PER_PAGE);
+            let comments = self
+                .get_json(&comments_url, &[("per_page", PER_PAGE.to_string()), ("page", last_page.to_string())])
+                .await?;
+            if let Some(comments) = comments.as_array() {
+                thread.extend(comments.iter().filter_map(|c| self.parse_comment(c, parent.id, url)));
+            }
+        }
+
+        Ok(thread)
+    }
 }
\ No newline at end of file
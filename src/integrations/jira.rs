@@ -2,8 +2,8 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde_json::Value;
-use crate::{Message, MessageSource};
-use super::MessageProvider;
+use crate::{stable_hash, Message, MessageSource};
+use super::{ensure_success, Assignee, MessageProvider, Transition};
 
 pub struct JiraProvider {
     base_url: String,
@@ -13,14 +13,44 @@ pub struct JiraProvider {
     client: Client,
 }
 
+/// Flattens an Atlassian Document Format node into plain text, one line per
+/// block
+fn adf_to_text(node: &Value, out: &mut String) {
+    match node["type"].as_str() {
+        Some("text") => out.push_str(node["text"].as_str().unwrap_or("")),
+        Some("hardBreak") => out.push('\n'),
+        Some("mention") => out.push_str(node["attrs"]["text"].as_str().unwrap_or("@someone")),
+        Some("emoji") => out.push_str(node["attrs"]["text"].as_str().unwrap_or("")),
+        _ => {}
+    }
+
+    if let Some(children) = node["content"].as_array() {
+        if node["type"] == "listItem" {
+            out.push_str("• ");
+        }
+        for child in children {
+            adf_to_text(child, out);
+        }
+        if matches!(node["type"].as_str(), Some("paragraph" | "heading" | "codeBlock" | "blockquote")) {
+            out.push('\n');
+        }
+    }
+}
+
+/// The issue key in a Jira `channel_id`, which is `{host}/{key}` so the
+/// same key on two sites stays apart
+pub fn issue_key(channel_id: &str) -> &str {
+    channel_id.rsplit_once('/').map_or(channel_id, |(_, key)| key)
+}
+
 impl JiraProvider {
-    pub fn new(base_url: String, email: String, api_token: String, project_keys: Vec<String>) -> Self {
+    pub fn new(base_url: String, email: String, api_token: String, project_keys: Vec<String>, client: Client) -> Self {
         Self {
             base_url,
             email,
             api_token,
             project_keys,
-            client: Client::new(),
+            client,
         }
     }
 
@@ -38,8 +68,10 @@ impl JiraProvider {
         
         let content = format!("{}: {} (Status: {})", key, summary, status);
         
-        let id = key.chars().filter(|c| c.is_ascii_digit()).collect::<String>()
-            .parse::<u64>().unwrap_or(0);
+        // Hash the site and the whole key so PROJ-123 and OTHER-123, or
+        // PROJ-123 on two sites, get distinct cache ids
+        let channel_id = format!("{}/{}", self.host(), key);
+        let id = stable_hash(&channel_id);
         
         Some(Message {
             id,
@@ -48,16 +80,55 @@ impl JiraProvider {
             timestamp,
             author: assignee.to_string(),
             attachments: vec![],
-            channel_id: None,
+            reactions: vec![],
+            // The issue is the conversation, and this keeps the key recoverable
+            channel_id: Some(channel_id),
+            reply_to_id: None,
+            edited: false,
+            send_status: None,
+        })
+    }
+
+    fn parse_comment(&self, channel_id: &str, parent_id: u64, comment: &Value) -> Option<Message> {
+        let comment_id = comment["id"].as_str()?;
+        let timestamp = DateTime::parse_from_rfc3339(comment["created"].as_str()?)
+            .ok()?
+            .with_timezone(&Utc);
+
+        let mut content = String::new();
+        adf_to_text(&comment["body"], &mut content);
+
+        Some(Message {
+            id: stable_hash(&format!("{}#comment-{}", channel_id, comment_id)),
+            source: MessageSource::Jira,
+            content: content.trim_end().to_string(),
+            timestamp,
+            author: comment["author"]["displayName"].as_str().unwrap_or("Unknown").to_string(),
+            attachments: vec![],
+            reactions: vec![],
+            channel_id: Some(channel_id.to_string()),
+            reply_to_id: Some(parent_id),
+            edited: false,
+            send_status: None,
         })
     }
 
+    fn host(&self) -> String {
+        self.base_url.replace("https://", "").replace("http://", "")
+    }
+
     fn get_auth_header(&self) -> String {
         use base64::Engine;
         let credentials = format!("{}:{}", self.email, self.api_token);
         let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
         format!("Basic {}", encoded)
     }
+
+    /// Sends an API request with credentials, failing on an error status
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+        let response = request.header("Authorization", self.get_auth_header()).send().await?;
+        ensure_success("Jira", response).await
+    }
 }
 
 #[async_trait]
@@ -72,8 +143,10 @@ impl MessageProvider for JiraProvider {
         let mut jql = project_filter;
         
         if let Some(since_time) = since {
-            let since_str = since_time.format("%Y-%m-%d %H:%M").to_string();
-            jql.push_str(&format!(" AND updated >= '{}'", since_str));
+            // Absolute JQL dates are read in the Jira user's timezone, so use a
+            // relative offset instead. Rounding up re-fetches the boundary minute.
+            let minutes_ago = (Utc::now() - since_time).num_minutes().max(0) + 1;
+            jql.push_str(&format!(" AND updated >= \"-{}m\"", minutes_ago));
         }
         
         jql.push_str(" ORDER BY updated DESC");
@@ -86,12 +159,13 @@ impl MessageProvider for JiraProvider {
             ("fields", "summary,status,assignee,updated".to_string()),
         ];
         
-        let response = self.client
-            .get(&url)
-            .header("Authorization", self.get_auth_header())
-            .header("Accept", "application/json")
-            .query(&query_params)
-            .send()
+        let response = self
+            .send(
+                self.client
+                    .get(&url)
+                    .header("Accept", "application/json")
+                    .query(&query_params)
+            )
             .await?;
             
         let data: Value = response.json().await?;
@@ -108,7 +182,7 @@ impl MessageProvider for JiraProvider {
         Ok(messages)
     }
 
-    async fn send_message(&self, content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn send_message(&self, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
         let url = format!("{}/rest/api/3/issue", self.base_url);
         
         let project_key = self.project_keys.first()
@@ -141,15 +215,49 @@ impl MessageProvider for JiraProvider {
             }
         });
         
-        self.client
-            .post(&url)
-            .header("Authorization", self.get_auth_header())
-            .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
+        self
+            .send(
+                self.client
+                    .post(&url)
+                    .header("Content-Type", "application/json")
+                    .json(&payload)
+            )
             .await?;
             
-        Ok(())
+        Ok(None)
+    }
+
+    /// Comments on the issue `channel_id`
+    async fn send_message_to(&self, channel_id: &str, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/rest/api/3/issue/{}/comment", self.base_url, issue_key(channel_id));
+        let payload = serde_json::json!({
+            "body": {
+                "type": "doc",
+                "version": 1,
+                "content": [
+                    {
+                        "type": "paragraph",
+                        "content": [
+                            {
+                                "type": "text",
+                                "text": content
+                            }
+                        ]
+                    }
+                ]
+            }
+        });
+
+        self
+            .send(
+                self.client
+                    .post(&url)
+                    .header("Content-Type", "application/json")
+                    .json(&payload)
+            )
+            .await?;
+
+        Ok(None)
     }
 
     async fn send_message_with_attachment(&self, _content: &str, _attachment_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -160,7 +268,7 @@ impl MessageProvider for JiraProvider {
         Err("Jira attachment downloads not implemented in this interface".into())
     }
 
-    async fn delete_message(&self, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn delete_message(&self, _channel_id: Option<&str>, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         Err("Jira does not support deleting issues through this interface".into())
     }
 
@@ -171,13 +279,162 @@ impl MessageProvider for JiraProvider {
     fn channel_id(&self) -> Option<String> {
         None
     }
+
+    /// Issues belong to the site they were fetched from
+    fn has_channel(&self, channel_id: &str) -> bool {
+        channel_id.rsplit_once('/').is_some_and(|(host, _)| host == self.host())
+    }
     
     fn provider_key(&self) -> String {
-        format!("jira_{}", self.base_url.replace("https://", "").replace("http://", ""))
+        format!("jira_{}", self.host())
+    }
+
+    fn display_name(&self) -> String {
+        format!("Jira ({})", self.host())
     }
     
     async fn fetch_messages_since_id(&self, _last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
-        // For now, just use the regular fetch method
+        // Issue keys aren't ordered by activity; incremental sync goes through
+        // `syncs_by_timestamp` and the `updated` watermark instead
         self.fetch_messages(None).await
     }
-}
\ No newline at end of file
+
+    fn syncs_by_timestamp(&self) -> bool {
+        true
+    }
+
+    async fn fetch_thread(&self, parent: &Message) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(channel_id) = parent.channel_id.as_deref() else {
+            return Ok(Vec::new());
+        };
+        let url = format!("{}/rest/api/3/issue/{}/comment", self.base_url, issue_key(channel_id));
+
+        let data: Value = self
+            .send(
+                self.client
+                    .get(&url)
+                    .header("Accept", "application/json")
+                    .query(&[("orderBy", "created"), ("maxResults", "100")])
+            )
+            .await?
+            .json()
+            .await?;
+
+        Ok(data["comments"]
+            .as_array()
+            .map(|comments| {
+                comments
+                    .iter()
+                    .filter_map(|comment| self.parse_comment(channel_id, parent.id, comment))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self
+            .send(
+                self.client
+                    .get(format!("{}/rest/api/3/myself", self.base_url))
+                    .header("Accept", "application/json")
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list_transitions(&self, channel_id: &str) -> Result<Vec<Transition>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/rest/api/3/issue/{}/transitions", self.base_url, issue_key(channel_id));
+
+        let data: Value = self
+            .send(
+                self.client
+                    .get(&url)
+                    .header("Accept", "application/json")
+            )
+            .await?
+            .json()
+            .await?;
+
+        let transitions = data["transitions"]
+            .as_array()
+            .map(|transitions| {
+                transitions
+                    .iter()
+                    .filter_map(|t| {
+                        let name = t["name"].as_str()?.to_string();
+                        Some(Transition {
+                            id: t["id"].as_str()?.to_string(),
+                            to_status: t["to"]["name"].as_str().unwrap_or(&name).to_string(),
+                            name,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(transitions)
+    }
+
+    async fn apply_transition(&self, channel_id: &str, transition_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/rest/api/3/issue/{}/transitions", self.base_url, issue_key(channel_id));
+
+        self
+            .send(
+                self.client
+                    .post(&url)
+                    .header("Content-Type", "application/json")
+                    .json(&serde_json::json!({ "transition": { "id": transition_id } }))
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn list_assignees(&self, channel_id: &str) -> Result<Vec<Assignee>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/rest/api/3/user/assignable/search", self.base_url);
+
+        let data: Value = self
+            .send(
+                self.client
+                    .get(&url)
+                    .header("Accept", "application/json")
+                    .query(&[("issueKey", issue_key(channel_id)), ("maxResults", "50")])
+            )
+            .await?
+            .json()
+            .await?;
+
+        let assignees = data
+            .as_array()
+            .map(|users| {
+                users
+                    .iter()
+                    .filter_map(|user| {
+                        Some(Assignee {
+                            id: user["accountId"].as_str()?.to_string(),
+                            name: user["displayName"].as_str()?.to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(assignees)
+    }
+
+    async fn assign(&self, channel_id: &str, account_id: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/rest/api/3/issue/{}/assignee", self.base_url, issue_key(channel_id));
+
+        // A null account id unassigns the issue
+        self
+            .send(
+                self.client
+                    .put(&url)
+                    .header("Content-Type", "application/json")
+                    .json(&serde_json::json!({ "accountId": account_id }))
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::{Attachment, Message, MessageSource};
+use super::MessageProvider;
+
+/// Deterministic stand-in for a real provider. Serves canned messages,
+/// records what it was asked for and what was sent, and can be told to fail
+/// or stall.
+pub struct MockProvider {
+    source: MessageSource,
+    channel_id: Option<String>,
+    /// Channels reported by `has_channel`, like the chats of an account
+    known_channels: Vec<String>,
+    messages: Vec<Message>,
+    failure: Option<String>,
+    /// Fail fetches as if the service couldn't be reached
+    offline: bool,
+    delay: Option<Duration>,
+    /// Sync by the newest timestamp, like Jira, instead of the last id
+    by_timestamp: bool,
+    /// Contents passed to `send_message`, shared so tests can inspect them
+    /// after the provider has moved into a manager
+    pub sent: Arc<Mutex<Vec<String>>>,
+    /// Every `last_message_id` passed to `fetch_messages_since_id`
+    pub since_ids: Arc<Mutex<Vec<Option<u64>>>>,
+}
+
+impl MockProvider {
+    pub fn new(source: MessageSource, messages: Vec<Message>) -> Self {
+        Self {
+            source,
+            channel_id: None,
+            known_channels: Vec::new(),
+            messages,
+            failure: None,
+            offline: false,
+            delay: None,
+            by_timestamp: false,
+            sent: Arc::default(),
+            since_ids: Arc::default(),
+        }
+    }
+
+    pub fn with_channel(mut self, channel_id: &str) -> Self {
+        self.channel_id = Some(channel_id.to_string());
+        self
+    }
+
+    pub fn with_known_channel(mut self, channel_id: &str) -> Self {
+        self.known_channels.push(channel_id.to_string());
+        self
+    }
+
+    /// Makes every call return this error
+    pub fn failing(mut self, error: &str) -> Self {
+        self.failure = Some(error.to_string());
+        self
+    }
+
+    /// Makes fetches fail with a connection error
+    pub fn offline(mut self) -> Self {
+        self.offline = true;
+        self
+    }
+
+    /// Makes fetches wait this long before answering
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Makes incremental syncs go by timestamp
+    pub fn syncing_by_timestamp(mut self) -> Self {
+        self.by_timestamp = true;
+        self
+    }
+
+    async fn respond(&self, keep: impl Fn(&Message) -> bool) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        if let Some(error) = &self.failure {
+            return Err(error.clone().into());
+        }
+        if self.offline {
+            return Err(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "mock is offline").into());
+        }
+        Ok(self.messages.iter().filter(|m| keep(m)).cloned().collect())
+    }
+}
+
+/// A message with the given id, `minutes_ago` old
+pub fn message(id: u64, source: MessageSource, minutes_ago: i64) -> Message {
+    Message {
+        id,
+        source,
+        content: format!("message {}", id),
+        timestamp: Utc::now() - chrono::Duration::minutes(minutes_ago),
+        author: "mock".to_string(),
+        attachments: vec![],
+        reactions: vec![],
+        channel_id: None,
+        reply_to_id: None,
+        edited: false,
+        send_status: None,
+    }
+}
+
+#[async_trait]
+impl MessageProvider for MockProvider {
+    async fn fetch_messages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        self.respond(|m| since.is_none_or(|since| m.timestamp > since)).await
+    }
+
+    async fn fetch_messages_since_id(&self, last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        self.since_ids.lock().unwrap().push(last_message_id);
+        self.respond(|m| last_message_id.is_none_or(|last| m.id > last)).await
+    }
+
+    async fn send_message(&self, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(error) = &self.failure {
+            return Err(error.clone().into());
+        }
+        self.sent.lock().unwrap().push(content.to_string());
+        Ok(None)
+    }
+
+    async fn send_message_with_attachment(&self, _content: &str, _attachment_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("mock provider does not support attachments".into())
+    }
+
+    async fn download_attachment(&self, _attachment: &Attachment, _save_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("mock provider does not support attachments".into())
+    }
+
+    async fn delete_message(&self, _channel_id: Option<&str>, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn supports_edit(&self) -> bool {
+        true
+    }
+
+    async fn edit_message(&self, _channel_id: Option<&str>, _message_id: u64, _new_content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn syncs_by_timestamp(&self) -> bool {
+        self.by_timestamp
+    }
+
+    fn source(&self) -> MessageSource {
+        self.source
+    }
+
+    fn channel_id(&self) -> Option<String> {
+        self.channel_id.clone()
+    }
+
+    fn has_channel(&self, channel_id: &str) -> bool {
+        self.known_channels.iter().any(|known| known == channel_id)
+    }
+
+    fn provider_key(&self) -> String {
+        match &self.channel_id {
+            Some(channel) => format!("mock_{:?}_{}", self.source, channel),
+            None => format!("mock_{:?}", self.source),
+        }
+    }
+
+    fn display_name(&self) -> String {
+        format!("Mock ({:?})", self.source)
+    }
+}
@@ -1,96 +1,739 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use futures::future;
-use crate::{Message, MessageSource, Attachment};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::{Message, MessageSource, Attachment, AttachmentType};
 
 pub mod telegram;
 pub mod discord;
 pub mod github;
 pub mod jira;
+pub mod reddit;
+pub mod rest;
+pub mod teams;
+pub mod twilio;
+pub mod xmpp;
+#[cfg(test)]
+pub mod mock;
+
+/// Pushed from background connections (e.g. the Discord gateway) so new
+/// messages show up without waiting for the next refresh.
+pub enum LiveEvent {
+    Message(Message),
+    /// With the provider's key when the connection is that one provider's
+    /// own (a shared gateway has none)
+    Connected(MessageSource, Option<String>),
+    Disconnected(MessageSource, String),
+    /// The provider (source, key) gave up restoring its connection, for
+    /// this reason; fetches and sends fail until it's back
+    Failed(MessageSource, String, String),
+}
+
+/// A workflow step an item can take, e.g. Jira's "Start Progress"
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub id: String,
+    pub name: String,
+    /// Status the item ends up in
+    pub to_status: String,
+}
+
+/// Someone an item can be assigned to
+#[derive(Debug, Clone)]
+pub struct Assignee {
+    pub id: String,
+    pub name: String,
+}
 
 #[async_trait]
 pub trait MessageProvider {
     async fn fetch_messages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>>;
     async fn fetch_messages_since_id(&self, last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>>;
-    async fn send_message(&self, content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Returns the sent message's id where the service reports one
+    async fn send_message(&self, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>>;
+    /// Sends to a specific conversation or item within the provider, e.g.
+    /// the Reddit message being replied to. Defaults to `send_message` for
+    /// providers that only have one destination.
+    async fn send_message_to(&self, _channel_id: &str, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        self.send_message(content).await
+    }
+    /// Sends `content` as a reply to message `message_id`, threaded natively
+    /// where the service supports it. Elsewhere it's an ordinary send to
+    /// the message's channel.
+    async fn send_reply(&self, channel_id: Option<&str>, _message_id: u64, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        match channel_id {
+            Some(channel_id) => self.send_message_to(channel_id, content).await,
+            None => self.send_message(content).await,
+        }
+    }
     #[allow(dead_code)]
     async fn send_message_with_attachment(&self, content: &str, attachment_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     #[allow(dead_code)]
     async fn download_attachment(&self, attachment: &Attachment, save_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
-    async fn delete_message(&self, message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Removes a message we sent. Takes the message's channel, like
+    /// `edit_message`, for services that number messages per chat.
+    async fn delete_message(&self, channel_id: Option<&str>, message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Whether `delete_message` can remove messages we sent, which is what
+    /// makes undoing a send possible
+    fn supports_delete(&self) -> bool {
+        false
+    }
     fn source(&self) -> MessageSource;
     fn channel_id(&self) -> Option<String>;
+    /// Whether a provider following many channels (e.g. a Telegram account
+    /// or Jira site) owns `channel_id`, so replies go out through the one
+    /// the chat or issue belongs to
+    fn has_channel(&self, _channel_id: &str) -> bool {
+        false
+    }
     fn provider_key(&self) -> String;
+    /// Human-readable name that tells provider instances apart, e.g. which
+    /// Discord channel or which Jira site
+    fn display_name(&self) -> String;
+    /// Providers whose items change in place (e.g. issues) sync from the
+    /// newest timestamp they've seen instead of the last message id.
+    fn syncs_by_timestamp(&self) -> bool {
+        false
+    }
+    /// Workflow transitions available to the item in `channel_id` (for Jira,
+    /// the issue key)
+    async fn list_transitions(&self, _channel_id: &str) -> Result<Vec<Transition>, Box<dyn std::error::Error + Send + Sync>> {
+        Err(format!("{} has no workflow transitions", self.display_name()).into())
+    }
+    async fn apply_transition(&self, _channel_id: &str, _transition_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err(format!("{} has no workflow transitions", self.display_name()).into())
+    }
+    /// Replies that aren't part of the regular feed (e.g. comments on an
+    /// issue), fetched when `parent` is selected. Oldest first.
+    async fn fetch_thread(&self, _parent: &Message) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Vec::new())
+    }
+    /// People the item in `channel_id` can be assigned to
+    async fn list_assignees(&self, _channel_id: &str) -> Result<Vec<Assignee>, Box<dyn std::error::Error + Send + Sync>> {
+        Err(format!("{} does not support assignees", self.display_name()).into())
+    }
+    /// Assigns the item to `assignee_id`, or unassigns it with `None`
+    async fn assign(&self, _channel_id: &str, _assignee_id: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err(format!("{} does not support assignees", self.display_name()).into())
+    }
+    /// Confirms the configured credentials are accepted. Defaults to a
+    /// plain fetch for providers without a cheaper "who am I" call.
+    async fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.fetch_messages(None).await.map(|_| ())
+    }
+    /// Replaces the text of a message we sent. Takes the message's channel
+    /// too, since some services (Telegram) only number messages per chat.
+    async fn edit_message(&self, _channel_id: Option<&str>, _message_id: u64, _new_content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err(format!("{} does not support editing messages", self.display_name()).into())
+    }
+    /// Whether `edit_message` can change messages we sent
+    fn supports_edit(&self) -> bool {
+        false
+    }
+    /// Shows "is typing…" in `channel_id` for a few seconds. Does nothing
+    /// where the service has no such indicator.
+    async fn send_typing(&self, _channel_id: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+    /// How often `send_typing` must be repeated to keep the indicator up
+    fn typing_interval(&self) -> Duration {
+        Duration::from_secs(8)
+    }
+}
+
+/// Whether an error looks like a connectivity problem (worth retrying later)
+/// rather than the provider rejecting the request.
+pub fn is_network_error(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+        return e.is_connect() || e.is_timeout() || e.is_request();
+    }
+    if let Some(e) = error.downcast_ref::<grammers_client::InvocationError>() {
+        return matches!(e, grammers_client::InvocationError::Dropped | grammers_client::InvocationError::Read(_));
+    }
+    error.downcast_ref::<std::io::Error>().is_some()
+}
+
+/// What kind of file an attachment is, from the MIME type the service gave
+/// (if any) or else the filename's extension. Vague MIME types like
+/// `application/octet-stream` defer to the extension.
+pub fn detect_attachment_type(filename: &str, content_type: Option<&str>) -> AttachmentType {
+    if let Some(content_type) = content_type {
+        let essence = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+        let (kind, subtype) = essence.split_once('/').unwrap_or((essence.as_str(), ""));
+        match kind {
+            "image" => return AttachmentType::Image,
+            "video" => return AttachmentType::Video,
+            "audio" => return AttachmentType::Audio,
+            "text" => return AttachmentType::Document,
+            "application"
+                if matches!(subtype, "pdf" | "msword" | "rtf" | "json" | "xml" | "epub+zip")
+                    || subtype.starts_with("vnd.ms-")
+                    || subtype.starts_with("vnd.openxmlformats-officedocument.")
+                    || subtype.starts_with("vnd.oasis.opendocument.") =>
+            {
+                return AttachmentType::Document;
+            }
+            "application"
+                if matches!(
+                    subtype,
+                    "zip" | "x-zip-compressed" | "x-tar" | "gzip" | "x-gzip" | "x-bzip2" | "x-xz" | "x-7z-compressed" | "vnd.rar" | "x-rar-compressed"
+                ) =>
+            {
+                return AttachmentType::Archive;
+            }
+            _ => {}
+        }
+    }
+
+    let extension = match filename.rsplit_once('.') {
+        Some((_, extension)) => extension.to_lowercase(),
+        None => return AttachmentType::Other,
+    };
+    match extension.as_str() {
+        "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "tif" | "tiff" | "heic" | "heif" | "avif" | "svg" | "ico" => AttachmentType::Image,
+        "mp4" | "m4v" | "mov" | "avi" | "mkv" | "webm" | "wmv" | "flv" | "mpg" | "mpeg" | "3gp" => AttachmentType::Video,
+        "mp3" | "wav" | "ogg" | "oga" | "opus" | "flac" | "m4a" | "aac" | "wma" | "aif" | "aiff" | "mid" | "midi" | "amr" => AttachmentType::Audio,
+        "pdf" | "doc" | "docx" | "odt" | "rtf" | "txt" | "md" | "csv" | "tsv" | "xls" | "xlsx" | "ods" | "ppt" | "pptx" | "odp" | "epub" | "json" | "xml" | "html" | "htm" | "log" => AttachmentType::Document,
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" => AttachmentType::Archive,
+        _ => AttachmentType::Other,
+    }
+}
+
+/// HTTP client shared by the REST providers, so they all identify
+/// themselves with the same `User-Agent`. The timeouts keep a stalled
+/// connection from hanging a refresh. Without an explicit proxy, reqwest
+/// still honours the usual `HTTP_PROXY`/`HTTPS_PROXY` variables.
+pub fn http_client(proxy_url: Option<&str>, user_agent: &str) -> Result<reqwest::Client, reqwest::Error> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(Duration::from_secs(10))
+        .read_timeout(Duration::from_secs(30));
+    if let Some(url) = proxy_url {
+        builder = builder.proxy(reqwest::Proxy::all(url)?);
+    }
+    builder.build()
+}
+
+/// Passes a successful response through and turns any other into an error
+/// naming the service and status, e.g. "Discord: 401 Unauthorized", plus
+/// whatever explanation the body gives.
+pub async fn ensure_success(service: &str, response: reqwest::Response) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(match error_detail(&body) {
+        Some(detail) => format!("{}: {} ({})", service, status, detail),
+        None => format!("{}: {}", service, status),
+    }
+    .into())
+}
+
+/// The explanation in an error body: the usual JSON message fields, or the
+/// start of the body itself
+fn error_detail(body: &str) -> Option<String> {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(body) {
+        let message = json["message"].as_str()
+            .or_else(|| json["errorMessages"][0].as_str())
+            .or_else(|| json["error"].as_str())
+            .or_else(|| json["error"]["message"].as_str());
+        if let Some(message) = message {
+            return Some(message.to_string());
+        }
+    }
+    let body = body.trim();
+    if body.is_empty() || body.starts_with('<') {
+        // Nothing useful, or an HTML error page
+        return None;
+    }
+    let mut snippet: String = body.chars().take(100).collect();
+    if snippet.len() < body.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/// How far one provider's incremental fetch got, by the provider's key. To
+/// be saved once the fetched messages are cached.
+pub enum SyncMark {
+    LastId(String, u64),
+    Timestamp(String, DateTime<Utc>),
+}
+
+/// Runs one provider's fetch, giving up with an error after `timeout`
+async fn fetch_with_timeout(
+    provider: &(dyn MessageProvider + Send + Sync),
+    timeout: Duration,
+    fetch: impl Future<Output = Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>>>,
+) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+    tokio::time::timeout(timeout, fetch).await.unwrap_or_else(|_| {
+        Err(format!("{} did not respond within {}s", provider.display_name(), timeout.as_secs()).into())
+    })
+}
+
+fn report_progress(progress: Option<&UnboundedSender<usize>>, provider_index: usize) {
+    if let Some(progress) = progress {
+        // The receiver going away just means nobody is watching anymore
+        let _ = progress.send(provider_index);
+    }
 }
 
 pub struct IntegrationManager {
     pub providers: Vec<Box<dyn MessageProvider + Send + Sync>>,
+    /// Per-provider fetch timeouts, parallel to `providers`
+    fetch_timeouts: Vec<Duration>,
+    /// Most messages kept per source; see `apply_limits`
+    source_limits: HashMap<MessageSource, usize>,
+    /// Lowercased authors whose messages are dropped as soon as they're fetched
+    ignored_authors: Vec<String>,
+    /// Every provider failed the last fetch with a network error
+    unreachable: AtomicBool,
 }
 
 impl IntegrationManager {
     pub fn new() -> Self {
         Self {
             providers: Vec::new(),
+            fetch_timeouts: Vec::new(),
+            source_limits: HashMap::new(),
+            ignored_authors: Vec::new(),
+            unreachable: AtomicBool::new(false),
         }
     }
 
-    pub fn add_provider(&mut self, provider: Box<dyn MessageProvider + Send + Sync>) {
+    pub fn set_source_limits(&mut self, limits: HashMap<MessageSource, usize>) {
+        self.source_limits = limits;
+    }
+
+    pub fn set_ignored_authors(&mut self, authors: Vec<String>) {
+        self.ignored_authors = authors;
+    }
+
+    /// Whether a message comes from an ignored author and shouldn't be kept
+    pub fn is_ignored(&self, message: &Message) -> bool {
+        !self.ignored_authors.is_empty() && self.ignored_authors.contains(&message.author.to_lowercase())
+    }
+
+    /// Sorts newest first and trims to `limit`. Sources with their own limit
+    /// keep up to that many of their newest messages regardless, and the
+    /// remaining room goes to the newest messages of the other sources.
+    pub fn apply_limits(&self, messages: &mut Vec<Message>, limit: Option<usize>) {
+        messages.sort_by_key(|m| std::cmp::Reverse(m.timestamp)); // Newest first
+
+        let mut kept_per_source: HashMap<MessageSource, usize> = HashMap::new();
+        messages.retain(|m| {
+            let Some(&source_limit) = self.source_limits.get(&m.source) else {
+                return true;
+            };
+            let kept = kept_per_source.entry(m.source).or_default();
+            *kept += 1;
+            *kept <= source_limit
+        });
+
+        let Some(limit) = limit else {
+            return;
+        };
+        let reserved = messages.iter().filter(|m| self.source_limits.contains_key(&m.source)).count();
+        let mut room = limit.saturating_sub(reserved);
+        messages.retain(|m| {
+            if self.source_limits.contains_key(&m.source) {
+                return true;
+            }
+            let keep = room > 0;
+            room = room.saturating_sub(1);
+            keep
+        });
+    }
+
+    pub fn add_provider(&mut self, provider: Box<dyn MessageProvider + Send + Sync>, fetch_timeout: Duration) {
         self.providers.push(provider);
+        self.fetch_timeouts.push(fetch_timeout);
     }
 
-    pub async fn fetch_all_messages(&self, since: Option<DateTime<Utc>>, limit: Option<usize>) -> Vec<Message> {
+    /// Finds the provider responsible for a source/channel. Providers without
+    /// a channel of their own (Telegram, GitHub, Jira) handle every channel
+    /// of their source.
+    pub fn find_provider(&self, source: MessageSource, channel_id: Option<&str>) -> Option<&(dyn MessageProvider + Send + Sync)> {
+        self.find_provider_index(source, channel_id)
+            .map(|index| self.providers[index].as_ref())
+    }
+
+    pub fn find_provider_by_key(&self, provider_key: &str) -> Option<&(dyn MessageProvider + Send + Sync)> {
+        self.providers
+            .iter()
+            .find(|p| p.provider_key() == provider_key)
+            .map(|p| p.as_ref())
+    }
+
+    pub fn find_provider_index(&self, source: MessageSource, channel_id: Option<&str>) -> Option<usize> {
+        if let Some(channel_id) = channel_id
+            && let Some(index) = self.providers.iter().position(|p| p.source() == source && p.has_channel(channel_id))
+        {
+            return Some(index);
+        }
+        self.providers.iter().position(|p| {
+            p.source() == source
+                && match (channel_id, p.channel_id()) {
+                    (Some(channel_id), Some(provider_channel)) => provider_channel == channel_id,
+                    _ => true,
+                }
+        })
+    }
+
+    /// Whether the last fetch reached none of the providers, every one of
+    /// them failing with a network error
+    pub fn last_fetch_unreachable(&self) -> bool {
+        self.unreachable.load(Ordering::Relaxed)
+    }
+
+    fn record_reachability(&self, results: &[Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>>]) {
+        let unreachable = !results.is_empty()
+            && results.iter().all(|result| result.as_ref().is_err_and(|e| is_network_error(e.as_ref())));
+        self.unreachable.store(unreachable, Ordering::Relaxed);
+    }
+
+    /// Fetches from every provider at once. Each provider's index is sent to
+    /// `progress` as soon as it has returned (or failed).
+    pub async fn fetch_all_messages(&self, since: Option<DateTime<Utc>>, limit: Option<usize>, progress: Option<&UnboundedSender<usize>>) -> Vec<Message> {
         let mut all_messages = Vec::new();
         
         // Fetch from all providers concurrently for better performance
         let futures: Vec<_> = self.providers.iter()
-            .map(|provider| provider.fetch_messages(since))
+            .zip(&self.fetch_timeouts)
+            .enumerate()
+            .map(|(index, (provider, &timeout))| async move {
+                let result = fetch_with_timeout(provider.as_ref(), timeout, provider.fetch_messages(since)).await;
+                report_progress(progress, index);
+                result
+            })
             .collect();
             
         let results = future::join_all(futures).await;
+        self.record_reachability(&results);
         
-        for result in results {
-            if let Ok(messages) = result {
-                all_messages.extend(messages);
-            }
-        }
-        
-        all_messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)); // Newest first
-        
-        // Apply limit if specified
-        if let Some(limit) = limit {
-            all_messages.truncate(limit);
+        for messages in results.into_iter().flatten() {
+            all_messages.extend(messages);
         }
         
+        // Before the limit, so ignored authors don't take anyone's place
+        all_messages.retain(|m| !self.is_ignored(m));
+        self.apply_limits(&mut all_messages, limit);
         all_messages
     }
     
-    pub async fn fetch_incremental_messages(&self, cache: &crate::database::MessageCache, limit: Option<usize>) -> Vec<Message> {
-        let mut all_messages = Vec::new();
-        
+    /// Fetches what's new since each provider's saved sync state. Also
+    /// returns where each provider got to, worked out from that provider's
+    /// own messages only, since providers of one source (e.g. two Telegram
+    /// accounts or Jira sites) don't share ids or clocks.
+    pub async fn fetch_incremental_messages(&self, cache: &crate::database::MessageCache, limit: Option<usize>, progress: Option<&UnboundedSender<usize>>) -> (Vec<Message>, Vec<SyncMark>) {
         // Fetch incrementally from all providers concurrently
         let futures: Vec<_> = self.providers.iter()
-            .map(|provider| async {
+            .zip(&self.fetch_timeouts)
+            .enumerate()
+            .map(|(index, (provider, &timeout))| async move {
                 let provider_key = provider.provider_key();
-                let last_message_id = cache.get_last_message_id(&provider_key).await.unwrap_or(None);
-                provider.fetch_messages_since_id(last_message_id).await
+                let result = if provider.syncs_by_timestamp() {
+                    let since = cache.get_sync_timestamp(&provider_key).await.unwrap_or(None);
+                    fetch_with_timeout(provider.as_ref(), timeout, provider.fetch_messages(since)).await
+                } else {
+                    let last_message_id = cache.get_last_message_id(&provider_key).await.unwrap_or(None);
+                    fetch_with_timeout(provider.as_ref(), timeout, provider.fetch_messages_since_id(last_message_id)).await
+                };
+                report_progress(progress, index);
+                result
             })
             .collect();
             
         let results = future::join_all(futures).await;
+        self.record_reachability(&results);
+        let fetched: Vec<Vec<Message>> = results.into_iter().map(Result::unwrap_or_default).collect();
         
-        for result in results {
-            if let Ok(messages) = result {
-                all_messages.extend(messages);
-            }
+        // Before the limit, so ignored authors don't take anyone's place
+        let mut all_messages: Vec<Message> = fetched.iter().flatten().filter(|m| !self.is_ignored(m)).cloned().collect();
+        self.apply_limits(&mut all_messages, limit);
+
+        // Only what's kept counts as seen, so the rest is fetched again
+        let kept: HashSet<(MessageSource, u64)> = all_messages.iter().map(|m| (m.source, m.id)).collect();
+        let marks = self.providers
+            .iter()
+            .zip(&fetched)
+            .filter_map(|(provider, messages)| {
+                let own = messages.iter().filter(|m| kept.contains(&(m.source, m.id)));
+                let provider_key = provider.provider_key();
+                if provider.syncs_by_timestamp() {
+                    own.map(|m| m.timestamp).max().map(|latest| SyncMark::Timestamp(provider_key, latest))
+                } else {
+                    own.map(|m| m.id).max().map(|latest| SyncMark::LastId(provider_key, latest))
+                }
+            })
+            .collect();
+        (all_messages, marks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::mock::{message, MockProvider};
+    use crate::database::MessageCache;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn manager(providers: Vec<MockProvider>) -> IntegrationManager {
+        let mut manager = IntegrationManager::new();
+        for provider in providers {
+            manager.add_provider(Box::new(provider), TIMEOUT);
         }
-        
-        all_messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)); // Newest first
-        
-        // Apply limit if specified
-        if let Some(limit) = limit {
-            all_messages.truncate(limit);
+        manager
+    }
+
+    fn ids(messages: &[Message]) -> Vec<u64> {
+        messages.iter().map(|m| m.id).collect()
+    }
+
+    #[tokio::test]
+    async fn fetch_all_merges_providers_newest_first() {
+        let manager = manager(vec![
+            MockProvider::new(MessageSource::Discord, vec![message(1, MessageSource::Discord, 30), message(2, MessageSource::Discord, 10)]),
+            MockProvider::new(MessageSource::Github, vec![message(3, MessageSource::Github, 20), message(4, MessageSource::Github, 1)]),
+        ]);
+
+        let messages = manager.fetch_all_messages(None, None, None).await;
+        assert_eq!(ids(&messages), vec![4, 2, 3, 1]);
+    }
+
+    #[tokio::test]
+    async fn fetch_all_applies_limit_after_merging() {
+        let manager = manager(vec![
+            MockProvider::new(MessageSource::Discord, vec![message(1, MessageSource::Discord, 30), message(2, MessageSource::Discord, 10)]),
+            MockProvider::new(MessageSource::Jira, vec![message(3, MessageSource::Jira, 5)]),
+        ]);
+
+        let messages = manager.fetch_all_messages(None, Some(2), None).await;
+        assert_eq!(ids(&messages), vec![3, 2]);
+    }
+
+    #[tokio::test]
+    async fn source_limits_keep_a_quiet_source_visible() {
+        let chatty: Vec<Message> = (1..=5).map(|id| message(id, MessageSource::Discord, id as i64)).collect();
+        let mut manager = manager(vec![
+            MockProvider::new(MessageSource::Discord, chatty),
+            MockProvider::new(MessageSource::Github, vec![message(10, MessageSource::Github, 60), message(11, MessageSource::Github, 90)]),
+        ]);
+        manager.set_source_limits(HashMap::from([(MessageSource::Github, 1)]));
+
+        // GitHub keeps its newest notification; Discord fills the rest
+        let messages = manager.fetch_all_messages(None, Some(3), None).await;
+        assert_eq!(ids(&messages), vec![1, 2, 10]);
+    }
+
+    #[tokio::test]
+    async fn fetch_all_passes_since_through() {
+        let manager = manager(vec![MockProvider::new(
+            MessageSource::Telegram,
+            vec![message(1, MessageSource::Telegram, 60), message(2, MessageSource::Telegram, 5)],
+        )]);
+
+        let since = Utc::now() - chrono::Duration::minutes(30);
+        let messages = manager.fetch_all_messages(Some(since), None, None).await;
+        assert_eq!(ids(&messages), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn failing_provider_does_not_hide_the_others() {
+        let manager = manager(vec![
+            MockProvider::new(MessageSource::Discord, vec![message(1, MessageSource::Discord, 1)]).failing("401 Unauthorized"),
+            MockProvider::new(MessageSource::Github, vec![message(2, MessageSource::Github, 1)]),
+        ]);
+
+        let messages = manager.fetch_all_messages(None, None, None).await;
+        assert_eq!(ids(&messages), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn unreachable_only_when_every_provider_is() {
+        let all_offline = manager(vec![
+            MockProvider::new(MessageSource::Discord, vec![]).offline(),
+            MockProvider::new(MessageSource::Github, vec![]).offline(),
+        ]);
+        all_offline.fetch_all_messages(None, None, None).await;
+        assert!(all_offline.last_fetch_unreachable());
+
+        let one_answered = manager(vec![
+            MockProvider::new(MessageSource::Discord, vec![]).offline(),
+            MockProvider::new(MessageSource::Github, vec![]).failing("401 Unauthorized"),
+        ]);
+        one_answered.fetch_all_messages(None, None, None).await;
+        assert!(!one_answered.last_fetch_unreachable());
+    }
+
+    #[tokio::test]
+    async fn reports_progress_for_failed_providers_too() {
+        let manager = manager(vec![
+            MockProvider::new(MessageSource::Discord, vec![]).failing("401 Unauthorized"),
+            MockProvider::new(MessageSource::Github, vec![message(1, MessageSource::Github, 1)]),
+        ]);
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        manager.fetch_all_messages(None, None, Some(&sender)).await;
+        let mut done = Vec::new();
+        while let Ok(index) = receiver.try_recv() {
+            done.push(index);
         }
-        
-        all_messages
+        done.sort();
+        assert_eq!(done, vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn stalled_provider_times_out() {
+        let mut manager = IntegrationManager::new();
+        manager.add_provider(
+            Box::new(MockProvider::new(MessageSource::Jira, vec![message(1, MessageSource::Jira, 1)]).with_delay(Duration::from_secs(60))),
+            Duration::from_millis(50),
+        );
+        manager.add_provider(Box::new(MockProvider::new(MessageSource::Github, vec![message(2, MessageSource::Github, 1)])), TIMEOUT);
+
+        let messages = manager.fetch_all_messages(None, None, None).await;
+        assert_eq!(ids(&messages), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn ignored_authors_are_dropped_before_the_limit() {
+        let mut from_bot = message(1, MessageSource::Discord, 1);
+        from_bot.author = "CI-Bot".to_string();
+        let mut manager = manager(vec![
+            MockProvider::new(MessageSource::Discord, vec![from_bot, message(2, MessageSource::Discord, 5)]),
+        ]);
+        manager.set_ignored_authors(vec!["ci-bot".to_string()]);
+
+        let messages = manager.fetch_all_messages(None, Some(1), None).await;
+        assert_eq!(ids(&messages), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn incremental_fetch_resumes_from_last_message_id() {
+        let cache = MessageCache::new("sqlite::memory:", None).await.unwrap();
+        let provider = MockProvider::new(
+            MessageSource::Discord,
+            vec![message(10, MessageSource::Discord, 3), message(11, MessageSource::Discord, 2), message(12, MessageSource::Discord, 1)],
+        );
+        let since_ids = provider.since_ids.clone();
+        cache.update_sync_state(&provider.provider_key(), 11).await.unwrap();
+        let manager = manager(vec![provider]);
+
+        let (messages, _) = manager.fetch_incremental_messages(&cache, None, None).await;
+        assert_eq!(ids(&messages), vec![12]);
+        assert_eq!(*since_ids.lock().unwrap(), vec![Some(11)]);
+    }
+
+    #[tokio::test]
+    async fn incremental_fetch_starts_from_scratch_without_sync_state() {
+        let cache = MessageCache::new("sqlite::memory:", None).await.unwrap();
+        let provider = MockProvider::new(MessageSource::Telegram, vec![message(1, MessageSource::Telegram, 2), message(2, MessageSource::Telegram, 1)]);
+        let since_ids = provider.since_ids.clone();
+        let manager = manager(vec![provider]);
+
+        let (messages, _) = manager.fetch_incremental_messages(&cache, Some(1), None).await;
+        assert_eq!(ids(&messages), vec![2]);
+        assert_eq!(*since_ids.lock().unwrap(), vec![None]);
+    }
+
+    #[tokio::test]
+    async fn incremental_fetch_marks_each_provider_from_its_own_messages() {
+        let cache = MessageCache::new("sqlite::memory:", None).await.unwrap();
+        let manager = manager(vec![
+            MockProvider::new(MessageSource::Telegram, vec![message(5, MessageSource::Telegram, 2)]).with_channel("personal"),
+            MockProvider::new(MessageSource::Telegram, vec![message(90, MessageSource::Telegram, 1)]).with_channel("work"),
+        ]);
+
+        let (_, marks) = manager.fetch_incremental_messages(&cache, None, None).await;
+        let last_ids: Vec<(String, u64)> = marks
+            .into_iter()
+            .filter_map(|mark| match mark {
+                SyncMark::LastId(key, id) => Some((key, id)),
+                SyncMark::Timestamp(..) => None,
+            })
+            .collect();
+        assert_eq!(
+            last_ids,
+            vec![("mock_Telegram_personal".to_string(), 5), ("mock_Telegram_work".to_string(), 90)]
+        );
+    }
+
+    #[tokio::test]
+    async fn incremental_fetch_marks_each_site_from_its_own_timestamps() {
+        let cache = MessageCache::new("sqlite::memory:", None).await.unwrap();
+        let older = message(1, MessageSource::Jira, 10);
+        let newer = message(2, MessageSource::Jira, 1);
+        let manager = manager(vec![
+            MockProvider::new(MessageSource::Jira, vec![older.clone()]).with_channel("one").syncing_by_timestamp(),
+            MockProvider::new(MessageSource::Jira, vec![newer.clone()]).with_channel("two").syncing_by_timestamp(),
+        ]);
+
+        let (_, marks) = manager.fetch_incremental_messages(&cache, None, None).await;
+        let timestamps: Vec<(String, DateTime<Utc>)> = marks
+            .into_iter()
+            .filter_map(|mark| match mark {
+                SyncMark::Timestamp(key, timestamp) => Some((key, timestamp)),
+                SyncMark::LastId(..) => None,
+            })
+            .collect();
+        assert_eq!(
+            timestamps,
+            vec![("mock_Jira_one".to_string(), older.timestamp), ("mock_Jira_two".to_string(), newer.timestamp)]
+        );
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn find_provider_matches_channel_and_sends_through_it() {
+        let general = MockProvider::new(MessageSource::Discord, vec![]).with_channel("general");
+        let random = MockProvider::new(MessageSource::Discord, vec![]).with_channel("random");
+        let sent = random.sent.clone();
+        let manager = manager(vec![general, random]);
+
+        let provider = manager.find_provider(MessageSource::Discord, Some("random")).unwrap();
+        provider.send_message("hello").await.unwrap();
+
+        assert_eq!(*sent.lock().unwrap(), vec!["hello".to_string()]);
+        assert!(manager.find_provider(MessageSource::Github, None).is_none());
+    }
+
+    #[test]
+    fn find_provider_picks_the_account_that_has_the_chat() {
+        let personal = MockProvider::new(MessageSource::Telegram, vec![]).with_known_channel("1");
+        let work = MockProvider::new(MessageSource::Telegram, vec![]).with_known_channel("2");
+        let manager = manager(vec![personal, work]);
+
+        assert_eq!(manager.find_provider_index(MessageSource::Telegram, Some("2")), Some(1));
+        assert_eq!(manager.find_provider_index(MessageSource::Telegram, Some("1")), Some(0));
+        // A chat neither has seen falls back to the first account
+        assert_eq!(manager.find_provider_index(MessageSource::Telegram, Some("3")), Some(0));
+    }
+
+    #[test]
+    fn error_detail_prefers_the_json_message() {
+        assert_eq!(error_detail(r#"{"message": "401: Unauthorized", "code": 0}"#).as_deref(), Some("401: Unauthorized"));
+        assert_eq!(error_detail(r#"{"errorMessages": ["Issue does not exist"]}"#).as_deref(), Some("Issue does not exist"));
+        assert_eq!(error_detail("  bad gateway\n").as_deref(), Some("bad gateway"));
+        assert_eq!(error_detail(&"x".repeat(150)).map(|s| s.chars().count()), Some(101));
+        assert_eq!(error_detail("<html><body>502</body></html>"), None);
+        assert_eq!(error_detail(""), None);
+    }
+
+    #[test]
+    fn attachment_type_prefers_a_specific_content_type() {
+        assert!(matches!(detect_attachment_type("clip", Some("video/webm")), AttachmentType::Video));
+        assert!(matches!(detect_attachment_type("notes.bin", Some("application/pdf")), AttachmentType::Document));
+        assert!(matches!(detect_attachment_type("song.FLAC", Some("application/octet-stream")), AttachmentType::Audio));
+        assert!(matches!(detect_attachment_type("IMG_0001.HEIC", None), AttachmentType::Image));
+        assert!(matches!(detect_attachment_type("report.xlsx", Some("reference")), AttachmentType::Document));
+        assert!(matches!(detect_attachment_type("backup.zip", Some("application/zip")), AttachmentType::Archive));
+        assert!(matches!(detect_attachment_type("logs.tar.gz", None), AttachmentType::Archive));
+        assert!(matches!(detect_attachment_type("book.epub", Some("application/epub+zip")), AttachmentType::Document));
+        assert!(matches!(detect_attachment_type("README", None), AttachmentType::Other));
+    }
+}
@@ -0,0 +1,196 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use crate::{Message, MessageSource};
+use super::{ensure_success, MessageProvider};
+
+pub struct RedditProvider {
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+    client: Client,
+    /// The current access token and when it stops being valid
+    access_token: Mutex<Option<(String, Instant)>>,
+}
+
+impl RedditProvider {
+    pub fn new(client_id: String, client_secret: String, refresh_token: String, client: Client) -> Self {
+        Self {
+            client_id,
+            client_secret,
+            refresh_token,
+            client,
+            access_token: Mutex::new(None),
+        }
+    }
+
+    /// A valid access token, trading the refresh token for a new one when
+    /// the last has expired (they only last an hour)
+    async fn token(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut cached = self.access_token.lock().await;
+        if let Some((token, expires)) = cached.as_ref()
+            && Instant::now() < *expires
+        {
+            return Ok(token.clone());
+        }
+
+        let response = self.client
+            .post("https://www.reddit.com/api/v1/access_token")
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[("grant_type", "refresh_token"), ("refresh_token", self.refresh_token.as_str())])
+            .send()
+            .await?;
+        let data: Value = ensure_success("Reddit", response).await?.json().await?;
+
+        let token = data["access_token"]
+            .as_str()
+            .ok_or_else(|| format!("Reddit rejected the refresh token: {}", data["error"].as_str().unwrap_or("no access token returned")))?
+            .to_string();
+        // Renew a minute early so a request never goes out with a stale token
+        let lifetime = data["expires_in"].as_u64().unwrap_or(3600).saturating_sub(60);
+        *cached = Some((token.clone(), Instant::now() + Duration::from_secs(lifetime)));
+        Ok(token)
+    }
+
+    /// Sends an authorized request, renewing the token once if Reddit
+    /// revoked it early
+    async fn request(
+        &self,
+        build: impl Fn(&Client) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+        let response = build(&self.client)
+            .bearer_auth(self.token().await?)
+            .send()
+            .await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return ensure_success("Reddit", response).await;
+        }
+
+        *self.access_token.lock().await = None;
+        let response = build(&self.client)
+            .bearer_auth(self.token().await?)
+            .send()
+            .await?;
+        ensure_success("Reddit", response).await
+    }
+
+    /// Maps an inbox item: a private message (`t4`) or a comment reply or
+    /// username mention (`t1`)
+    fn parse_item(&self, item: &Value) -> Option<Message> {
+        let data = &item["data"];
+        // Ids are base 36, so decoding them keeps newer items sorting higher
+        let id = u64::from_str_radix(data["id"].as_str()?, 36).ok()?;
+        let timestamp = DateTime::from_timestamp(data["created_utc"].as_f64()? as i64, 0)?;
+        let subject = data["subject"].as_str().unwrap_or("message");
+        let body = data["body"].as_str().unwrap_or("").trim_end();
+
+        let content = match data["subreddit"].as_str() {
+            Some(subreddit) if data["was_comment"].as_bool() == Some(true) => {
+                format!("{} in r/{} ({}): {}", subject, subreddit, data["link_title"].as_str().unwrap_or("a post"), body)
+            }
+            _ => format!("{}: {}", subject, body),
+        };
+
+        Some(Message {
+            id,
+            source: MessageSource::Reddit,
+            content,
+            timestamp,
+            author: data["author"].as_str().unwrap_or("[deleted]").to_string(),
+            attachments: vec![],
+            reactions: vec![],
+            // The fullname (e.g. t1_abc123) is what replies are addressed to
+            channel_id: data["name"].as_str().map(str::to_string),
+            reply_to_id: None,
+            edited: false,
+            send_status: None,
+        })
+    }
+}
+
+#[async_trait]
+impl MessageProvider for RedditProvider {
+    async fn fetch_messages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        let data: Value = self
+            .request(|client| client.get("https://oauth.reddit.com/message/inbox").query(&[("limit", "50"), ("raw_json", "1")]))
+            .await?
+            .json()
+            .await?;
+
+        Ok(data["data"]["children"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| self.parse_item(item))
+                    .filter(|msg| since.is_none_or(|since| msg.timestamp >= since))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn send_message(&self, _content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        Err("Select a Reddit message to reply to it".into())
+    }
+
+    /// Replies to the inbox item with fullname `channel_id`; the comment API
+    /// answers private messages and comments alike
+    async fn send_message_to(&self, channel_id: &str, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let data: Value = self
+            .request(|client| {
+                client
+                    .post("https://oauth.reddit.com/api/comment")
+                    .form(&[("thing_id", channel_id), ("text", content), ("api_type", "json")])
+            })
+            .await?
+            .json()
+            .await?;
+
+        // Rejections (e.g. a locked thread) come back as 200 with errors
+        if let Some(error) = data["json"]["errors"].as_array().and_then(|errors| errors.first()) {
+            let reason = error.as_array().and_then(|e| e.get(1)).and_then(Value::as_str).unwrap_or("unknown error");
+            return Err(format!("Reddit rejected the reply: {}", reason).into());
+        }
+        Ok(None)
+    }
+
+    async fn send_message_with_attachment(&self, _content: &str, _attachment_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Reddit does not support attachments through this interface".into())
+    }
+
+    async fn download_attachment(&self, _attachment: &crate::Attachment, _save_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Reddit messages have no attachments".into())
+    }
+
+    async fn delete_message(&self, _channel_id: Option<&str>, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Reddit does not support deleting messages through this interface".into())
+    }
+
+    fn source(&self) -> MessageSource {
+        MessageSource::Reddit
+    }
+
+    fn channel_id(&self) -> Option<String> {
+        None
+    }
+
+    fn provider_key(&self) -> String {
+        format!("reddit_{}", self.client_id)
+    }
+
+    fn display_name(&self) -> String {
+        "Reddit inbox".to_string()
+    }
+
+    async fn fetch_messages_since_id(&self, _last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        // The inbox is small and newest first, so a full read is cheap
+        self.fetch_messages(None).await
+    }
+
+    async fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.request(|client| client.get("https://oauth.reddit.com/api/v1/me")).await.map(|_| ())
+    }
+}
@@ -0,0 +1,168 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde_json::Value;
+use crate::config::RestConfig;
+use crate::{stable_hash, Message, MessageSource};
+use super::{ensure_success, MessageProvider};
+
+pub struct RestProvider {
+    config: RestConfig,
+    client: Client,
+}
+
+/// Follows a dotted path like `$.data.items[0].text` (or `data.items.0.text`)
+/// into a JSON value. An empty path or `$` selects the root.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let mut current = value;
+
+    for segment in path.split(['.', '[']).filter(|s| !s.is_empty()) {
+        let segment = segment.strip_suffix(']').unwrap_or(segment);
+        current = match segment.parse::<usize>() {
+            Ok(index) if current.is_array() => current.get(index)?,
+            _ => current.get(segment)?,
+        };
+    }
+
+    Some(current)
+}
+
+/// Accepts RFC 3339 strings and Unix timestamps in seconds or milliseconds
+fn parse_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    if let Some(text) = value.as_str() {
+        return DateTime::parse_from_rfc3339(text).ok().map(|t| t.with_timezone(&Utc));
+    }
+    let number = value.as_i64()?;
+    if number > 1_000_000_000_000 {
+        DateTime::from_timestamp_millis(number)
+    } else {
+        DateTime::from_timestamp(number, 0)
+    }
+}
+
+impl RestProvider {
+    pub fn new(config: RestConfig, client: Client) -> Self {
+        Self { config, client }
+    }
+
+    fn parse_item(&self, item: &Value) -> Option<Message> {
+        // Numeric ids are used as-is, anything else is hashed to a stable id
+        let id = match lookup(item, &self.config.id_path)? {
+            Value::Number(n) => n.as_u64()?,
+            Value::String(s) => s.parse::<u64>().unwrap_or_else(|_| stable_hash(s)),
+            _ => return None,
+        };
+        let content = match lookup(item, &self.config.content_path)? {
+            Value::String(s) if self.config.content_html => crate::html::strip_html(s),
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let author = self.config.author_path
+            .as_deref()
+            .and_then(|path| lookup(item, path))
+            .and_then(Value::as_str)
+            .unwrap_or(&self.config.name)
+            .to_string();
+        let timestamp = parse_timestamp(lookup(item, &self.config.timestamp_path)?)?;
+
+        Some(Message {
+            id,
+            source: MessageSource::Rest,
+            content,
+            timestamp,
+            author,
+            attachments: vec![],
+            reactions: vec![],
+            // Tells messages from different REST providers apart
+            channel_id: Some(self.config.name.clone()),
+            reply_to_id: None,
+            edited: false,
+            send_status: None,
+        })
+    }
+}
+
+#[async_trait]
+impl MessageProvider for RestProvider {
+    async fn fetch_messages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut request = self.client.get(&self.config.fetch_url).header("Accept", "application/json");
+        if let Some(auth) = &self.config.auth_header {
+            request = request.header("Authorization", auth);
+        }
+
+        let data: Value = ensure_success(&self.config.name, request.send().await?).await?.json().await?;
+
+        let items = lookup(&data, &self.config.items_path)
+            .and_then(Value::as_array)
+            .ok_or_else(|| format!("{}: no array at items_path {:?}", self.config.name, self.config.items_path))?;
+
+        Ok(items
+            .iter()
+            .filter_map(|item| self.parse_item(item))
+            .filter(|msg| since.is_none_or(|since| msg.timestamp > since))
+            .collect())
+    }
+
+    async fn fetch_messages_since_id(&self, _last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        // Ids may be hashes, so incremental sync goes by timestamp instead
+        self.fetch_messages(None).await
+    }
+
+    async fn send_message(&self, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = self.config.send_url
+            .as_deref()
+            .ok_or_else(|| format!("{} has no send_url configured", self.config.name))?;
+
+        // Escape the text as a JSON string, minus the surrounding quotes
+        let escaped = serde_json::to_string(content)?;
+        let escaped = &escaped[1..escaped.len() - 1];
+        let body = self.config.send_body
+            .as_deref()
+            .unwrap_or(r#"{"content": "{{content}}"}"#)
+            .replace("{{content}}", escaped);
+
+        let mut request = self.client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .body(body);
+        if let Some(auth) = &self.config.auth_header {
+            request = request.header("Authorization", auth);
+        }
+
+        ensure_success(&self.config.name, request.send().await?).await?;
+        Ok(None)
+    }
+
+    async fn send_message_with_attachment(&self, _content: &str, _attachment_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("REST providers do not support attachments".into())
+    }
+
+    async fn download_attachment(&self, _attachment: &crate::Attachment, _save_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("REST providers do not support attachments".into())
+    }
+
+    async fn delete_message(&self, _channel_id: Option<&str>, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("REST providers do not support deleting messages".into())
+    }
+
+    fn source(&self) -> MessageSource {
+        MessageSource::Rest
+    }
+
+    fn channel_id(&self) -> Option<String> {
+        Some(self.config.name.clone())
+    }
+
+    fn provider_key(&self) -> String {
+        format!("rest_{}", self.config.name)
+    }
+
+    fn display_name(&self) -> String {
+        self.config.name.clone()
+    }
+
+    fn syncs_by_timestamp(&self) -> bool {
+        true
+    }
+}
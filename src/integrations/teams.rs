@@ -0,0 +1,307 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use crate::config::TeamsConfig;
+use crate::{Attachment, Message, MessageSource};
+use super::{detect_attachment_type, ensure_success, MessageProvider};
+
+const GRAPH_URL: &str = "https://graph.microsoft.com/v1.0";
+const SCOPES: &str = "https://graph.microsoft.com/.default offline_access";
+/// Most recently active chats read on each refresh
+const CHAT_LIMIT: &str = "20";
+/// Newest messages read from each channel and chat
+const MESSAGES_PER_CONVERSATION: &str = "20";
+
+/// Microsoft Teams channels and chats, read and answered through the
+/// Microsoft Graph API
+pub struct TeamsProvider {
+    tenant_id: String,
+    client_id: String,
+    client_secret: Option<String>,
+    /// `team id/channel id` of each followed channel
+    channels: Vec<String>,
+    include_chats: bool,
+    client: Client,
+    tokens: Mutex<Tokens>,
+}
+
+struct Tokens {
+    /// Microsoft hands out a new refresh token with every access token; the
+    /// newest is used next time
+    refresh: String,
+    /// The current access token and when it stops being valid
+    access: Option<(String, Instant)>,
+}
+
+impl TeamsProvider {
+    pub fn new(config: TeamsConfig, client: Client) -> Self {
+        Self {
+            tenant_id: config.tenant_id,
+            client_id: config.client_id,
+            client_secret: config.client_secret,
+            channels: config.channels,
+            include_chats: config.include_chats,
+            client,
+            tokens: Mutex::new(Tokens { refresh: config.refresh_token, access: None }),
+        }
+    }
+
+    /// A valid access token, redeeming the refresh token for a new one when
+    /// the last has expired (they last about an hour)
+    async fn token(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tokens = self.tokens.lock().await;
+        if let Some((token, expires)) = tokens.access.as_ref()
+            && Instant::now() < *expires
+        {
+            return Ok(token.clone());
+        }
+
+        let mut form = vec![
+            ("grant_type", "refresh_token"),
+            ("client_id", self.client_id.as_str()),
+            ("refresh_token", tokens.refresh.as_str()),
+            ("scope", SCOPES),
+        ];
+        // Public clients (e.g. device code sign-in) have no secret
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+        let response = self.client
+            .post(format!("https://login.microsoftonline.com/{}/oauth2/v2.0/token", self.tenant_id))
+            .form(&form)
+            .send()
+            .await?;
+        let data: Value = ensure_success("Microsoft", response).await?.json().await?;
+
+        let token = data["access_token"]
+            .as_str()
+            .ok_or("Microsoft returned no access token")?
+            .to_string();
+        if let Some(refresh) = data["refresh_token"].as_str() {
+            tokens.refresh = refresh.to_string();
+        }
+        // Renew a minute early so a request never goes out with a stale token
+        let lifetime = data["expires_in"].as_u64().unwrap_or(3600).saturating_sub(60);
+        tokens.access = Some((token.clone(), Instant::now() + Duration::from_secs(lifetime)));
+        Ok(token)
+    }
+
+    /// Sends an authorized request, renewing the token once if it was
+    /// revoked early
+    async fn request(
+        &self,
+        build: impl Fn(&Client) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+        let response = build(&self.client)
+            .bearer_auth(self.token().await?)
+            .send()
+            .await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return ensure_success("Teams", response).await;
+        }
+
+        self.tokens.lock().await.access = None;
+        let response = build(&self.client)
+            .bearer_auth(self.token().await?)
+            .send()
+            .await?;
+        ensure_success("Teams", response).await
+    }
+
+    async fn get_json(&self, path: &str, query: &[(&str, &str)]) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}{}", GRAPH_URL, path);
+        Ok(self.request(|client| client.get(&url).query(query)).await?.json().await?)
+    }
+
+    /// Posts `content` to `path` and returns the new message's id
+    async fn post_message(&self, path: &str, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}{}", GRAPH_URL, path);
+        let body = json!({ "body": { "contentType": "text", "content": content } });
+        let sent: Value = self.request(|client| client.post(&url).json(&body)).await?.json().await?;
+        Ok(sent["id"].as_str().and_then(|id| id.parse().ok()))
+    }
+
+    /// Where the messages of a channel (`team/channel`) or chat are
+    fn messages_path(channel_id: &str) -> String {
+        match channel_id.split_once('/') {
+            Some((team, channel)) => format!("/teams/{}/channels/{}/messages", team, channel),
+            None => format!("/chats/{}/messages", channel_id),
+        }
+    }
+
+    /// The followed channels and, if enabled, the most recently active chats
+    async fn conversations(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conversations = self.channels.clone();
+        if self.include_chats {
+            let chats = self
+                .get_json("/me/chats", &[("$top", CHAT_LIMIT), ("$orderby", "lastMessagePreview/createdDateTime desc")])
+                .await?;
+            conversations.extend(
+                chats["value"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|chat| chat["id"].as_str().map(str::to_string)),
+            );
+        }
+        Ok(conversations)
+    }
+}
+
+/// Maps a chat or channel message. System events (people joining, renamed
+/// channels) and deleted messages are skipped.
+fn parse_message(item: &Value, channel_id: &str) -> Option<Message> {
+    if item["messageType"].as_str() != Some("message") || !item["deletedDateTime"].is_null() {
+        return None;
+    }
+    // Ids are the creation time in milliseconds, so newer ones sort higher
+    let id = item["id"].as_str()?.parse::<u64>().ok()?;
+    let timestamp = DateTime::parse_from_rfc3339(item["createdDateTime"].as_str()?).ok()?.with_timezone(&Utc);
+    let body = item["body"]["content"].as_str().unwrap_or("");
+    let content = match item["body"]["contentType"].as_str() {
+        Some("html") => crate::html::strip_html(body),
+        _ => body.to_string(),
+    };
+    let author = item["from"]["user"]["displayName"]
+        .as_str()
+        .or_else(|| item["from"]["application"]["displayName"].as_str())
+        .unwrap_or("Unknown");
+
+    let attachments = item["attachments"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        // Cards and quoted messages are attachments too, but have no file
+        .filter_map(|attachment| {
+            let url = attachment["contentUrl"].as_str()?;
+            let filename = attachment["name"].as_str().unwrap_or("attachment").to_string();
+            // File links have a content type of `reference`, so the name decides
+            let file_type = detect_attachment_type(&filename, attachment["contentType"].as_str());
+            Some(Attachment { filename, url: url.to_string(), file_type, size: None, local_path: None })
+        })
+        .collect();
+
+    Some(Message {
+        id,
+        source: MessageSource::Teams,
+        content: content.trim_end().to_string(),
+        timestamp,
+        author: author.to_string(),
+        attachments,
+        reactions: vec![],
+        channel_id: Some(channel_id.to_string()),
+        reply_to_id: item["replyToId"].as_str().and_then(|id| id.parse().ok()),
+        edited: !item["lastEditedDateTime"].is_null(),
+        send_status: None,
+    })
+}
+
+#[async_trait]
+impl MessageProvider for TeamsProvider {
+    async fn fetch_messages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        let conversations = self.conversations().await?;
+        let pages = future::try_join_all(conversations.iter().map(|channel_id| async move {
+            let page = self
+                .get_json(&Self::messages_path(channel_id), &[("$top", MESSAGES_PER_CONVERSATION)])
+                .await?;
+            let messages: Vec<Message> = page["value"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|item| parse_message(item, channel_id))
+                .collect();
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(messages)
+        }))
+        .await?;
+
+        Ok(pages
+            .into_iter()
+            .flatten()
+            .filter(|msg| since.is_none_or(|since| msg.timestamp >= since))
+            .collect())
+    }
+
+    async fn fetch_messages_since_id(&self, last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        let messages = self.fetch_messages(None).await?;
+        Ok(messages
+            .into_iter()
+            .filter(|msg| last_message_id.is_none_or(|last| msg.id > last))
+            .collect())
+    }
+
+    async fn send_message(&self, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        match self.channels.as_slice() {
+            [only] if !self.include_chats => self.send_message_to(only, content).await,
+            _ => Err("Select a Teams message to reply in its channel or chat".into()),
+        }
+    }
+
+    async fn send_message_to(&self, channel_id: &str, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        self.post_message(&Self::messages_path(channel_id), content).await
+    }
+
+    /// Channel messages are answered in their thread; chats have no threads
+    async fn send_reply(&self, channel_id: Option<&str>, message_id: u64, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        match channel_id {
+            Some(channel_id) if channel_id.contains('/') => {
+                let path = format!("{}/{}/replies", Self::messages_path(channel_id), message_id);
+                self.post_message(&path, content).await
+            }
+            Some(channel_id) => self.send_message_to(channel_id, content).await,
+            None => self.send_message(content).await,
+        }
+    }
+
+    /// Replies to a channel message, which aren't part of the channel's feed
+    async fn fetch_thread(&self, parent: &Message) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(channel_id) = parent.channel_id.as_deref().filter(|channel_id| channel_id.contains('/')) else {
+            return Ok(Vec::new());
+        };
+        let path = format!("{}/{}/replies", Self::messages_path(channel_id), parent.id);
+        let page = self.get_json(&path, &[("$top", "50")]).await?;
+        let mut replies: Vec<Message> = page["value"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| parse_message(item, channel_id))
+            .collect();
+        replies.sort_by_key(|reply| reply.timestamp);
+        Ok(replies)
+    }
+
+    async fn send_message_with_attachment(&self, _content: &str, _attachment_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Teams does not support attachments through this interface".into())
+    }
+
+    async fn download_attachment(&self, _attachment: &Attachment, _save_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Teams files are stored in SharePoint; open them from Teams".into())
+    }
+
+    async fn delete_message(&self, _channel_id: Option<&str>, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Teams does not support deleting messages through this interface".into())
+    }
+
+    fn source(&self) -> MessageSource {
+        MessageSource::Teams
+    }
+
+    fn channel_id(&self) -> Option<String> {
+        None
+    }
+
+    fn provider_key(&self) -> String {
+        format!("teams_{}_{}", self.tenant_id, self.client_id)
+    }
+
+    fn display_name(&self) -> String {
+        "Microsoft Teams".to_string()
+    }
+
+    async fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.get_json("/me", &[]).await.map(|_| ())
+    }
+}
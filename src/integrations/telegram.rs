@@ -1,25 +1,83 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use grammers_client::{Client, Config, SignInError};
-use grammers_session::Session;
+use grammers_client::{Client, Config, InitParams, InputMessage, SignInError};
+use grammers_client::grammers_tl_types::enums::SendMessageAction;
+use grammers_session::{PackedChat, Session};
+use std::collections::HashMap;
 use std::path::Path;
-use crate::{Message, MessageSource, Attachment, AttachmentType};
-use super::MessageProvider;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::{stable_hash, Message, MessageSource, Attachment, AttachmentType, Reaction};
+use crate::config::{TelegramConfig, TelegramFetchLimits};
+use super::{detect_attachment_type, LiveEvent, MessageProvider};
+
+/// Reconnects tried before an operation gives up on a dropped connection
+const MAX_RECONNECT_ATTEMPTS: u32 = 3;
 
 pub struct TelegramProvider {
-    client: Client,
-    #[allow(dead_code)]
+    /// Replaced on reconnect; shared with the live update listener so it
+    /// picks up the new connection too
+    client: Arc<Mutex<Client>>,
+    /// Held while reconnecting, so concurrent failures reconnect once
+    reconnecting: tokio::sync::Mutex<()>,
     api_id: i32,
-    #[allow(dead_code)]
     api_hash: String,
-    #[allow(dead_code)]
+    proxy_url: Option<String>,
+    /// Where to report live messages and a connection that can't be restored
+    events: UnboundedSender<LiveEvent>,
+    /// Set when several accounts are configured, to tell them apart
+    account: Option<String>,
     phone: String,
     session_file: String,
+    fetch_limits: TelegramFetchLimits,
+    /// Chats seen while searching dialogs, by id, so later lookups are free
+    chats: Mutex<HashMap<i64, PackedChat>>,
+}
+
+/// Telegram numbers messages per chat, and in private chats per account,
+/// so ours put a hash of the account and chat in the high half (kept below
+/// 2^63) and Telegram's own id in the low half
+fn scoped_id(account: &str, chat_id: i64, id: i32) -> u64 {
+    let scope = stable_hash(&format!("{}/{}", account, chat_id)) & 0x7fff_ffff;
+    (scope << 32) | id as u32 as u64
+}
+
+/// Telegram's own id of one of our message ids, for edits, deletes and replies
+fn telegram_id(id: u64) -> i32 {
+    id as u32 as i32
+}
+
+/// Older versions kept the session in the working directory. Moves it to
+/// `default` so upgrading doesn't mean signing in again.
+fn move_legacy_session(default: &Path) {
+    let legacy = Path::new("telegram_session.session");
+    if default.exists() || !legacy.exists() {
+        return;
+    }
+    let moved = default
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::rename(legacy, default));
+    match moved {
+        Ok(()) => println!("Moved {} to {}", legacy.display(), default.display()),
+        Err(e) => eprintln!(
+            "Warning: found {} in the current directory but could not move it to {}: {} (move it there or set TELEGRAM_SESSION_FILE)",
+            legacy.display(),
+            default.display(),
+            e
+        ),
+    }
 }
 
 impl TelegramProvider {
-    pub async fn new(api_id: i32, api_hash: String, phone: String, session_file: Option<String>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let session_file = session_file.unwrap_or_else(|| "telegram_session.session".to_string());
+    pub async fn new(config: &TelegramConfig, events: UnboundedSender<LiveEvent>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let TelegramConfig { name: account, api_id, api_hash, phone, session_file, fetch_limits, proxy_url, .. } = config.clone();
+        let session_file = session_file.unwrap_or_else(|| {
+            let default = crate::config::data_dir().join("telegram_session.session");
+            move_legacy_session(&default);
+            default.to_string_lossy().to_string()
+        });
         
         // Make sure we're using absolute path
         let session_file = if session_file.starts_with('/') {
@@ -60,21 +118,30 @@ impl TelegramProvider {
             session,
             api_id,
             api_hash: api_hash.clone(),
-            params: Default::default(),
+            params: InitParams {
+                proxy_url: proxy_url.clone(),
+                ..Default::default()
+            },
         }).await?;
 
         println!("Connected! Checking authorization...");
 
         let mut provider = Self {
-            client,
+            client: Arc::new(Mutex::new(client)),
+            reconnecting: tokio::sync::Mutex::new(()),
             api_id,
             api_hash,
+            proxy_url,
+            events,
+            account,
             phone: phone.clone(),
             session_file,
+            fetch_limits,
+            chats: Mutex::new(HashMap::new()),
         };
 
         // Authenticate if not already signed in
-        let is_authorized = provider.client.is_authorized().await?;
+        let is_authorized = provider.client().is_authorized().await?;
         println!("Is authorized: {}", is_authorized);
         
         // Log authorization status
@@ -100,9 +167,9 @@ impl TelegramProvider {
 
     async fn authenticate(&mut self, phone: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         println!("Requesting login code...");
-        let token = self.client.request_login_code(phone).await?;
+        let token = self.client().request_login_code(phone).await?;
         
-        println!("Login code has been sent to your Telegram app!");
+        println!("Login code for {} has been sent to your Telegram app!", phone);
         print!("Enter verification code: ");
         std::io::Write::flush(&mut std::io::stdout())?;
         
@@ -112,7 +179,7 @@ impl TelegramProvider {
         println!("You entered code: '{}'", code);
 
         println!("Attempting to sign in...");
-        match self.client.sign_in(&token, code).await {
+        match self.client().sign_in(&token, code).await {
             Err(SignInError::PasswordRequired(password_token)) => {
                 println!("2FA password required.");
                 print!("Enter 2FA password: ");
@@ -123,7 +190,7 @@ impl TelegramProvider {
                 let password = password.trim();
                 
                 println!("Checking 2FA password...");
-                self.client.check_password(password_token, password).await?;
+                self.client().check_password(password_token, password).await?;
             }
             Ok(_) => {
                 println!("Sign in successful!");
@@ -142,16 +209,16 @@ impl TelegramProvider {
             std::fs::read_to_string("telegram_debug.log").unwrap_or_default(), save_start_log));
         
         // Ensure parent directory exists
-        if let Some(parent) = Path::new(&self.session_file).parent() {
-            if !parent.exists() {
-                println!("Creating session directory: {:?}", parent);
-                if let Err(e) = std::fs::create_dir_all(parent) {
-                    eprintln!("Warning: Failed to create session directory: {}", e);
-                }
+        if let Some(parent) = Path::new(&self.session_file).parent()
+            && !parent.exists()
+        {
+            println!("Creating session directory: {:?}", parent);
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                eprintln!("Warning: Failed to create session directory: {}", e);
             }
         }
         
-        let _session = self.client.session();
+        let _session = self.client().session();
         
         // Try to create an empty file first to test permissions
         match std::fs::File::create(&self.session_file) {
@@ -181,8 +248,34 @@ impl TelegramProvider {
         Ok(())
     }
 
-    fn convert_message(&self, message: &grammers_client::types::Message) -> Option<Message> {
-        let id = message.id() as u64;
+    fn convert_reactions(message: &grammers_client::types::Message) -> Vec<Reaction> {
+        use grammers_client::grammers_tl_types::enums;
+
+        let Some(enums::MessageReactions::Reactions(reactions)) = &message.raw.reactions else {
+            return Vec::new();
+        };
+
+        reactions
+            .results
+            .iter()
+            .filter_map(|result| {
+                let enums::ReactionCount::Count(result) = result;
+                let emoji = match &result.reaction {
+                    enums::Reaction::Emoji(emoji) => emoji.emoticon.clone(),
+                    // Custom emoji are stickers we can't draw in a terminal
+                    enums::Reaction::CustomEmoji(_) => "✨".to_string(),
+                    enums::Reaction::Paid => "⭐".to_string(),
+                    enums::Reaction::Empty => return None,
+                };
+                Some(Reaction { emoji, count: result.count as u32 })
+            })
+            .collect()
+    }
+
+    /// `account` is the provider key, which scopes the message's id
+    fn convert_message(account: &str, message: &grammers_client::types::Message) -> Option<Message> {
+        let id = message.id();
+        let chat_id = message.chat().id();
         let content = message.text().to_string();
         let timestamp = DateTime::from_timestamp(message.date().timestamp(), 0)?;
         
@@ -214,6 +307,7 @@ impl TelegramProvider {
                         url: format!("photo_{}", id),
                         file_type: AttachmentType::Image,
                         size: None,
+                        local_path: None,
                     });
                 }
                 grammers_client::types::Media::Document(doc) => {
@@ -222,19 +316,14 @@ impl TelegramProvider {
                     } else {
                         doc.name().to_string()
                     };
-                    let file_type = match filename.split('.').last().unwrap_or("") {
-                        "jpg" | "jpeg" | "png" | "gif" | "webp" => AttachmentType::Image,
-                        "mp4" | "avi" | "mov" | "mkv" => AttachmentType::Video,
-                        "mp3" | "wav" | "ogg" => AttachmentType::Audio,
-                        "pdf" | "doc" | "docx" | "txt" => AttachmentType::Document,
-                        _ => AttachmentType::Other,
-                    };
+                    let file_type = detect_attachment_type(&filename, doc.mime_type());
                     
                     attachments.push(Attachment {
                         filename,
                         url: format!("document_{}", id),
                         file_type,
                         size: Some(doc.size() as u64),
+                        local_path: None,
                     });
                 }
                 _ => {} // Handle other media types as needed
@@ -242,118 +331,308 @@ impl TelegramProvider {
         }
 
         Some(Message {
-            id,
+            id: scoped_id(account, chat_id, id),
             source: MessageSource::Telegram,
             content,
             timestamp,
             author,
             attachments,
+            reactions: Self::convert_reactions(message),
+            reply_to_id: message.reply_to_message_id().map(|reply_to| scoped_id(account, chat_id, reply_to)),
+            edited: message.edit_date().is_some(),
+            send_status: None,
             channel_id,
         })
     }
 
-    async fn send_to_chat_id(&self, content: &str, chat_id: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Get all dialogs to find the chat
-        let mut dialogs = self.client.iter_dialogs();
-        while let Some(dialog) = dialogs.next().await? {
-            let chat = dialog.chat();
-            let current_chat_id = match chat {
-                grammers_client::types::Chat::User(user) => user.id(),
-                grammers_client::types::Chat::Group(group) => group.id(),
-                grammers_client::types::Chat::Channel(channel) => channel.id(),
+    fn client(&self) -> Client {
+        self.client.lock().unwrap().clone()
+    }
+
+    /// Opens a new connection with the current session, replacing the
+    /// dropped one
+    async fn reconnect(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _reconnecting = self.reconnecting.lock().await;
+        let session = Session::load(&self.client().session().save())?;
+        let client = Client::connect(Config {
+            session,
+            api_id: self.api_id,
+            api_hash: self.api_hash.clone(),
+            params: InitParams {
+                proxy_url: self.proxy_url.clone(),
+                ..Default::default()
+            },
+        }).await?;
+        *self.client.lock().unwrap() = client;
+        Ok(())
+    }
+
+    /// Runs `operation`, reconnecting and retrying it when the connection
+    /// has dropped. Once reconnecting keeps failing, the app is told the
+    /// connection is gone.
+    async fn with_reconnect<T, F>(
+        &self,
+        operation: impl Fn() -> F,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>>
+    where
+        F: Future<Output = Result<T, Box<dyn std::error::Error + Send + Sync>>>,
+    {
+        let mut attempts = 0;
+        loop {
+            let error = match operation().await {
+                Err(e) if super::is_network_error(e.as_ref()) => e,
+                result => return result,
             };
-            
-            if current_chat_id == chat_id {
-                self.client.send_message(chat, content.to_string()).await?;
-                return Ok(());
+            if attempts == MAX_RECONNECT_ATTEMPTS {
+                let reason = format!("reconnecting failed {} times: {}", attempts, error);
+                let _ = self.events.send(LiveEvent::Failed(MessageSource::Telegram, self.provider_key(), reason.clone()));
+                return Err(format!("Telegram connection lost, {}", reason).into());
+            }
+
+            attempts += 1;
+            tokio::time::sleep(Duration::from_secs(1 << attempts)).await;
+            // A failed reconnect counts as an attempt; the next round retries
+            if self.reconnect().await.is_ok() {
+                let _ = self.events.send(LiveEvent::Connected(MessageSource::Telegram, Some(self.provider_key())));
             }
         }
+    }
+
+    /// Forwards incoming messages from `client.next_update()` as they arrive,
+    /// so new chats show up without waiting for the next refresh. Messages
+    /// also returned by polling are deduplicated by the app on (source, id).
+    pub fn spawn_live_updates(&self) {
+        let events = self.events.clone();
+        let client = Arc::clone(&self.client);
+        let include_channels = self.fetch_limits.include_channels;
+        let provider_key = self.provider_key();
+
+        tokio::spawn(async move {
+            let _ = events.send(LiveEvent::Connected(MessageSource::Telegram, Some(provider_key.clone())));
+            let mut connected = true;
+
+            loop {
+                // Fetched each time, so a reconnect is picked up here too
+                let current = client.lock().unwrap().clone();
+                let update = current.next_update().await;
+                if update.is_ok() && !connected {
+                    connected = true;
+                    let _ = events.send(LiveEvent::Connected(MessageSource::Telegram, Some(provider_key.clone())));
+                }
+                match update {
+                    Ok(grammers_client::Update::NewMessage(message)) => {
+                        if !include_channels
+                            && let grammers_client::types::Chat::Channel(_) = message.chat()
+                        {
+                            continue;
+                        }
+                        if let Some(msg) = Self::convert_message(&provider_key, &message)
+                            && events.send(LiveEvent::Message(msg)).is_err()
+                        {
+                            return; // The app has shut down
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        if connected && events.send(LiveEvent::Disconnected(MessageSource::Telegram, e.to_string())).is_err() {
+                            return;
+                        }
+                        connected = false;
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+            }
+        });
+    }
+
+    fn dialogs(&self) -> grammers_client::client::dialogs::DialogIter {
+        let dialogs = self.client().iter_dialogs();
+        match self.fetch_limits.dialog_limit {
+            Some(limit) => dialogs.limit(limit),
+            None => dialogs,
+        }
+    }
+
+    /// How many messages to read from a chat, or `None` to skip it entirely.
+    /// Broadcast channels can hold thousands of messages, so they are opt-in
+    /// and capped separately.
+    fn message_limit_for(&self, chat: &grammers_client::types::Chat) -> Option<usize> {
+        match chat {
+            grammers_client::types::Chat::Channel(_) if !self.fetch_limits.include_channels => None,
+            grammers_client::types::Chat::Channel(_) => Some(self.fetch_limits.messages_per_channel),
+            _ => Some(self.fetch_limits.messages_per_chat),
+        }
+    }
+
+    /// Notes a chat as belonging to this account, so sends to it are routed
+    /// here when several accounts are configured
+    fn remember_chat(&self, chat: &grammers_client::types::Chat) {
+        let chat = chat.pack();
+        self.chats.lock().unwrap().insert(chat.id, chat);
+    }
+
+    /// Looks a chat up by id among the dialogs, remembering every chat
+    /// passed on the way
+    async fn find_chat(&self, chat_id: i64) -> Result<Option<PackedChat>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(chat) = self.chats.lock().unwrap().get(&chat_id) {
+            return Ok(Some(*chat));
+        }
+
+        let mut dialogs = self.client().iter_dialogs();
+        while let Some(dialog) = dialogs.next().await? {
+            let chat = dialog.chat().pack();
+            self.chats.lock().unwrap().insert(chat.id, chat);
+            if chat.id == chat_id {
+                return Ok(Some(chat));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Our id for a message this account just sent
+    fn sent_id(&self, sent: &grammers_client::types::Message) -> u64 {
+        scoped_id(&self.provider_key(), sent.chat().id(), sent.id())
+    }
+
+    async fn send_to_chat_id(&self, content: &str, chat_id: i64) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(chat) = self.find_chat(chat_id).await? {
+            let sent = self.client().send_message(chat, content.to_string()).await?;
+            return Ok(Some(self.sent_id(&sent)));
+        }
         
         // If chat not found, fall back to saved messages with error
-        let me = self.client.get_me().await?;
-        self.client.send_message(&me, format!("(Chat {} not found) {}", chat_id, content)).await?;
-        Ok(())
+        let me = self.client().get_me().await?;
+        let sent = self.client().send_message(&me, format!("(Chat {} not found) {}", chat_id, content)).await?;
+        Ok(Some(self.sent_id(&sent)))
     }
 }
 
 #[async_trait]
 impl MessageProvider for TelegramProvider {
     async fn fetch_messages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
-        let mut messages = Vec::new();
+        let provider_key = &self.provider_key();
+        self.with_reconnect(|| async move {
+            let mut messages = Vec::new();
         
-        // Get dialogs (chats) - reduce to 5 for much faster loading
-        let mut dialogs = self.client.iter_dialogs().limit(5);
-        let mut _chat_count = 0;
+            let mut dialogs = self.dialogs();
+            let mut _chat_count = 0;
         
-        while let Some(dialog) = dialogs.next().await? {
-            let chat = dialog.chat();
-            _chat_count += 1;
+            while let Some(dialog) = dialogs.next().await? {
+                let chat = dialog.chat();
+                self.remember_chat(chat);
+                _chat_count += 1;
             
-            let _chat_name = match chat {
-                grammers_client::types::Chat::User(user) => {
-                    format!("{} {}", user.first_name(), user.last_name().unwrap_or(""))
-                }
-                grammers_client::types::Chat::Group(group) => group.title().to_string(),
-                grammers_client::types::Chat::Channel(channel) => channel.title().to_string(),
-            };
+                let _chat_name = match chat {
+                    grammers_client::types::Chat::User(user) => {
+                        format!("{} {}", user.first_name(), user.last_name().unwrap_or(""))
+                    }
+                    grammers_client::types::Chat::Group(group) => group.title().to_string(),
+                    grammers_client::types::Chat::Channel(channel) => channel.title().to_string(),
+                };
             
-            // Skip loading messages from very large channels/groups for performance
-            if let grammers_client::types::Chat::Channel(_) = chat {
-                // Skip channels for now as they can have thousands of messages
-                continue;
-            }
+                let Some(limit) = self.message_limit_for(chat) else {
+                    continue;
+                };
             
-            // Get messages from this chat - reduce to 3 messages per chat for faster loading
-            let limit = 3;
-            let mut chat_messages = self.client.iter_messages(chat).limit(limit);
+                let mut chat_messages = self.client().iter_messages(chat).limit(limit);
             
-            while let Some(message) = chat_messages.next().await? {
-                // Filter by timestamp if provided
-                if let Some(since_time) = since {
-                    let msg_time = DateTime::from_timestamp(message.date().timestamp(), 0);
-                    if let Some(msg_time) = msg_time {
-                        if msg_time < since_time {
+                while let Some(message) = chat_messages.next().await? {
+                    // Filter by timestamp if provided
+                    if let Some(since_time) = since {
+                        let msg_time = DateTime::from_timestamp(message.date().timestamp(), 0);
+                        if let Some(msg_time) = msg_time
+                            && msg_time < since_time
+                        {
                             break; // Messages are in reverse chronological order
                         }
                     }
-                }
                 
-                // Convert to our Message format
-                if let Some(msg) = self.convert_message(&message) {
-                    messages.push(msg);
+                    // Convert to our Message format
+                    if let Some(msg) = Self::convert_message(provider_key, &message) {
+                        messages.push(msg);
+                    }
                 }
             }
-        }
         
-        // Messages loaded successfully
+            // Messages loaded successfully
         
-        // Sort by timestamp (newest first)
-        messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        Ok(messages)
+            // Sort by timestamp (newest first)
+            messages.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+            Ok(messages)
+        }).await
     }
 
-    async fn send_message(&self, content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Parse if this is a targeted message (format: "Reply to chat {chat_id}: {message}")
-        if content.starts_with("Reply to chat ") {
-            if let Some(colon_pos) = content.find(": ") {
-                let chat_part = &content[14..colon_pos]; // Skip "Reply to chat "
-                let message_part = &content[colon_pos + 2..]; // Skip ": "
-                
-                if let Ok(chat_id) = chat_part.parse::<i64>() {
-                    return self.send_to_chat_id(message_part, chat_id).await;
-                }
+    async fn send_message(&self, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        self.with_reconnect(|| async move {
+            // Without a chat, send to "Saved Messages" (self chat)
+            let me = self.client().get_me().await?;
+            let sent = self.client().send_message(&me, content.to_string()).await?;
+            Ok(Some(self.sent_id(&sent)))
+        }).await
+    }
+
+
+    async fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.with_reconnect(|| async move {
+            self.client().get_me().await?;
+            Ok(())
+        }).await
+    }
+
+    fn supports_edit(&self) -> bool {
+        true
+    }
+
+    async fn edit_message(&self, channel_id: Option<&str>, message_id: u64, new_content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.with_reconnect(|| async move {
+            let chat_id = channel_id
+                .and_then(|id| id.parse::<i64>().ok())
+                .ok_or("Message has no Telegram chat")?;
+            let chat = self.find_chat(chat_id).await?.ok_or_else(|| format!("Chat {} not found", chat_id))?;
+            self.client().edit_message(chat, telegram_id(message_id), new_content.to_string()).await?;
+            Ok(())
+        }).await
+    }
+
+    async fn send_message_to(&self, channel_id: &str, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        self.with_reconnect(|| async move {
+            let chat_id = channel_id.parse::<i64>().map_err(|_| format!("Not a Telegram chat id: {}", channel_id))?;
+            self.send_to_chat_id(content, chat_id).await
+        }).await
+    }
+
+    async fn send_reply(&self, channel_id: Option<&str>, message_id: u64, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        self.with_reconnect(|| async move {
+            let chat_id = channel_id
+                .and_then(|id| id.parse::<i64>().ok())
+                .ok_or("Message has no Telegram chat")?;
+            let chat = self.find_chat(chat_id).await?.ok_or_else(|| format!("Chat {} not found", chat_id))?;
+            let sent = self.client()
+                .send_message(chat, InputMessage::text(content).reply_to(Some(telegram_id(message_id))))
+                .await?;
+            Ok(Some(self.sent_id(&sent)))
+        }).await
+    }
+
+    async fn send_typing(&self, channel_id: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(chat_id) = channel_id.and_then(|id| id.parse::<i64>().ok()) else {
+            return Ok(());
+        };
+        self.with_reconnect(|| async move {
+            if let Some(chat) = self.find_chat(chat_id).await? {
+                self.client().action(chat).oneshot(SendMessageAction::SendMessageTypingAction).await?;
             }
-        }
-        
-        // Default: send to "Saved Messages" (self chat)
-        let me = self.client.get_me().await?;
-        self.client.send_message(&me, content.to_string()).await?;
-        Ok(())
+            Ok(())
+        }).await
     }
 
+    /// Telegram clears the indicator after about 5 seconds
+    fn typing_interval(&self) -> Duration {
+        Duration::from_secs(4)
+    }
 
     async fn send_message_with_attachment(&self, content: &str, attachment_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let me = self.client.get_me().await?;
+        let me = self.client().get_me().await?;
         
         // Read the file and send it as bytes with caption
         let _file_bytes = tokio::fs::read(attachment_path).await?;
@@ -365,7 +644,7 @@ impl MessageProvider for TelegramProvider {
         
         // For now, send as document with caption
         // TODO: Implement proper file upload with grammers
-        self.client.send_message(&me, format!("{}\n[Attachment: {}]", content, file_name)).await?;
+        self.client().send_message(&me, format!("{}\n[Attachment: {}]", content, file_name)).await?;
         
         Ok(())
     }
@@ -379,26 +658,21 @@ impl MessageProvider for TelegramProvider {
         Err("Attachment download requires access to original media objects from messages".into())
     }
 
-    async fn delete_message(&self, message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Find the message across all dialogs
-        let mut dialogs = self.client.iter_dialogs();
-        while let Some(dialog) = dialogs.next().await? {
-            let chat = dialog.chat();
-            
-            // Get recent messages from this chat to find the one with matching ID
-            let mut chat_messages = self.client.iter_messages(chat).limit(50);
-            while let Some(message) = chat_messages.next().await? {
-                if message.id() as u64 == message_id {
-                    // Found the message, attempt to delete it
-                    if let Err(e) = self.client.delete_messages(chat, &[message.id()]).await {
-                        return Err(format!("Failed to delete message: {}", e).into());
-                    }
-                    return Ok(());
-                }
-            }
-        }
-        
-        Err("Message not found or cannot be deleted".into())
+    /// Deletes within the message's own chat only: ids are numbered per
+    /// chat, so the same id elsewhere is a different message
+    async fn delete_message(&self, channel_id: Option<&str>, message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.with_reconnect(|| async move {
+            let chat_id = channel_id
+                .and_then(|id| id.parse::<i64>().ok())
+                .ok_or("Message has no Telegram chat")?;
+            let chat = self.find_chat(chat_id).await?.ok_or_else(|| format!("Chat {} not found", chat_id))?;
+            self.client().delete_messages(chat, &[telegram_id(message_id)]).await?;
+            Ok(())
+        }).await
+    }
+
+    fn supports_delete(&self) -> bool {
+        true
     }
 
     fn source(&self) -> MessageSource {
@@ -409,47 +683,35 @@ impl MessageProvider for TelegramProvider {
         // Return None since we're fetching from all chats
         None
     }
+
+    fn has_channel(&self, channel_id: &str) -> bool {
+        channel_id.parse::<i64>().is_ok_and(|chat_id| self.chats.lock().unwrap().contains_key(&chat_id))
+    }
     
+    /// Named accounts are keyed by phone number, since they may share an
+    /// api id. The single unnamed account keeps the api id key it has always
+    /// had, so its sync state, queued sends and drafts carry over.
     fn provider_key(&self) -> String {
-        format!("telegram_{}", self.api_id)
+        if self.account.is_none() {
+            return format!("telegram_{}", self.api_id);
+        }
+        let digits: String = self.phone.chars().filter(char::is_ascii_digit).collect();
+        format!("telegram_{}", digits)
     }
-    
-    async fn fetch_messages_since_id(&self, last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
-        let mut messages = Vec::new();
-        
-        // Get fewer dialogs for incremental sync (just 3 most recent)
-        let mut dialogs = self.client.iter_dialogs().limit(3);
-        
-        while let Some(dialog) = dialogs.next().await? {
-            let chat = dialog.chat();
-            
-            // Skip channels for incremental sync
-            if let grammers_client::types::Chat::Channel(_) = chat {
-                continue;
-            }
-            
-            // Get only 2 most recent messages per chat for incremental sync
-            let mut chat_messages = self.client.iter_messages(chat).limit(2);
-            
-            while let Some(message) = chat_messages.next().await? {
-                let message_id = message.id() as u64;
-                
-                // Skip messages we've already seen
-                if let Some(last_id) = last_message_id {
-                    if message_id <= last_id {
-                        break; // Messages are in reverse chronological order
-                    }
-                }
-                
-                // Convert to our Message format
-                if let Some(msg) = self.convert_message(&message) {
-                    messages.push(msg);
-                }
-            }
+
+    fn display_name(&self) -> String {
+        match &self.account {
+            Some(account) => format!("Telegram ({})", account),
+            None => "Telegram".to_string(),
         }
-        
-        // Sort by timestamp (newest first)
-        messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        Ok(messages)
+    }
+    
+    async fn fetch_messages_since_id(&self, _last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        // Ids are only ordered within one chat, so syncing goes by timestamp
+        self.fetch_messages(None).await
+    }
+
+    fn syncs_by_timestamp(&self) -> bool {
+        true
     }
 }
\ No newline at end of file
@@ -0,0 +1,155 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future;
+use reqwest::Client;
+use serde_json::Value;
+use crate::{stable_hash, Message, MessageSource};
+use super::{ensure_success, MessageProvider};
+
+const API_URL: &str = "https://api.twilio.com/2010-04-01";
+/// Newest messages read in each direction
+const PAGE_SIZE: &str = "50";
+
+/// Text messages to and from one Twilio number. Each conversation is the
+/// other party's phone number.
+pub struct TwilioProvider {
+    account_sid: String,
+    auth_token: String,
+    from_number: String,
+    client: Client,
+}
+
+impl TwilioProvider {
+    pub fn new(account_sid: String, auth_token: String, from_number: String, client: Client) -> Self {
+        Self { account_sid, auth_token, from_number, client }
+    }
+
+    fn messages_url(&self) -> String {
+        format!("{}/Accounts/{}/Messages.json", API_URL, self.account_sid)
+    }
+
+    /// One page of messages matching `filter` (`To` or `From` our number)
+    async fn list(&self, filter: &str) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.client
+            .get(self.messages_url())
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .query(&[(filter, self.from_number.as_str()), ("PageSize", PAGE_SIZE)])
+            .send()
+            .await?;
+        let data: Value = ensure_success("Twilio", response).await?.json().await?;
+        Ok(data["messages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| self.parse_message(item))
+            .collect())
+    }
+
+    fn parse_message(&self, item: &Value) -> Option<Message> {
+        let sid = item["sid"].as_str()?;
+        let from = item["from"].as_str()?;
+        let to = item["to"].as_str()?;
+        // Queued messages have no send date yet
+        let date = item["date_sent"].as_str().or_else(|| item["date_created"].as_str())?;
+        let timestamp = DateTime::parse_from_rfc2822(date).ok()?.with_timezone(&Utc);
+        let inbound = item["direction"].as_str() == Some("inbound");
+        let mut content = item["body"].as_str().unwrap_or("").trim_end().to_string();
+        // Picture messages keep their media behind another request
+        match item["num_media"].as_str().and_then(|n| n.parse::<u32>().ok()) {
+            Some(0) | None => {}
+            Some(count) => content.push_str(&format!(" [{} media]", count)),
+        }
+        if matches!(item["status"].as_str(), Some("failed" | "undelivered")) {
+            content.push_str(" (not delivered)");
+        }
+
+        Some(Message {
+            // Sids are `SM` plus 32 hex digits, too long for an id
+            id: stable_hash(sid) & i64::MAX as u64,
+            source: MessageSource::Sms,
+            content,
+            timestamp,
+            author: from.to_string(),
+            attachments: vec![],
+            reactions: vec![],
+            channel_id: Some(if inbound { from } else { to }.to_string()),
+            reply_to_id: None,
+            edited: false,
+            send_status: None,
+        })
+    }
+}
+
+#[async_trait]
+impl MessageProvider for TwilioProvider {
+    async fn fetch_messages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        let (received, sent) = future::try_join(self.list("To"), self.list("From")).await?;
+        Ok(received
+            .into_iter()
+            .chain(sent)
+            .filter(|msg| since.is_none_or(|since| msg.timestamp >= since))
+            .collect())
+    }
+
+    async fn fetch_messages_since_id(&self, _last_message_id: Option<u64>) -> Result<Vec<Message>, Box<dyn std::error::Error + Send + Sync>> {
+        // Ids are hashes, so they can't be compared
+        self.fetch_messages(None).await
+    }
+
+    async fn send_message(&self, _content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        Err("Select a text message to reply to its number".into())
+    }
+
+    /// Texts the phone number `channel_id` from our number
+    async fn send_message_to(&self, channel_id: &str, content: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.client
+            .post(self.messages_url())
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("To", channel_id), ("From", self.from_number.as_str()), ("Body", content)])
+            .send()
+            .await?;
+        let sent: Value = ensure_success("Twilio", response).await?.json().await?;
+        Ok(sent["sid"].as_str().map(|sid| stable_hash(sid) & i64::MAX as u64))
+    }
+
+    async fn send_message_with_attachment(&self, _content: &str, _attachment_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Twilio does not support attachments through this interface".into())
+    }
+
+    async fn download_attachment(&self, _attachment: &crate::Attachment, _save_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Text messages have no attachments here".into())
+    }
+
+    async fn delete_message(&self, _channel_id: Option<&str>, _message_id: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Text messages can't be unsent".into())
+    }
+
+    fn source(&self) -> MessageSource {
+        MessageSource::Sms
+    }
+
+    fn channel_id(&self) -> Option<String> {
+        None
+    }
+
+    fn provider_key(&self) -> String {
+        format!("twilio_{}", self.from_number)
+    }
+
+    fn display_name(&self) -> String {
+        format!("SMS ({})", self.from_number)
+    }
+
+    fn syncs_by_timestamp(&self) -> bool {
+        true
+    }
+
+    async fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.client
+            .get(format!("{}/Accounts/{}.json", API_URL, self.account_sid))
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .send()
+            .await?;
+        ensure_success("Twilio", response).await.map(|_| ())
+    }
+}
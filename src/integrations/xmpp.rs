@@ -0,0 +1,658 @@
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::{self, Message as WsMessage};
+use crate::config::XmppConfig;
+use crate::{Message, MessageSource};
+use super::{LiveEvent, MessageProvider};
+
+/// Received messages kept for refreshes to pick up; older ones are cached
+const MAX_BUFFERED: usize = 500;
+/// Group chat history requested when joining a room
+const ROOM_HISTORY: usize = 20;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// How often the connection is pinged so proxies don't drop it when idle
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60);
+/// How long a health check waits for the connection to sign in
+const SIGN_IN_TIMEOUT: Duration = Duration::from_secs(20);
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Chats on an XMPP account. There's no history to fetch, so a background
+/// connection buffers what arrives (and forwards it live) until a refresh
+/// picks it up.
+pub struct XmppProvider {
+    jid: String,
+    rooms: Vec<String>,
+    contacts: Vec<String>,
+    shared: Arc<Shared>,
+    /// Stanzas for the connection task to send
+    outgoing: UnboundedSender<String>,
+}
+
+/// Where the background connection stands
+#[derive(Debug, Clone, Default)]
+enum Connection {
+    #[default]
+    Connecting,
+    Connected,
+    /// Dropped and about to retry, with why
+    Down(String),
+    /// The server turned down the account; retrying won't help
+    Rejected(String),
+}
+
+/// What the connection task and the provider both see
+#[derive(Default)]
+struct Shared {
+    received: Mutex<VecDeque<Message>>,
+    state: watch::Sender<Connection>,
+}
+
+impl Shared {
+    fn remember(&self, message: Message) {
+        let mut received = self.received.lock().unwrap();
+        // Rooms replay their history on every reconnect
+        if received.iter().any(|m| m.id == message.id) {
+            return;
+        }
+        received.push_back(message);
+        if received.len() > MAX_BUFFERED {
+            received.pop_front();
+        }
+    }
+}
+
+/// The server turned down the account; retrying won't help
+#[derive(Debug)]
+struct SignInRejected(String);
+
+impl std::fmt::Display for SignInRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sign-in rejected: {}", self.0)
+    }
+}
+
+impl std::error::Error for SignInRejected {}
+
+impl XmppProvider {
+    /// Starts connecting in the background. The connection is restored
+    /// with exponential backoff when it drops, unless the server rejected
+    /// the credentials.
+    pub fn new(config: XmppConfig, events: UnboundedSender<LiveEvent>) -> Self {
+        let shared = Arc::new(Shared::default());
+        let (outgoing, queued) = mpsc::unbounded_channel();
+        let provider = Self {
+            jid: config.jid.clone(),
+            rooms: config.rooms.clone(),
+            contacts: config.contacts.clone(),
+            shared: Arc::clone(&shared),
+            outgoing,
+        };
+        tokio::spawn(maintain_connection(config, shared, queued, events));
+        provider
+    }
+
+    fn is_room(&self, channel_id: &str) -> bool {
+        self.rooms.iter().any(|room| room.eq_ignore_ascii_case(channel_id))
+    }
+}
+
+async fn maintain_connection(
+    config: XmppConfig,
+    shared: Arc<Shared>,
+    mut queued: UnboundedReceiver<String>,
+    events: UnboundedSender<LiveEvent>,
+) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let result = run_connection(&config, &shared, &mut queued, &events, &mut backoff).await;
+        // Ok only when the provider or the app has gone away
+        let Err(error) = result else {
+            shared.state.send_replace(Connection::Down("the connection was closed".to_string()));
+            return;
+        };
+
+        if error.downcast_ref::<SignInRejected>().is_some() {
+            shared.state.send_replace(Connection::Rejected(error.to_string()));
+            let _ = events.send(LiveEvent::Failed(MessageSource::Xmpp, format!("xmpp_{}", config.jid), error.to_string()));
+            return;
+        }
+        shared.state.send_replace(Connection::Down(error.to_string()));
+        if events.send(LiveEvent::Disconnected(MessageSource::Xmpp, error.to_string())).is_err() {
+            return;
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+async fn run_connection(
+    config: &XmppConfig,
+    shared: &Shared,
+    queued: &mut UnboundedReceiver<String>,
+    events: &UnboundedSender<LiveEvent>,
+    backoff: &mut Duration,
+) -> Result<(), BoxError> {
+    let (local, domain) = config.jid.split_once('@').ok_or("XMPP_JID has no domain")?;
+    let url = config.server.clone().unwrap_or_else(|| format!("wss://{}/xmpp-websocket", domain));
+    // PLAIN sign-in carries the password as-is, so the transport has to be TLS
+    if !url.starts_with("wss://") {
+        return Err(Box::new(SignInRejected(format!("{} isn't a secure WebSocket (wss://) URL", url))));
+    }
+    let mut request = url.as_str().into_client_request()?;
+    request.headers_mut().insert("Sec-WebSocket-Protocol", HeaderValue::from_static("xmpp"));
+    let (mut socket, _) = tokio_tungstenite::connect_async(request).await?;
+
+    open_stream(&mut socket, domain).await?;
+    let features = next_element(&mut socket).await?;
+    let offers_plain = features
+        .child("mechanisms")
+        .is_some_and(|mechanisms| mechanisms.children.iter().any(|m| m.text.trim() == "PLAIN"));
+    if !offers_plain {
+        return Err(Box::new(SignInRejected("the server doesn't offer PLAIN sign-in".to_string())));
+    }
+    let credentials = base64::engine::general_purpose::STANDARD.encode(format!("\0{}\0{}", local, config.password));
+    send(&mut socket, format!(r#"<auth xmlns="urn:ietf:params:xml:ns:xmpp-sasl" mechanism="PLAIN">{}</auth>"#, credentials)).await?;
+    let outcome = next_element(&mut socket).await?;
+    if outcome.name != "success" {
+        let reason = outcome.children.first().map_or("unknown reason", |condition| condition.name.as_str());
+        return Err(Box::new(SignInRejected(reason.to_string())));
+    }
+
+    // Signing in restarts the stream, this time offering resource binding
+    open_stream(&mut socket, domain).await?;
+    next_element(&mut socket).await?;
+    send(
+        &mut socket,
+        r#"<iq type="set" id="bind"><bind xmlns="urn:ietf:params:xml:ns:xmpp-bind"><resource>friend</resource></bind></iq>"#.to_string(),
+    )
+    .await?;
+    loop {
+        let reply = next_element(&mut socket).await?;
+        if reply.name == "iq" && reply.attr("id") == Some("bind") {
+            if reply.attr("type") != Some("result") {
+                return Err("the server refused to bind a resource".into());
+            }
+            break;
+        }
+    }
+
+    send(&mut socket, "<presence/>".to_string()).await?;
+    for room in &config.rooms {
+        send(
+            &mut socket,
+            format!(
+                r#"<presence to="{}/{}"><x xmlns="http://jabber.org/protocol/muc"><history maxstanzas="{}"/></x></presence>"#,
+                escape(room),
+                escape(&config.nickname),
+                ROOM_HISTORY
+            ),
+        )
+        .await?;
+    }
+
+    *backoff = Duration::from_secs(1);
+    shared.state.send_replace(Connection::Connected);
+    let _ = events.send(LiveEvent::Connected(MessageSource::Xmpp, Some(format!("xmpp_{}", config.jid))));
+
+    let (mut write, mut read) = socket.split();
+    let mut keepalive = tokio::time::interval_at(tokio::time::Instant::now() + KEEPALIVE_INTERVAL, KEEPALIVE_INTERVAL);
+    loop {
+        tokio::select! {
+            stanza = queued.recv() => match stanza {
+                Some(stanza) => write.send(WsMessage::Text(stanza.into())).await?,
+                None => return Ok(()),
+            },
+            _ = keepalive.tick() => write.send(WsMessage::Ping(Default::default())).await?,
+            element = next_element(&mut read) => {
+                let element = element?;
+                match element.name.as_str() {
+                    "message" => {
+                        if let Some(message) = parse_message(&element, config) {
+                            shared.remember(message.clone());
+                            if events.send(LiveEvent::Message(message)).is_err() {
+                                return Ok(());
+                            }
+                        }
+                    }
+                    "iq" if matches!(element.attr("type"), Some("get" | "set")) => {
+                        write.send(WsMessage::Text(iq_reply(&element).into())).await?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+async fn send<S>(socket: &mut S, stanza: String) -> Result<(), BoxError>
+where
+    S: Sink<WsMessage, Error = tungstenite::Error> + Unpin,
+{
+    socket.send(WsMessage::Text(stanza.into())).await?;
+    Ok(())
+}
+
+/// Opens the stream (again, after signing in) and waits for the server's
+/// answering `<open/>`
+async fn open_stream<S>(socket: &mut S, domain: &str) -> Result<(), BoxError>
+where
+    S: Sink<WsMessage, Error = tungstenite::Error> + Stream<Item = Result<WsMessage, tungstenite::Error>> + Unpin,
+{
+    send(socket, format!(r#"<open xmlns="urn:ietf:params:xml:ns:xmpp-framing" to="{}" version="1.0"/>"#, escape(domain))).await?;
+    let reply = next_element(socket).await?;
+    if reply.name != "open" {
+        return Err(format!("expected the stream to open, got <{}>", reply.name).into());
+    }
+    Ok(())
+}
+
+/// The next element from the server; over WebSocket each frame holds
+/// exactly one. Closing the stream or a stream error ends the connection.
+async fn next_element<S>(read: &mut S) -> Result<Element, BoxError>
+where
+    S: Stream<Item = Result<WsMessage, tungstenite::Error>> + Unpin,
+{
+    loop {
+        let text = match read.next().await.ok_or("connection closed")?? {
+            WsMessage::Text(text) => text,
+            WsMessage::Close(frame) => return Err(format!("connection closed: {:?}", frame).into()),
+            _ => continue,
+        };
+        // Nothing we could act on anyway
+        let Some(element) = parse(text.as_str()) else {
+            continue;
+        };
+        match element.name.as_str() {
+            "close" => return Err("the server closed the stream".into()),
+            "error" => {
+                let condition = element.children.first().map_or("unknown", |condition| condition.name.as_str());
+                return Err(format!("stream error: {}", condition).into());
+            }
+            _ => return Ok(element),
+        }
+    }
+}
+
+/// A chat message with a body, from a joined room or a followed contact.
+/// Chat states, receipts and room subjects come without one.
+fn parse_message(stanza: &Element, config: &XmppConfig) -> Option<Message> {
+    let body = stanza.child("body")?.text.trim_end();
+    if body.is_empty() {
+        return None;
+    }
+    let from = stanza.attr("from")?;
+    let (bare, resource) = from.split_once('/').unwrap_or((from, ""));
+    let author = match stanza.attr("type") {
+        // From room@conference/nick; without a nick it's the room itself
+        Some("groupchat") if !resource.is_empty() => resource,
+        Some("groupchat") | Some("error") => return None,
+        _ => {
+            if !config.contacts.is_empty() && !config.contacts.iter().any(|contact| contact.eq_ignore_ascii_case(bare)) {
+                return None;
+            }
+            bare.split('@').next().unwrap_or(bare)
+        }
+    };
+    // Set on room history and messages stored while offline
+    let timestamp = stanza
+        .child("delay")
+        .and_then(|delay| delay.attr("stamp"))
+        .and_then(|stamp| DateTime::parse_from_rfc3339(stamp).ok())
+        .map_or_else(Utc::now, |stamp| stamp.with_timezone(&Utc));
+    // Stanza ids aren't numbers (or even always present), so the id is a
+    // hash of what identifies the message, which history replays repeat
+    let id = crate::stable_hash(&format!("{}\n{}\n{}", from, stanza.attr("id").unwrap_or(""), body)) & i64::MAX as u64;
+
+    Some(Message {
+        id,
+        source: MessageSource::Xmpp,
+        content: body.to_string(),
+        timestamp,
+        author: author.to_string(),
+        attachments: vec![],
+        reactions: vec![],
+        channel_id: Some(bare.to_string()),
+        reply_to_id: None,
+        edited: false,
+        send_status: None,
+    })
+}
+
+/// Answers a request from the server: pings (XEP-0199) succeed, anything
+/// else is declined as unsupported
+fn iq_reply(iq: &Element) -> String {
+    let id = escape(iq.attr("id").unwrap_or(""));
+    let to = iq.attr("from").map(|from| format!(r#" to="{}""#, escape(from))).unwrap_or_default();
+    if iq.child("ping").is_some() {
+        format!(r#"<iq type="result" id="{}"{}/>"#, id, to)
+    } else {
+        format!(
+            r#"<iq type="error" id="{}"{}><error type="cancel"><service-unavailable xmlns="urn:ietf:params:xml:ns:xmpp-stanzas"/></error></iq>"#,
+            id, to
+        )
+    }
+}
+
+/// An XML element, as much as stanzas need. Names lose their namespace
+/// prefix (`stream:features` is `features`); attributes keep theirs.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    /// Text directly inside the element, unescaped
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+/// Parses one complete element, skipping any XML declaration or comments
+/// before it
+fn parse(xml: &str) -> Option<Element> {
+    let mut rest = xml.trim_start();
+    loop {
+        if let Some(after) = rest.strip_prefix("<?") {
+            rest = after[after.find("?>")? + 2..].trim_start();
+        } else if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after[after.find("-->")? + 3..].trim_start();
+        } else {
+            break;
+        }
+    }
+    parse_element(&mut rest)
+}
+
+/// Parses the element at the start of `input`, advancing past it
+fn parse_element(input: &mut &str) -> Option<Element> {
+    let mut rest = input.strip_prefix('<')?;
+    let name_end = rest.find(|c: char| c.is_whitespace() || c == '/' || c == '>')?;
+    let mut element = Element {
+        name: rest[..name_end].rsplit(':').next()?.to_string(),
+        ..Element::default()
+    };
+    rest = &rest[name_end..];
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            *input = after;
+            return Some(element);
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break;
+        }
+        let equals = rest.find('=')?;
+        let key = rest[..equals].trim().to_string();
+        rest = rest[equals + 1..].trim_start();
+        let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        rest = &rest[1..];
+        let end = rest.find(quote)?;
+        element.attributes.push((key, unescape(&rest[..end])));
+        rest = &rest[end + 1..];
+    }
+
+    loop {
+        if let Some(after) = rest.strip_prefix("</") {
+            *input = &after[after.find('>')? + 1..];
+            return Some(element);
+        }
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = &after[after.find("-->")? + 3..];
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>")?;
+            element.text.push_str(&after[..end]);
+            rest = &after[end + 3..];
+        } else if rest.starts_with('<') {
+            element.children.push(parse_element(&mut rest)?);
+        } else {
+            let end = rest.find('<')?;
+            element.text.push_str(&unescape(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+}
+
+/// Decodes the predefined entities and character references
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(ampersand) = rest.find('&') {
+        out.push_str(&rest[..ampersand]);
+        rest = &rest[ampersand..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let code = match entity.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, length)) => {
+                out.push(c);
+                rest = &rest[length..];
+            }
+            // A stray ampersand, kept as written
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[async_trait]
+impl MessageProvider for XmppProvider {
+    async fn fetch_messages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Message>, BoxError> {
+        Ok(self
+            .shared
+            .received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| since.is_none_or(|since| msg.timestamp >= since))
+            .cloned()
+            .collect())
+    }
+
+    async fn fetch_messages_since_id(&self, _last_message_id: Option<u64>) -> Result<Vec<Message>, BoxError> {
+        // Ids are hashes, so they can't be compared
+        self.fetch_messages(None).await
+    }
+
+    /// Sends to the only room or contact, when there's just one
+    async fn send_message(&self, content: &str) -> Result<Option<u64>, BoxError> {
+        match (self.rooms.as_slice(), self.contacts.as_slice()) {
+            ([only], []) | ([], [only]) => self.send_message_to(only, content).await,
+            _ => Err("Select an XMPP message to reply to its chat".into()),
+        }
+    }
+
+    async fn send_message_to(&self, channel_id: &str, content: &str) -> Result<Option<u64>, BoxError> {
+        if !matches!(*self.shared.state.borrow(), Connection::Connected) {
+            // An io error, so the send is queued and retried once connected
+            return Err(Box::new(std::io::Error::new(std::io::ErrorKind::NotConnected, "XMPP is not connected")));
+        }
+        let kind = if self.is_room(channel_id) { "groupchat" } else { "chat" };
+        let stanza = format!(
+            r#"<message to="{}" type="{}" id="friend-{}"><body>{}</body></message>"#,
+            escape(channel_id),
+            kind,
+            Utc::now().timestamp_micros(),
+            escape(content)
+        );
+        self.outgoing.send(stanza).map_err(|_| "the XMPP connection has shut down")?;
+        Ok(None)
+    }
+
+    async fn send_message_with_attachment(&self, _content: &str, _attachment_path: &str) -> Result<(), BoxError> {
+        Err("XMPP does not support attachments through this interface".into())
+    }
+
+    async fn download_attachment(&self, _attachment: &crate::Attachment, _save_path: &str) -> Result<(), BoxError> {
+        Err("XMPP messages have no attachments".into())
+    }
+
+    async fn delete_message(&self, _channel_id: Option<&str>, _message_id: u64) -> Result<(), BoxError> {
+        Err("XMPP does not support deleting messages".into())
+    }
+
+    fn source(&self) -> MessageSource {
+        MessageSource::Xmpp
+    }
+
+    fn channel_id(&self) -> Option<String> {
+        None
+    }
+
+    fn has_channel(&self, channel_id: &str) -> bool {
+        self.is_room(channel_id)
+            || self.contacts.iter().any(|contact| contact.eq_ignore_ascii_case(channel_id))
+            || self.shared.received.lock().unwrap().iter().any(|m| m.channel_id.as_deref() == Some(channel_id))
+    }
+
+    fn provider_key(&self) -> String {
+        format!("xmpp_{}", self.jid)
+    }
+
+    fn display_name(&self) -> String {
+        format!("XMPP ({})", self.jid)
+    }
+
+    fn syncs_by_timestamp(&self) -> bool {
+        true
+    }
+
+    /// Waits for the background connection to sign in. Only a rejected
+    /// sign-in fails right away; a dropped connection is retried until the
+    /// deadline.
+    async fn health_check(&self) -> Result<(), BoxError> {
+        let mut state = self.shared.state.subscribe();
+        let settled = state.wait_for(|state| matches!(state, Connection::Connected | Connection::Rejected(_)));
+        if let Ok(Ok(state)) = tokio::time::timeout(SIGN_IN_TIMEOUT, settled).await {
+            return match &*state {
+                Connection::Rejected(reason) => Err(reason.clone().into()),
+                _ => Ok(()),
+            };
+        }
+
+        match &*self.shared.state.borrow() {
+            Connection::Down(error) => Err(format!("not connected after {}s: {}", SIGN_IN_TIMEOUT.as_secs(), error).into()),
+            _ => Err(format!("not connected after {}s", SIGN_IN_TIMEOUT.as_secs()).into()),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(contacts: &[&str]) -> XmppConfig {
+        XmppConfig {
+            jid: "me@example.org".to_string(),
+            password: String::new(),
+            server: None,
+            rooms: vec!["dev@conference.example.org".to_string()],
+            contacts: contacts.iter().map(|c| c.to_string()).collect(),
+            nickname: "me".to_string(),
+            fetch_timeout_secs: 30,
+        }
+    }
+
+    #[test]
+    fn parses_stanzas_with_prefixes_entities_and_children() {
+        let stanza = parse(
+            r#"<?xml version='1.0'?><stream:features xmlns:stream="http://etherx.jabber.org/streams"><mechanisms xmlns="urn:ietf:params:xml:ns:xmpp-sasl"><mechanism>SCRAM-SHA-1</mechanism><mechanism>PLAIN</mechanism></mechanisms><sm/></stream:features>"#,
+        )
+        .unwrap();
+        assert_eq!(stanza.name, "features");
+        let mechanisms: Vec<&str> = stanza.child("mechanisms").unwrap().children.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(mechanisms, ["SCRAM-SHA-1", "PLAIN"]);
+        assert!(stanza.child("sm").is_some());
+
+        let body = parse("<body a='x &amp; y'>1 &lt; 2 &#x1F389; &unknown; <![CDATA[<raw>]]></body>").unwrap();
+        assert_eq!(body.attr("a"), Some("x & y"));
+        assert_eq!(body.text, "1 < 2 🎉 &unknown; <raw>");
+        assert_eq!(unescape(&escape(r#"<a href="x">&'"#)), r#"<a href="x">&'"#);
+    }
+
+    #[test]
+    fn maps_room_and_direct_messages() {
+        let room = parse(
+            r#"<message from="dev@conference.example.org/alice" type="groupchat" id="1"><body>deploy done</body><delay xmlns="urn:xmpp:delay" stamp="2024-05-01T10:00:00Z"/></message>"#,
+        )
+        .unwrap();
+        let message = parse_message(&room, &config(&[])).unwrap();
+        assert_eq!(message.author, "alice");
+        assert_eq!(message.channel_id.as_deref(), Some("dev@conference.example.org"));
+        assert_eq!(message.timestamp.to_rfc3339(), "2024-05-01T10:00:00+00:00");
+
+        let direct = parse(r#"<message from="bob@example.org/phone" type="chat"><body>hi</body></message>"#).unwrap();
+        assert_eq!(parse_message(&direct, &config(&[])).unwrap().author, "bob");
+        assert!(parse_message(&direct, &config(&["carol@example.org"])).is_none());
+
+        let typing = parse(r#"<message from="bob@example.org/phone" type="chat"><composing xmlns="http://jabber.org/protocol/chatstates"/></message>"#).unwrap();
+        assert!(parse_message(&typing, &config(&[])).is_none());
+    }
+
+    #[tokio::test]
+    async fn health_check_rides_out_drops_but_not_rejections() {
+        let provider = XmppProvider {
+            jid: "me@example.org".to_string(),
+            rooms: vec![],
+            contacts: vec![],
+            shared: Arc::new(Shared::default()),
+            outgoing: mpsc::unbounded_channel().0,
+        };
+
+        provider.shared.state.send_replace(Connection::Down("connection reset".to_string()));
+        let shared = Arc::clone(&provider.shared);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            shared.state.send_replace(Connection::Connected);
+        });
+        assert!(provider.health_check().await.is_ok());
+
+        provider.shared.state.send_replace(Connection::Rejected("not-authorized".to_string()));
+        assert_eq!(provider.health_check().await.unwrap_err().to_string(), "not-authorized");
+    }
+}
@@ -1,33 +1,90 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-    Terminal,
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame, Terminal,
 };
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
 use std::io;
 use std::time::{Duration, Instant};
-use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 mod integrations;
+mod cli;
 mod config;
 mod database;
+mod emoji;
+mod export;
+mod html;
+mod markdown;
+mod palette;
+mod search;
+mod secrets;
+mod spellcheck;
+#[cfg(feature = "image-preview")]
+mod preview;
 
 use config::Config;
-use integrations::{IntegrationManager, telegram::TelegramProvider, discord::DiscordProvider, github::GitHubProvider, jira::JiraProvider};
+use integrations::{Assignee, IntegrationManager, LiveEvent, MessageProvider, SyncMark, Transition, telegram::TelegramProvider, discord::DiscordProvider, github::GitHubProvider, jira::{self, JiraProvider}, reddit::RedditProvider, rest::RestProvider, teams::TeamsProvider, twilio::TwilioProvider, xmpp::XmppProvider};
 use database::MessageCache;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MessageSource {
     Telegram,
     Discord,
     Github,
     Jira,
+    Reddit,
+    Xmpp,
+    Teams,
+    Sms,
+    /// Any service polled through a config-defined `RestProvider`
+    Rest,
+}
+
+impl MessageSource {
+    /// Parses a source name as written in config (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "telegram" => Some(MessageSource::Telegram),
+            "discord" => Some(MessageSource::Discord),
+            "github" => Some(MessageSource::Github),
+            "jira" => Some(MessageSource::Jira),
+            "reddit" => Some(MessageSource::Reddit),
+            "xmpp" => Some(MessageSource::Xmpp),
+            "teams" => Some(MessageSource::Teams),
+            "sms" => Some(MessageSource::Sms),
+            "rest" => Some(MessageSource::Rest),
+            _ => None,
+        }
+    }
+
+    /// Two-letter label for tight spaces like the unread badge
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            MessageSource::Telegram => "TG",
+            MessageSource::Discord => "DC",
+            MessageSource::Github => "GH",
+            MessageSource::Jira => "JR",
+            MessageSource::Reddit => "RD",
+            MessageSource::Xmpp => "XM",
+            MessageSource::Teams => "MT",
+            MessageSource::Sms => "SM",
+            MessageSource::Rest => "RS",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +93,8 @@ pub struct Attachment {
     pub url: String,
     pub file_type: AttachmentType,
     pub size: Option<u64>,
+    /// Where it was last downloaded to
+    pub local_path: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,9 +103,39 @@ pub enum AttachmentType {
     Video,
     Audio,
     Document,
+    Archive,
     Other,
 }
 
+#[derive(Debug, Clone)]
+pub struct Reaction {
+    pub emoji: String,
+    pub count: u32,
+}
+
+/// How far a message we sent got. Received messages have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendStatus {
+    /// Sending, or queued until the service can be reached
+    Pending,
+    Sent,
+    Failed,
+}
+
+impl SendStatus {
+    /// Shown after the message in the list
+    fn glyph(self, plain: bool) -> &'static str {
+        match (self, plain) {
+            (SendStatus::Pending, false) => "⏳",
+            (SendStatus::Sent, false) => "✓",
+            (SendStatus::Failed, false) => "✗",
+            (SendStatus::Pending, true) => "(sending)",
+            (SendStatus::Sent, true) => "(sent)",
+            (SendStatus::Failed, true) => "(failed)",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Message {
     pub id: u64,
@@ -55,24 +144,247 @@ pub struct Message {
     pub timestamp: DateTime<Utc>,
     pub author: String,
     pub attachments: Vec<Attachment>,
+    pub reactions: Vec<Reaction>,
     pub channel_id: Option<String>,
+    /// The message this one replies to, from the same source
+    pub reply_to_id: Option<u64>,
+    /// Changed since it was first sent
+    pub edited: bool,
+    /// Set on messages sent from here
+    pub send_status: Option<SendStatus>,
+}
+
+/// Where an outgoing message goes: a provider (by index into the
+/// integration manager) and optionally a specific channel within it
+#[derive(Debug, Clone)]
+struct SendTarget {
+    provider_index: usize,
+    channel_id: Option<String>,
+}
+
+/// A send queued by the input box, dispatched by the event loop after the
+/// optimistic message has been drawn
+struct PendingSend {
+    content: String,
+    target: SendTarget,
+    /// The message being replied to, for native threading
+    reply_to: Option<u64>,
+    optimistic_id: u64,
+}
+
+/// The `@mention` being completed in the input box
+struct Autocomplete {
+    /// Byte offset of the `@` in the input
+    start: usize,
+    /// Authors matching what follows the `@`, most recently active first
+    candidates: Vec<String>,
+    selected: usize,
+}
+
+/// A row of the messages list: either a day separator or a message (by
+/// index into `App::messages`)
+enum ListRow {
+    DateHeader(String),
+    Message(usize),
+}
+
+/// A thread fetched in the background, for the (source, id) of its parent
+type ThreadResult = ((MessageSource, u64), Result<Vec<Message>, String>);
+
+/// One attachment saved (or not) by a batch download: the message's
+/// (source, id), the attachment's filename, and where it went
+type BatchResult = ((MessageSource, u64), String, Result<String, String>);
+
+/// How far a batch download has got
+struct BatchDownload {
+    total: usize,
+    saved: usize,
+    /// Already on disk before the batch started
+    skipped: usize,
+    failed: usize,
+    last_error: Option<String>,
+}
+
+enum Popup {
+    /// Choose which provider a new message goes to
+    ProviderPicker { selected: usize },
+    /// Choose a workflow transition for the selected issue
+    TransitionPicker {
+        provider_index: usize,
+        channel_id: String,
+        transitions: Vec<Transition>,
+        selected: usize,
+    },
+    /// Fuzzy-search actions and channels; `selected` indexes the matches
+    Palette {
+        entries: Vec<palette::Entry>,
+        query: String,
+        selected: usize,
+    },
+    /// Choose which attachment of a message to open. The message is kept by
+    /// (source, id) since a refresh may reorder the list meanwhile.
+    Attachments {
+        message: (MessageSource, u64),
+        filenames: Vec<String>,
+        selected: usize,
+    },
+    /// Choose who the selected issue is assigned to; `None` unassigns
+    AssigneePicker {
+        provider_index: usize,
+        channel_id: String,
+        assignees: Vec<Option<Assignee>>,
+        selected: usize,
+    },
+    /// Search emoji by shortcode to insert into the input; `selected`
+    /// indexes the matches
+    Emoji { query: String, selected: usize },
 }
 
 struct App {
     messages: Vec<Message>,
     selected_message: Option<usize>,
-    integration_manager: IntegrationManager,
+    /// Shared with background refresh tasks
+    integration_manager: Arc<IntegrationManager>,
     input_mode: bool,
     input_text: String,
     last_refresh: Instant,
     message_limit: usize,
     colors: config::ColorConfig,
+    icons: config::IconConfig,
+    time: config::TimeConfig,
+    layout: config::LayoutConfig,
     cache: MessageCache,
     is_refreshing: bool,
+    refresh_started: Instant,
+    refresh_sender: mpsc::UnboundedSender<Vec<Message>>,
+    refresh_results: mpsc::UnboundedReceiver<Vec<Message>>,
+    /// Indices of providers that have returned during the current refresh
+    providers_done: HashSet<usize>,
+    progress_sender: mpsc::UnboundedSender<usize>,
+    refresh_progress: mpsc::UnboundedReceiver<usize>,
+    /// Replies shown under a message (e.g. Jira comments), by (source, parent id)
+    threads: HashMap<(MessageSource, u64), Vec<Message>>,
+    /// Parents whose thread was requested, with the parent's timestamp then,
+    /// so an updated issue fetches its comments again
+    threads_requested: HashMap<(MessageSource, u64), DateTime<Utc>>,
+    thread_sender: mpsc::UnboundedSender<ThreadResult>,
+    thread_results: mpsc::UnboundedReceiver<ThreadResult>,
+    batch_download: Option<BatchDownload>,
+    batch_sender: mpsc::UnboundedSender<BatchResult>,
+    batch_results: mpsc::UnboundedReceiver<BatchResult>,
+    last_refresh_at: Option<DateTime<Utc>>,
+    source_filter: Option<MessageSource>,
+    auto_refresh: bool,
+    refresh_interval: Duration,
+    render_markdown: bool,
+    render_shortcodes: bool,
+    /// Draw without styling or non-ASCII symbols
+    plain: bool,
+    group_by_author: bool,
+    wrap_list: bool,
+    alerts: config::AlertConfig,
+    /// Set once the spell checking dictionary has loaded
+    dictionary: Arc<OnceLock<spellcheck::Dictionary>>,
+    /// Do-not-disturb, until turned off again
+    dnd: bool,
+    /// Alerts are silenced until this deadline
+    snoozed_until: Option<Instant>,
+    /// Bookmarked messages as saved, so they can be shown after pruning
+    bookmarks: HashMap<(MessageSource, u64), Message>,
+    bookmarks_only: bool,
+    read: HashSet<(MessageSource, u64)>,
+    /// Unread cached messages per source, for the badge in the list title
+    unread_counts: HashMap<MessageSource, usize>,
+    mark_read_on_select: bool,
+    confirm_send: bool,
+    /// Where the input would go, while asking whether to send it there
+    confirming_send: Option<SendTarget>,
+    /// The search box text, and whether it's being typed in
+    search_text: String,
+    search_editing: bool,
+    /// Last valid parse of `search_text`; None shows everything
+    search: Option<search::Query>,
+    search_error: Option<String>,
+    muted_channels: HashSet<(MessageSource, String)>,
+    /// Lowercased, so matching ignores case
+    muted_authors: HashSet<String>,
+    live_events: mpsc::UnboundedReceiver<LiveEvent>,
+    live_sources: Vec<MessageSource>,
+    /// Providers (by key) whose connection is lost for good, with the reason
+    connection_failures: HashMap<String, String>,
+    /// False once refreshes keep failing to reach any provider (or the
+    /// optional probe fails). Auto-refresh then only retries now and then.
+    online: bool,
+    /// Refreshes in a row that reached no provider
+    unreachable_refreshes: usize,
+    connectivity: mpsc::UnboundedReceiver<bool>,
+    popup: Option<Popup>,
+    compose_target: Option<SendTarget>,
+    /// Set while the input box holds a sent message being edited: where it
+    /// was sent and its (source, id)
+    editing: Option<(SendTarget, (MessageSource, u64))>,
+    /// The id and author of the message the input quotes, set by `>`
+    replying_to: Option<(u64, String)>,
+    /// Open while the word being typed is an `@mention`
+    autocomplete: Option<Autocomplete>,
+    /// Where a typing indicator was last sent and when, to throttle them
+    last_typing: Option<((usize, Option<String>), Instant)>,
+    /// When the input last changed without the draft being saved
+    draft_changed: Option<Instant>,
+    /// The last message sent where it can be deleted again: where it went,
+    /// its id, its text and when, so `u` can take it back
+    last_sent: Option<(SendTarget, u64, String, Instant)>,
+    pending_send: Option<PendingSend>,
+    queued_sends: usize,
+    /// The id the last local message (optimistic sends, errors) was given;
+    /// they count down from `LOCAL_IDS_START`
+    last_local_id: u64,
+    /// Where the messages list was last drawn and how far it was scrolled,
+    /// used to map mouse clicks back to messages
+    list_area: Cell<Rect>,
+    list_offset: Cell<usize>,
+    /// Lines each list row took when last drawn, which differ once rows wrap
+    list_heights: std::cell::RefCell<Vec<usize>>,
+    /// The area shared by the list and content panes, for resizing them
+    panes_area: Cell<Rect>,
+    /// Set while the divider between the panes is being dragged
+    resizing: bool,
+    /// Created on first copy; kept alive because on X11 the copied text
+    /// disappears when the clipboard handle is dropped
+    clipboard: Option<arboard::Clipboard>,
+    /// Short-lived feedback shown in the status bar
+    status_message: Option<(String, Instant)>,
+    download_dir: std::path::PathBuf,
+    should_quit: bool,
+    /// Set when built with `image-preview` on a terminal with graphics
+    /// support; drawing needs it mutably, hence the RefCell
+    #[cfg(feature = "image-preview")]
+    image_preview: std::cell::RefCell<Option<preview::ImagePreview>>,
 }
 
-fn parse_color(color_name: &str) -> Color {
-    match color_name.to_lowercase().as_str() {
+/// Color names accepted in config, besides `#rrggbb` and xterm-256 indices
+pub const COLOR_NAMES: [&str; 17] = [
+    "Black", "Red", "Green", "Yellow", "Blue", "Magenta", "Cyan", "Gray", "DarkGray", "LightRed",
+    "LightGreen", "LightYellow", "LightBlue", "LightMagenta", "LightCyan", "White", "Default",
+];
+
+/// A configured color, or `None` if it isn't one of `COLOR_NAMES`, `#rrggbb`
+/// or an xterm-256 index (`0`-`255`)
+pub fn try_parse_color(color_name: &str) -> Option<Color> {
+    let color_name = color_name.trim();
+    // `#rrggbb` for themes beyond the 16 named colors
+    if let Some(hex) = color_name.strip_prefix('#') {
+        return match u32::from_str_radix(hex, 16) {
+            // from_str_radix would also take a leading `+`
+            Ok(rgb) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Some(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)),
+            _ => None,
+        };
+    }
+    // Out-of-range numbers fail to parse as u8 and are flagged like typos
+    if color_name.starts_with(|c: char| c.is_ascii_digit()) {
+        return color_name.parse::<u8>().ok().map(Color::Indexed);
+    }
+    let color = match color_name.to_lowercase().as_str() {
         "black" => Color::Black,
         "red" => Color::Red,
         "green" => Color::Green,
@@ -89,64 +401,451 @@ fn parse_color(color_name: &str) -> Color {
         "lightmagenta" => Color::LightMagenta,
         "lightcyan" => Color::LightCyan,
         "white" => Color::White,
-        _ => Color::Reset, // Use terminal default
+        "default" | "reset" => Color::Reset,
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// Unknown names (flagged by `Config::warnings`) fall back to the terminal default
+fn parse_color(color_name: &str) -> Color {
+    try_parse_color(color_name).unwrap_or(Color::Reset)
+}
+
+/// Removes repeated (source, id) pairs, keeping the first occurrence
+fn dedup_messages(messages: &mut Vec<Message>) {
+    let mut seen = HashSet::new();
+    messages.retain(|m| seen.insert((m.source, m.id)));
+}
+
+/// Services don't know what was sent from here, so fetched copies take the
+/// send status of the same message in `previous`
+fn carry_send_status(messages: &mut [Message], previous: &[Message]) {
+    let statuses: HashMap<(MessageSource, u64), SendStatus> = previous
+        .iter()
+        .filter_map(|m| Some(((m.source, m.id), m.send_status?)))
+        .collect();
+    for message in messages.iter_mut().filter(|m| m.send_status.is_none()) {
+        message.send_status = statuses.get(&(message.source, message.id)).copied();
+    }
+}
+
+/// Why `message` can't be edited through `provider`, if it can't
+fn edit_refusal(message: &Message, provider: &(dyn MessageProvider + Send + Sync)) -> Option<String> {
+    // Only messages sent from here are known to be ours
+    if message.send_status != Some(SendStatus::Sent) {
+        return Some("Only messages you sent can be edited".to_string());
+    }
+    if !provider.supports_edit() {
+        return Some(format!("{} does not support editing messages", provider.display_name()));
+    }
+    None
+}
+
+/// 64-bit FNV-1a. Unlike the std hasher its output is specified, so values
+/// stay stable across runs and builds and can be persisted.
+pub fn stable_hash(input: &str) -> u64 {
+    input.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// How long typing has to pause before the draft is saved
+const DRAFT_SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Names offered at most when completing an `@mention`
+const MAX_MENTION_CANDIDATES: usize = 8;
+
+/// Messages that only exist on screen get ids counting down from here, far
+/// above any provider's (queued sends sit above it, at `u64::MAX - queue id`)
+const LOCAL_IDS_START: u64 = u64::MAX - (1 << 32);
+
+/// How often `CONNECTIVITY_PROBE` is tried, and how long a try may take
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Refreshes in a row that reach no provider before showing OFFLINE
+const OFFLINE_AFTER_FAILURES: usize = 2;
+
+/// While offline, auto-refresh only retries this often (or at the normal
+/// interval, if that's longer)
+const OFFLINE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long after sending a message `u` can still take it back
+const UNDO_WINDOW: Duration = Duration::from_secs(5);
+
+/// Older cached messages loaded at a time when moving past the last one
+const SCROLL_BACK_PAGE: usize = 100;
+
+const DEFAULT_AUTHOR_PALETTE: [Color; 10] = [
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::LightBlue,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::Red,
+];
+
+/// Sends `content` through `provider`, addressed to `channel_id` when given
+async fn deliver(
+    provider: &(dyn MessageProvider + Send + Sync),
+    channel_id: Option<&str>,
+    reply_to: Option<u64>,
+    content: &str,
+) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(message_id) = reply_to {
+        return provider.send_reply(channel_id, message_id, content).await;
+    }
+    match channel_id {
+        Some(channel_id) => provider.send_message_to(channel_id, content).await,
+        None => provider.send_message(content).await,
+    }
+}
+
+/// Accent color for a source, from config or a built-in default
+fn source_color(source: MessageSource, colors: &config::ColorConfig) -> Color {
+    let (configured, default) = match source {
+        MessageSource::Telegram => (&colors.telegram_color, Color::Cyan),
+        MessageSource::Discord => (&colors.discord_color, Color::Magenta),
+        MessageSource::Github => (&colors.github_color, Color::Green),
+        MessageSource::Jira => (&colors.jira_color, Color::Blue),
+        MessageSource::Reddit => (&colors.reddit_color, Color::LightRed),
+        MessageSource::Xmpp => (&colors.xmpp_color, Color::LightGreen),
+        MessageSource::Teams => (&colors.teams_color, Color::LightMagenta),
+        MessageSource::Sms => (&colors.sms_color, Color::LightGreen),
+        MessageSource::Rest => (&colors.rest_color, Color::Yellow),
+    };
+    configured.as_deref().map(parse_color).unwrap_or(default)
+}
+
+/// The body of a refresh, run off the UI task: retries queued sends,
+/// fetches (incrementally when possible), caches what's new and returns the
+/// messages to display
+async fn fetch_refresh(
+    manager: &IntegrationManager,
+    cache: &MessageCache,
+    message_limit: usize,
+    progress: &mpsc::UnboundedSender<usize>,
+) -> Vec<Message> {
+    flush_send_queue(manager, cache).await;
+    
+    // Try incremental sync first (much faster)
+    let (new_messages, sync_marks) = manager.fetch_incremental_messages(cache, Some(message_limit), Some(progress)).await;
+    
+    let cached_messages = cache.get_cached_messages(Some(message_limit)).await.unwrap_or_default();
+    let mut messages_to_use = if new_messages.is_empty() {
+        // Fallback to full fetch if incremental returns nothing
+        manager.fetch_all_messages(None, Some(message_limit), None).await
+    } else {
+        // Merge new messages with cached ones, preferring the freshly fetched copy
+        let mut merged = new_messages.clone();
+        merged.extend(cached_messages.iter().cloned());
+        dedup_messages(&mut merged);
+        manager.apply_limits(&mut merged, Some(message_limit));
+        merged
+    };
+    carry_send_status(&mut messages_to_use, &cached_messages);
+    
+    // Cache any new messages
+    if !new_messages.is_empty() {
+        if let Err(e) = cache.cache_messages(&new_messages).await {
+            eprintln!("Warning: Failed to cache messages: {}", e);
+        }
+        
+        // Update sync state for each provider
+        for mark in sync_marks {
+            let (provider_key, result) = match mark {
+                SyncMark::Timestamp(provider_key, latest) => {
+                    let result = cache.update_sync_timestamp(&provider_key, latest).await;
+                    (provider_key, result)
+                }
+                SyncMark::LastId(provider_key, latest) => {
+                    let result = cache.update_sync_state(&provider_key, latest).await;
+                    (provider_key, result)
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("Warning: Failed to update sync state for {}: {}", provider_key, e);
+            }
+        }
+    }
+    
+    messages_to_use
+}
+
+/// Retries every queued send, dropping the ones that go through. Sends
+/// for a provider that's no longer configured can never go, so they're
+/// dropped too; a network error stops the flush until the next refresh.
+async fn flush_send_queue(manager: &IntegrationManager, cache: &MessageCache) {
+    let queued = match cache.get_pending_sends().await {
+        Ok(queued) => queued,
+        Err(e) => {
+            eprintln!("Warning: Failed to load send queue: {}", e);
+            return;
+        }
+    };
+
+    for send in queued {
+        let Some(provider) = manager.find_provider_by_key(&send.provider_key) else {
+            eprintln!("Warning: Dropped a queued message for {}, which is no longer configured", send.provider_key);
+            if let Err(e) = cache.delete_pending_send(send.id).await {
+                eprintln!("Warning: Failed to update send queue: {}", e);
+            }
+            continue;
+        };
+
+        let result = deliver(provider, send.channel_id.as_deref(), None, &send.content).await;
+        if let Ok(Some(id)) = &result {
+            record_sent(cache, provider.source(), *id, send.channel_id.clone(), None, &send.content).await;
+        }
+        let offline = result.as_ref().is_err_and(|e| integrations::is_network_error(e.as_ref()));
+        let update = match result {
+            Ok(_) => cache.delete_pending_send(send.id).await,
+            Err(e) => cache.record_send_failure(send.id, &e.to_string()).await,
+        };
+        if let Err(e) = update {
+            eprintln!("Warning: Failed to update send queue: {}", e);
+        }
+        // The rest would fail the same way
+        if offline {
+            break;
+        }
+    }
+}
+
+/// Tries a TCP connection to `address` every `PROBE_INTERVAL` and reports
+/// whenever the outcome changes. Stops once the app has gone.
+async fn watch_connectivity(address: String, changes: mpsc::UnboundedSender<bool>) {
+    let mut last = true;
+    loop {
+        let online = matches!(
+            tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect(address.as_str())).await,
+            Ok(Ok(_))
+        );
+        if online != last {
+            if changes.send(online).is_err() {
+                return;
+            }
+            last = online;
+        }
+        tokio::time::sleep(PROBE_INTERVAL).await;
+    }
+}
+
+/// The name an attachment is saved under. The message id keeps same-named
+/// files from different messages apart.
+fn attachment_file_name(message_id: u64, filename: &str) -> String {
+    let filename: String = filename
+        .chars()
+        .map(|c| if std::path::is_separator(c) { '_' } else { c })
+        .collect();
+    format!("{}-{}", message_id, filename)
+}
+
+/// `name` with anything but letters, digits and dashes replaced, for use
+/// as a file or folder name
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Caches a delivered message as sent. The service's copy replaces it on
+/// the next refresh but keeps the status.
+async fn record_sent(cache: &MessageCache, source: MessageSource, id: u64, channel_id: Option<String>, reply_to_id: Option<u64>, content: &str) {
+    let message = Message {
+        id,
+        source,
+        content: content.to_string(),
+        timestamp: Utc::now(),
+        author: "You".to_string(),
+        attachments: vec![],
+        reactions: vec![],
+        channel_id,
+        reply_to_id,
+        edited: false,
+        send_status: Some(SendStatus::Sent),
+    };
+    if let Err(e) = cache.cache_messages(&[message]).await {
+        eprintln!("Warning: Failed to record sent message: {}", e);
+    }
+}
+
+/// Whether `message`'s content or author contains any of the lowercased
+/// `terms`
+fn matches_terms(terms: &[String], message: &Message) -> bool {
+    if terms.is_empty() {
+        return false;
+    }
+    let content = message.content.to_lowercase();
+    let author = message.author.to_lowercase();
+    terms.iter().any(|t| content.contains(t) || author.contains(t))
+}
+
+/// Up to two letters standing for `author`, e.g. `JD` for `jane.doe` or
+/// `Jane Doe`, for the badge in the content pane
+fn initials(author: &str) -> String {
+    let mut initials: String = author
+        .split(|c: char| !c.is_alphanumeric())
+        .filter_map(|word| word.chars().next())
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect();
+    if initials.is_empty() {
+        initials.push('?');
+    }
+    initials
+}
+
+/// Picks a stable palette entry so the same author always gets the same color
+fn author_color(author: &str, palette: &[Color]) -> Color {
+    if palette.is_empty() {
+        return Color::Reset;
+    }
+    palette[(stable_hash(author) % palette.len() as u64) as usize]
+}
+
+/// Every provider that needs only its config and an HTTP client, with its
+/// fetch timeout. Telegram accounts sign in and Discord finds its channels
+/// first, so callers build those themselves.
+fn build_providers(config: &Config, http_client: &reqwest::Client, live_sender: &mpsc::UnboundedSender<LiveEvent>) -> Vec<(Box<dyn MessageProvider + Send + Sync>, Duration)> {
+    let mut providers: Vec<(Box<dyn MessageProvider + Send + Sync>, Duration)> = Vec::new();
+    if let Some(github) = &config.github {
+        let provider = GitHubProvider::new(github.token.clone(), github.username.clone(), http_client.clone());
+        providers.push((Box::new(provider), Duration::from_secs(github.fetch_timeout_secs)));
+    }
+    for jira in &config.jira {
+        let provider = JiraProvider::new(jira.base_url.clone(), jira.email.clone(), jira.api_token.clone(), jira.project_keys.clone(), http_client.clone());
+        providers.push((Box::new(provider), Duration::from_secs(jira.fetch_timeout_secs)));
+    }
+    if let Some(reddit) = &config.reddit {
+        let provider = RedditProvider::new(reddit.client_id.clone(), reddit.client_secret.clone(), reddit.refresh_token.clone(), http_client.clone());
+        providers.push((Box::new(provider), Duration::from_secs(reddit.fetch_timeout_secs)));
+    }
+    if let Some(xmpp) = &config.xmpp {
+        let provider = XmppProvider::new(xmpp.clone(), live_sender.clone());
+        providers.push((Box::new(provider), Duration::from_secs(xmpp.fetch_timeout_secs)));
+    }
+    if let Some(teams) = &config.teams {
+        let provider = TeamsProvider::new(teams.clone(), http_client.clone());
+        providers.push((Box::new(provider), Duration::from_secs(teams.fetch_timeout_secs)));
     }
+    if let Some(twilio) = &config.twilio {
+        let provider = TwilioProvider::new(twilio.account_sid.clone(), twilio.auth_token.clone(), twilio.from_number.clone(), http_client.clone());
+        providers.push((Box::new(provider), Duration::from_secs(twilio.fetch_timeout_secs)));
+    }
+    for rest in &config.rest {
+        let provider = RestProvider::new(rest.clone(), http_client.clone());
+        providers.push((Box::new(provider), Duration::from_secs(rest.fetch_timeout_secs)));
+    }
+    providers
 }
 
 impl App {
-    async fn new(config: Config, telegram_provider: Option<TelegramProvider>) -> Result<App, Box<dyn std::error::Error + Send + Sync>> {
-        // Initialize database cache - use absolute path
-        let db_path = std::env::current_dir()
-            .unwrap_or_else(|_| std::path::PathBuf::from("."))
-            .join("messages.db");
-        let db_url = format!("sqlite://{}", db_path.to_string_lossy());
+    async fn new(
+        config: Config,
+        telegram_providers: Vec<TelegramProvider>,
+        live_sender: mpsc::UnboundedSender<LiveEvent>,
+        live_events: mpsc::UnboundedReceiver<LiveEvent>,
+    ) -> Result<App, Box<dyn std::error::Error + Send + Sync>> {
+        let db_path = &config.database.path;
+        let db_url = database::database_url(db_path)?;
         println!("Initializing database at: {}", db_path.display());
-        let cache = MessageCache::new(&db_url).await.map_err(|e| {
+        let mut cache = MessageCache::new(&db_url, config.database.passphrase.as_deref()).await.map_err(|e| {
             eprintln!("Failed to initialize database: {}", e);
             e
         })?;
+        cache.set_retention(config.database.max_cached_messages, config.database.source_max_cached_messages.clone());
         println!("Database initialized successfully!");
+        let http_client = integrations::http_client(config.proxy_url.as_deref(), &config.user_agent)?;
         let mut integration_manager = IntegrationManager::new();
-        
-        if let Some(provider) = telegram_provider {
-            integration_manager.add_provider(Box::new(provider));
+        integration_manager.set_source_limits(config.source_limits.clone());
+        integration_manager.set_ignored_authors(config.ignored_authors.clone());
+        let (refresh_sender, refresh_results) = mpsc::unbounded_channel();
+        let (progress_sender, refresh_progress) = mpsc::unbounded_channel();
+        let (thread_sender, thread_results) = mpsc::unbounded_channel();
+        let (batch_sender, batch_results) = mpsc::unbounded_channel();
+        let (connectivity_sender, connectivity) = mpsc::unbounded_channel();
+        if let Some(address) = config.connectivity_probe.clone() {
+            tokio::spawn(watch_connectivity(address, connectivity_sender));
         }
         
-        if let Some(discord_config) = config.discord {
-            for channel_id in discord_config.channel_ids {
-                let provider = DiscordProvider::new(
-                    discord_config.user_token.clone(),
-                    channel_id,
-                );
-                integration_manager.add_provider(Box::new(provider));
+        // Signed in up front, one per configured account
+        for (provider, telegram_config) in telegram_providers.into_iter().zip(&config.telegram) {
+            if telegram_config.live_updates {
+                provider.spawn_live_updates();
             }
+            integration_manager.add_provider(Box::new(provider), Duration::from_secs(telegram_config.fetch_timeout_secs));
         }
         
-        if let Some(github_config) = config.github {
-            let provider = GitHubProvider::new(
-                github_config.token,
-                github_config.username,
-            );
-            integration_manager.add_provider(Box::new(provider));
+        if let Some(discord_config) = &config.discord {
+            let token = &discord_config.user_token;
+            let mut providers: Vec<DiscordProvider> = discord_config.channel_ids
+                .iter()
+                .map(|channel_id| DiscordProvider::new(token.clone(), channel_id.clone(), http_client.clone()))
+                .collect();
+            futures::future::join_all(providers.iter_mut().map(|p| p.resolve_channel_name())).await;
+
+            let discovered = integrations::discord::discover_channels(&http_client, token, discord_config.include_dms, &discord_config.guild_ids).await;
+            let mut channel_ids = discord_config.channel_ids.clone();
+            for provider in discovered {
+                let channel_id = provider.channel_id().unwrap_or_default();
+                if !channel_ids.contains(&channel_id) {
+                    channel_ids.push(channel_id);
+                    providers.push(provider);
+                }
+            }
+
+            if discord_config.gateway {
+                integrations::discord::spawn_gateway(token.clone(), channel_ids, live_sender.clone());
+            }
+            for provider in providers {
+                integration_manager.add_provider(Box::new(provider), Duration::from_secs(discord_config.fetch_timeout_secs));
+            }
         }
         
-        if let Some(jira_config) = config.jira {
-            let provider = JiraProvider::new(
-                jira_config.base_url,
-                jira_config.email,
-                jira_config.api_token,
-                jira_config.project_keys,
-            );
-            integration_manager.add_provider(Box::new(provider));
+        for (provider, fetch_timeout) in build_providers(&config, &http_client, &live_sender) {
+            integration_manager.add_provider(provider, fetch_timeout);
         }
 
         // Try to load cached messages first for instant startup
         let cached_messages = cache.get_cached_messages(Some(config.message_limit)).await.unwrap_or_default();
+        let queued_sends = cache.count_pending_sends().await.unwrap_or(0);
+        let read = cache.get_read_messages().await.unwrap_or_default().into_iter().collect();
+        let unread_counts = cache.unread_counts().await.unwrap_or_default();
+
+        let dictionary = Arc::new(OnceLock::new());
+        if let Some(spellcheck) = config.spellcheck.clone() {
+            let dictionary = dictionary.clone();
+            // Reading a dictionary takes a moment; typing shouldn't wait for it
+            tokio::task::spawn_blocking(move || {
+                match spellcheck::Dictionary::load(&spellcheck.language, spellcheck.dictionary.as_deref()) {
+                    Ok(loaded) => {
+                        let _ = dictionary.set(loaded);
+                    }
+                    Err(e) => eprintln!("Warning: Spell checking is off: {}", e),
+                }
+            });
+        }
+        let bookmarks = cache
+            .get_bookmarks()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|m| ((m.source, m.id), m))
+            .collect();
+        let (stored_muted_channels, stored_muted_authors) = cache.get_mutes().await.unwrap_or_default();
+        let muted_channels = config.mutes.channels.into_iter().chain(stored_muted_channels).collect();
+        let muted_authors = config.mutes.authors.into_iter().chain(stored_muted_authors).collect();
         let messages = if !cached_messages.is_empty() {
             cached_messages
         } else {
             // If no cached messages, fetch from providers (this will be slow the first time)
-            integration_manager.fetch_all_messages(None, Some(config.message_limit)).await
+            integration_manager.fetch_all_messages(None, Some(config.message_limit), None).await
         };
         
         let selected_message = if messages.is_empty() { None } else { Some(0) };
@@ -154,77 +853,242 @@ impl App {
         Ok(App {
             messages,
             selected_message,
-            integration_manager,
+            integration_manager: Arc::new(integration_manager),
             input_mode: false,
             input_text: String::new(),
             last_refresh: Instant::now(),
             message_limit: config.message_limit,
             colors: config.colors,
+            icons: config.icons,
+            time: config.time,
+            layout: config.layout,
             cache,
             is_refreshing: false,
+            refresh_started: Instant::now(),
+            refresh_sender,
+            refresh_results,
+            providers_done: HashSet::new(),
+            progress_sender,
+            refresh_progress,
+            threads: HashMap::new(),
+            threads_requested: HashMap::new(),
+            thread_sender,
+            thread_results,
+            batch_download: None,
+            batch_sender,
+            batch_results,
+            last_refresh_at: None,
+            source_filter: None,
+            auto_refresh: true,
+            refresh_interval: Duration::from_secs(config.refresh_interval_secs),
+            render_markdown: config.render_markdown,
+            render_shortcodes: config.render_shortcodes,
+            plain: config.plain,
+            group_by_author: config.group_by_author,
+            wrap_list: config.wrap_list,
+            alerts: config.alerts,
+            dictionary,
+            dnd: false,
+            snoozed_until: None,
+            bookmarks,
+            bookmarks_only: false,
+            read,
+            unread_counts,
+            mark_read_on_select: config.mark_read_on_select,
+            confirm_send: config.confirm_send,
+            confirming_send: None,
+            search_text: String::new(),
+            search_editing: false,
+            search: None,
+            search_error: None,
+            muted_channels,
+            muted_authors,
+            live_events,
+            live_sources: Vec::new(),
+            connection_failures: HashMap::new(),
+            online: true,
+            unreachable_refreshes: 0,
+            connectivity,
+            popup: None,
+            compose_target: None,
+            editing: None,
+            replying_to: None,
+            autocomplete: None,
+            last_typing: None,
+            draft_changed: None,
+            last_sent: None,
+            pending_send: None,
+            queued_sends,
+            last_local_id: LOCAL_IDS_START,
+            list_area: Cell::new(Rect::default()),
+            list_heights: std::cell::RefCell::new(Vec::new()),
+            panes_area: Cell::new(Rect::default()),
+            resizing: false,
+            list_offset: Cell::new(0),
+            clipboard: None,
+            status_message: None,
+            download_dir: config.download_dir.clone(),
+            should_quit: false,
+            #[cfg(feature = "image-preview")]
+            image_preview: std::cell::RefCell::new(preview::ImagePreview::detect()),
         })
     }
     
-    async fn refresh_messages(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// Starts a refresh on a background task; the result arrives through
+    /// `refresh_results` so input keeps working while providers are slow.
+    fn request_refresh(&mut self) {
         if self.is_refreshing {
-            return Ok(()); // Avoid multiple concurrent refreshes
+            return; // Avoid multiple concurrent refreshes
         }
         
         self.is_refreshing = true;
+        self.refresh_started = Instant::now();
+        self.providers_done.clear();
         
-        // Try incremental sync first (much faster)
-        let new_messages = self.integration_manager.fetch_incremental_messages(&self.cache, Some(self.message_limit)).await;
-        
-        let messages_to_use = if new_messages.is_empty() {
-            // Fallback to full fetch if incremental returns nothing
-            self.integration_manager.fetch_all_messages(None, Some(self.message_limit)).await
-        } else {
-            // Merge new messages with cached ones
-            let mut cached_messages = self.cache.get_cached_messages(Some(self.message_limit)).await.unwrap_or_default();
-            cached_messages.extend(new_messages.clone());
-            cached_messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-            cached_messages.truncate(self.message_limit);
-            cached_messages
+        let manager = Arc::clone(&self.integration_manager);
+        let cache = self.cache.clone();
+        let message_limit = self.message_limit;
+        let results = self.refresh_sender.clone();
+        let progress = self.progress_sender.clone();
+        tokio::spawn(async move {
+            let messages = fetch_refresh(&manager, &cache, message_limit, &progress).await;
+            let _ = results.send(messages);
+        });
+    }
+    
+    async fn process_refresh_results(&mut self) {
+        while let Ok(provider_index) = self.refresh_progress.try_recv() {
+            self.providers_done.insert(provider_index);
+        }
+        while let Ok(messages) = self.refresh_results.try_recv() {
+            self.record_reachability(!self.integration_manager.last_fetch_unreachable());
+            self.apply_refresh(messages).await;
+        }
+    }
+    
+    /// Shows the cached thread of the selected message right away and fetches
+    /// the current one in the background
+    async fn load_selected_thread(&mut self) {
+        let Some(parent) = self.get_selected_message().cloned() else {
+            return;
         };
-        
-        // Cache any new messages
-        if !new_messages.is_empty() {
-            if let Err(e) = self.cache.cache_messages(&new_messages).await {
-                eprintln!("Warning: Failed to cache messages: {}", e);
-            }
-            
-            // Update sync state for each provider
-            for provider in &self.integration_manager.providers {
-                let provider_key = provider.provider_key();
-                let provider_messages: Vec<_> = new_messages.iter()
-                    .filter(|m| m.source == provider.source())
-                    .collect();
-                
-                if let Some(latest_message) = provider_messages.iter().max_by_key(|m| m.id) {
-                    if let Err(e) = self.cache.update_sync_state(&provider_key, latest_message.id).await {
-                        eprintln!("Warning: Failed to update sync state for {}: {}", provider_key, e);
+        let key = (parent.source, parent.id);
+        if self.threads_requested.get(&key) == Some(&parent.timestamp) {
+            return;
+        }
+        self.threads_requested.insert(key, parent.timestamp);
+
+        if !self.threads.contains_key(&key)
+            && let Ok(cached) = self.cache.get_thread(parent.source, parent.id).await
+            && !cached.is_empty()
+        {
+            self.threads.insert(key, cached);
+        }
+
+        let Some(provider_index) = self.integration_manager.find_provider_index(parent.source, parent.channel_id.as_deref()) else {
+            return;
+        };
+        let manager = Arc::clone(&self.integration_manager);
+        let cache = self.cache.clone();
+        let results = self.thread_sender.clone();
+        tokio::spawn(async move {
+            let result = match manager.providers[provider_index].fetch_thread(&parent).await {
+                Ok(replies) => {
+                    if let Err(e) = cache.cache_thread(&parent, &replies).await {
+                        eprintln!("Warning: Failed to cache thread: {}", e);
                     }
+                    Ok(replies)
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            let _ = results.send((key, result));
+        });
+    }
+
+    fn process_thread_results(&mut self) {
+        while let Ok((key, result)) = self.thread_results.try_recv() {
+            match result {
+                Ok(replies) => {
+                    self.threads.insert(key, replies);
                 }
+                // The cached thread (if any) stays on screen
+                Err(e) => self.set_status(format!("Could not load discussion: {}", e)),
             }
         }
+    }
+
+    async fn apply_refresh(&mut self, mut messages_to_use: Vec<Message>) {
+        // Only alert on messages that weren't on screen before, and not on
+        // the very first load where everything would count as new
+        if !self.messages.is_empty() {
+            let known: HashSet<(MessageSource, u64)> = self.messages.iter().map(|m| (m.source, m.id)).collect();
+            self.alert_on(messages_to_use.iter().filter(|m| !known.contains(&(m.source, m.id))));
+        }
         
+        // Fetched copies don't know which attachments were downloaded
+        let downloaded: HashMap<(MessageSource, u64, String), String> = self.messages
+            .iter()
+            .flat_map(|m| m.attachments.iter().filter_map(move |a| Some(((m.source, m.id, a.filename.clone()), a.local_path.clone()?))))
+            .collect();
+        carry_send_status(&mut messages_to_use, &self.messages);
         self.messages = messages_to_use;
-        
-        if self.messages.is_empty() {
-            self.selected_message = None;
-        } else if self.selected_message.is_none() {
-            self.selected_message = Some(0);
-        } else if let Some(selected) = self.selected_message {
-            if selected >= self.messages.len() {
-                self.selected_message = Some(self.messages.len() - 1);
+        for message in &mut self.messages {
+            for attachment in &mut message.attachments {
+                if attachment.local_path.is_none() {
+                    attachment.local_path = downloaded.get(&(message.source, message.id, attachment.filename.clone())).cloned();
+                }
             }
         }
+        if self.bookmarks_only {
+            self.include_bookmarked();
+        }
+        self.queued_sends = self.cache.count_pending_sends().await.unwrap_or(0);
+        self.show_queued_sends().await;
+        self.update_unread_counts().await;
+        
+        if let Some(selected) = self.selected_message
+            && selected >= self.messages.len()
+        {
+            self.selected_message = self.messages.len().checked_sub(1);
+        }
+        self.ensure_selection_visible();
         
         self.last_refresh = Instant::now();
+        self.last_refresh_at = Some(Utc::now());
         self.is_refreshing = false;
-        Ok(())
+    }
+
+    /// Adds a placeholder for each send still waiting in the queue, so they
+    /// stay visible across refreshes until delivered
+    async fn show_queued_sends(&mut self) {
+        let queued = self.cache.get_pending_sends().await.unwrap_or_default();
+
+        for send in queued {
+            let Some(provider) = self.integration_manager.find_provider_by_key(&send.provider_key) else {
+                continue;
+            };
+            let status = if send.attempts > 0 {
+                format!("Queued (retried {}×): {}", send.attempts, send.content)
+            } else {
+                format!("Queued: {}", send.content)
+            };
+            self.messages.insert(0, Message {
+                id: u64::MAX - send.id as u64,
+                source: provider.source(),
+                content: status,
+                timestamp: Utc::now(),
+                author: "You".to_string(),
+                attachments: vec![],
+                reactions: vec![],
+                channel_id: send.channel_id,
+                reply_to_id: None,
+                edited: false,
+                send_status: Some(SendStatus::Pending),
+            });
+        }
     }
     
+    #[allow(dead_code)]
     async fn load_cached_messages(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Quick load from cache - this should be near-instant
         let cached_messages = self.cache.get_cached_messages(Some(self.message_limit)).await?;
@@ -237,8 +1101,151 @@ impl App {
         Ok(())
     }
     
+    /// Drains events pushed by live connections without blocking
+    async fn process_live_events(&mut self) {
+        while let Ok(event) = self.live_events.try_recv() {
+            match event {
+                LiveEvent::Message(message) => self.add_live_message(message).await,
+                LiveEvent::Connected(source, provider_key) => {
+                    if let Some(provider_key) = provider_key {
+                        self.connection_failures.remove(&provider_key);
+                    }
+                    if !self.live_sources.contains(&source) {
+                        self.live_sources.push(source);
+                    }
+                }
+                LiveEvent::Disconnected(source, _reason) => {
+                    // Polling carries on as before until the connection is back
+                    self.live_sources.retain(|&s| s != source);
+                }
+                LiveEvent::Failed(source, provider_key, reason) => {
+                    self.live_sources.retain(|&s| s != source);
+                    let name = self.integration_manager
+                        .find_provider_by_key(&provider_key)
+                        .map_or_else(|| format!("{:?}", source), |provider| provider.display_name());
+                    self.set_status(format!("{} connection lost: {}", name, reason));
+                    self.connection_failures.insert(provider_key, reason);
+                }
+            }
+        }
+    }
+
+    async fn add_live_message(&mut self, message: Message) {
+        if self.integration_manager.is_ignored(&message)
+            || self.messages.iter().any(|m| m.source == message.source && m.id == message.id)
+        {
+            return;
+        }
+
+        if let Err(e) = self.cache.cache_messages(std::slice::from_ref(&message)).await {
+            eprintln!("Warning: Failed to cache live message: {}", e);
+        }
+        self.alert_on(std::iter::once(&message));
+
+        // Keep newest-first order and keep the same message selected
+        let position = self.messages
+            .iter()
+            .position(|m| m.timestamp < message.timestamp)
+            .unwrap_or(self.messages.len());
+        self.messages.insert(position, message);
+        if let Some(selected) = self.selected_message
+            && selected >= position
+        {
+            self.selected_message = Some(selected + 1);
+        }
+
+        self.messages.truncate(self.message_limit);
+        if let Some(selected) = self.selected_message
+            && selected >= self.messages.len()
+        {
+            self.selected_message = self.messages.len().checked_sub(1);
+        }
+        self.ensure_selection_visible();
+    }
+
+    fn matches_alert(&self, message: &Message) -> bool {
+        matches_terms(&self.alerts.keywords, message)
+    }
+
+    /// Rings the bell if any of `messages` hits an alert keyword, or while
+    /// silenced only a `bypass_dnd` term. Terminals typically surface the
+    /// bell as a desktop notification or urgency hint.
+    fn alert_on<'a>(&self, mut messages: impl Iterator<Item = &'a Message>) {
+        let terms = if self.is_silenced() { &self.alerts.bypass_dnd } else { &self.alerts.keywords };
+        if self.alerts.bell && messages.any(|m| matches_terms(terms, m)) {
+            use std::io::Write;
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+        }
+    }
+
+    fn is_silenced(&self) -> bool {
+        self.dnd || self.snoozed_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    fn toggle_dnd(&mut self) {
+        self.dnd = !self.dnd;
+        self.snoozed_until = None;
+    }
+
+    /// Silences alerts for the configured snooze, or ends a running snooze
+    fn toggle_snooze(&mut self) {
+        self.snoozed_until = match self.snoozed_until {
+            Some(until) if Instant::now() < until => None,
+            _ => Some(Instant::now() + self.alerts.snooze),
+        };
+    }
+
     fn should_refresh(&self) -> bool {
-        !self.is_refreshing && self.last_refresh.elapsed() >= Duration::from_secs(30) // Refresh every 30 seconds
+        self.auto_refresh && !self.is_refreshing && self.last_refresh.elapsed() >= self.current_refresh_interval()
+    }
+
+    /// The auto-refresh interval, stretched while offline
+    fn current_refresh_interval(&self) -> Duration {
+        if self.online {
+            self.refresh_interval
+        } else {
+            self.refresh_interval.max(OFFLINE_RETRY_INTERVAL)
+        }
+    }
+
+    /// Notes whether the last refresh or send got through to a service.
+    /// Several refreshes in a row that reach nothing mean we're offline;
+    /// anything getting through means we're back.
+    fn record_reachability(&mut self, reached: bool) {
+        if reached {
+            self.unreachable_refreshes = 0;
+            if !self.online {
+                self.online = true;
+                self.set_status("Back online".to_string());
+            }
+        } else {
+            self.unreachable_refreshes += 1;
+            if self.unreachable_refreshes >= OFFLINE_AFTER_FAILURES {
+                self.online = false;
+            }
+        }
+    }
+
+    /// Picks up changes found by the optional connectivity probe. The probe
+    /// succeeding while offline makes a retry due at once; whether we're
+    /// back is up to that refresh.
+    fn process_connectivity(&mut self) {
+        while let Ok(reachable) = self.connectivity.try_recv() {
+            if reachable && !self.online {
+                self.last_refresh = Instant::now() - self.current_refresh_interval();
+            } else if !reachable {
+                self.online = false;
+            }
+        }
+    }
+
+    fn toggle_auto_refresh(&mut self) {
+        self.auto_refresh = !self.auto_refresh;
+        // Start a fresh interval when resuming so we don't refresh immediately
+        if self.auto_refresh {
+            self.last_refresh = Instant::now();
+        }
     }
 
     async fn delete_selected_message(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -253,15 +1260,10 @@ impl App {
         };
 
         // Find the appropriate provider for this message
-        let provider = self.integration_manager.providers
-            .iter()
-            .find(|p| p.source() == message.source && 
-                     (message.channel_id.is_none() || 
-                      p.channel_id() == message.channel_id || 
-                      (message.source == MessageSource::Telegram && p.channel_id().is_none())));
+        let provider = self.integration_manager.find_provider(message.source, message.channel_id.as_deref());
 
         if let Some(provider) = provider {
-            match provider.delete_message(message.id).await {
+            match provider.delete_message(message.channel_id.as_deref(), message.id).await {
                 Ok(()) => {
                     // Remove the message from local list
                     self.messages.remove(selected_index);
@@ -281,13 +1283,17 @@ impl App {
                 Err(e) => {
                     // Add a local error message if deletion failed
                     let error_message = Message {
-                        id: (self.messages.len() + 1) as u64,
+                        id: self.next_local_id(),
                         source: message.source,
                         content: format!("❌ Failed to delete message: {}", e),
                         timestamp: Utc::now(),
                         author: "System".to_string(),
                         attachments: vec![],
+                        reactions: vec![],
                         channel_id: None,
+                        reply_to_id: None,
+                        edited: false,
+                        send_status: None,
                     };
                     self.messages.insert(0, error_message);
                     self.selected_message = Some(0);
@@ -296,157 +1302,2525 @@ impl App {
         } else {
             // No matching provider available
             let error_message = Message {
-                id: (self.messages.len() + 1) as u64,
+                id: self.next_local_id(),
                 source: message.source,
                 content: format!("❌ No provider available to delete {:?} message", message.source),
                 timestamp: Utc::now(),
                 author: "System".to_string(),
                 attachments: vec![],
+                reactions: vec![],
                 channel_id: None,
+                reply_to_id: None,
+                edited: false,
+                send_status: None,
             };
             self.messages.insert(0, error_message);
             self.selected_message = Some(0);
         }
         
+        self.ensure_selection_visible();
         Ok(())
     }
 
-    fn select_next(&mut self) {
-        if let Some(selected) = self.selected_message {
-            if selected < self.messages.len() - 1 {
-                self.selected_message = Some(selected + 1);
-            }
+    fn is_visible(&self, message: &Message) -> bool {
+        self.source_filter.is_none_or(|source| message.source == source)
+            && (!self.bookmarks_only || self.is_bookmarked(message))
+            && self.search.as_ref().is_none_or(|query| query.matches(message))
+            && !self.is_muted(message)
+    }
+
+    fn is_unread(&self, message: &Message) -> bool {
+        !self.read.contains(&(message.source, message.id))
+    }
+
+    async fn mark_selected_read(&mut self) {
+        let Some(key) = self.get_selected_message().map(|m| (m.source, m.id)) else {
+            return;
+        };
+        if self.read.contains(&key) {
+            return;
         }
+        self.read.insert(key);
+        if let Err(e) = self.cache.mark_read(&[key]).await {
+            eprintln!("Warning: Failed to mark message read: {}", e);
+        }
+        self.update_unread_counts().await;
     }
 
-    fn select_previous(&mut self) {
-        if let Some(selected) = self.selected_message {
-            if selected > 0 {
-                self.selected_message = Some(selected - 1);
-            }
+    async fn update_unread_counts(&mut self) {
+        match self.cache.unread_counts().await {
+            Ok(counts) => self.unread_counts = counts,
+            Err(e) => eprintln!("Warning: Failed to count unread messages: {}", e),
         }
     }
 
-    fn get_selected_message(&self) -> Option<&Message> {
-        self.selected_message.and_then(|i| self.messages.get(i))
+    /// Marks everything the list currently shows as read
+    async fn mark_visible_read(&mut self) {
+        let keys: Vec<(MessageSource, u64)> = self
+            .visible_indices()
+            .into_iter()
+            .map(|i| (self.messages[i].source, self.messages[i].id))
+            .filter(|key| !self.read.contains(key))
+            .collect();
+        match self.cache.mark_read(&keys).await {
+            Ok(()) => self.read.extend(keys),
+            Err(e) => self.set_status(format!("Error marking messages read: {}", e)),
+        }
+        self.update_unread_counts().await;
     }
-    
-    fn send_message_non_blocking(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if self.input_text.is_empty() {
+
+    /// Marks every cached message of the filtered source (or all) as read
+    async fn mark_all_read(&mut self) {
+        if let Err(e) = self.cache.mark_all_read(self.source_filter).await {
+            self.set_status(format!("Error marking messages read: {}", e));
+            return;
+        }
+        let source_filter = self.source_filter;
+        let keys: Vec<_> = self.messages
+            .iter()
+            .filter(|m| source_filter.is_none_or(|source| m.source == source))
+            .map(|m| (m.source, m.id))
+            .collect();
+        self.read.extend(keys);
+        self.update_unread_counts().await;
+    }
+
+    fn is_bookmarked(&self, message: &Message) -> bool {
+        self.bookmarks.contains_key(&(message.source, message.id))
+    }
+
+    async fn toggle_bookmark(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(message) = self.get_selected_message().cloned() else {
             return Ok(());
+        };
+
+        let key = (message.source, message.id);
+        if self.bookmarks.contains_key(&key) {
+            self.cache.remove_bookmark(message.source, message.id).await?;
+            self.bookmarks.remove(&key);
+            self.ensure_selection_visible();
+        } else {
+            self.cache.add_bookmark(&message).await?;
+            self.bookmarks.insert(key, message);
         }
-        
-        let message_content = self.input_text.clone();
-        self.input_text.clear();
+        Ok(())
+    }
+
+    fn toggle_bookmarks_only(&mut self) {
+        self.bookmarks_only = !self.bookmarks_only;
+        if self.bookmarks_only {
+            self.include_bookmarked();
+        }
+        self.ensure_selection_visible();
+    }
+
+    /// Adds bookmarked messages that are no longer in the feed (e.g. pruned
+    /// from the cache)
+    fn include_bookmarked(&mut self) {
+        let bookmarked = self.bookmarks.values().cloned().collect();
+        self.include_messages(bookmarked);
+    }
+
+    /// Adds whichever of `messages` aren't in the feed yet, keeping the same
+    /// message selected
+    fn include_messages(&mut self, messages: Vec<Message>) {
+        let selected = self.get_selected_message().map(|m| (m.source, m.id));
+        let present: HashSet<(MessageSource, u64)> = self.messages.iter().map(|m| (m.source, m.id)).collect();
+        let missing: Vec<Message> = messages
+            .into_iter()
+            .filter(|m| !present.contains(&(m.source, m.id)))
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+
+        self.messages.extend(missing);
+        self.messages.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+        if let Some(selected) = selected {
+            self.selected_message = self.messages.iter().position(|m| (m.source, m.id) == selected);
+        }
+    }
+
+    /// Re-parses the search box, filtering as the user types. An invalid
+    /// query keeps the previous filter and shows why instead.
+    fn update_search(&mut self) {
+        match search::parse_query(&self.search_text) {
+            Ok(query) => {
+                self.search = (!query.is_empty()).then_some(query);
+                self.search_error = None;
+                self.ensure_selection_visible();
+            }
+            Err(e) => self.search_error = Some(e),
+        }
+    }
+
+    /// Stops editing and pulls in cached matches older than the loaded feed
+    async fn apply_search(&mut self) {
+        self.search_editing = false;
+        let Some(query) = &self.search else {
+            return;
+        };
+        match self.cache.search_messages(query, self.message_limit).await {
+            Ok(found) => self.include_messages(found),
+            Err(e) => self.set_status(format!("Search failed: {}", e)),
+        }
+        self.ensure_selection_visible();
+    }
+
+    fn clear_search(&mut self) {
+        self.search_editing = false;
+        self.search_text.clear();
+        self.search = None;
+        self.search_error = None;
+        self.ensure_selection_visible();
+    }
+
+    fn is_muted(&self, message: &Message) -> bool {
+        self.muted_authors.contains(&message.author.to_lowercase())
+            || message
+                .channel_id
+                .as_ref()
+                .is_some_and(|channel| self.muted_channels.contains(&(message.source, channel.clone())))
+    }
+
+    async fn mute_selected_channel(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some((source, channel_id)) = self
+            .get_selected_message()
+            .and_then(|msg| Some((msg.source, msg.channel_id.clone()?)))
+        else {
+            return Ok(());
+        };
+
+        self.cache.mute_channel(source, &channel_id).await?;
+        self.muted_channels.insert((source, channel_id));
+        self.ensure_selection_visible();
+        Ok(())
+    }
+
+    async fn mute_selected_author(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(author) = self.get_selected_message().map(|msg| msg.author.to_lowercase()) else {
+            return Ok(());
+        };
+
+        self.cache.mute_author(&author).await?;
+        self.muted_authors.insert(author);
+        self.ensure_selection_visible();
+        Ok(())
+    }
+
+    /// Indices into `messages` of the rows currently shown in the list
+    fn visible_indices(&self) -> Vec<usize> {
+        self.messages
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| self.is_visible(msg))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The visible messages with a header row before each new day
+    fn list_rows(&self) -> Vec<ListRow> {
+        let mut rows = Vec::new();
+        let mut current_day = None;
+
+        for index in self.visible_indices() {
+            let day = self.local_date(self.messages[index].timestamp);
+            if current_day != Some(day) {
+                rows.push(ListRow::DateHeader(self.day_label(day)));
+                current_day = Some(day);
+            }
+            rows.push(ListRow::Message(index));
+        }
+
+        rows
+    }
+
+    /// Moves the selection onto a visible message if the current one is
+    /// hidden (or gone), preferring the nearest one below it.
+    fn ensure_selection_visible(&mut self) {
+        if self.get_selected_message().is_some_and(|msg| self.is_visible(msg)) {
+            return;
+        }
+        let visible = self.visible_indices();
+        let current = self.selected_message.unwrap_or(0);
+        self.selected_message = visible
+            .iter()
+            .find(|&&i| i >= current)
+            .or(visible.last())
+            .copied();
+    }
+
+    fn select_next(&mut self) {
+        let visible = self.visible_indices();
+        if let Some(selected) = self.selected_message
+            && let Some(&next) = visible.iter().find(|&&i| i > selected)
+        {
+            self.selected_message = Some(next);
+        }
+    }
+
+    /// Moving down from the last message loads the next page of older
+    /// cached messages, and the feed keeps that many from then on
+    async fn load_older_messages(&mut self) {
+        if self.messages.len() < self.message_limit || self.selected_message != self.visible_indices().last().copied() {
+            return;
+        }
+        let total = match self.cache.count_messages(None).await {
+            Ok(total) => total,
+            Err(e) => {
+                self.set_status(format!("Could not count cached messages: {}", e));
+                return;
+            }
+        };
+        if total <= self.messages.len() {
+            return;
+        }
+
+        match self.cache.get_cached_messages_paged(SCROLL_BACK_PAGE, self.messages.len()).await {
+            Ok(older) => {
+                let known: HashSet<(MessageSource, u64)> = self.messages.iter().map(|m| (m.source, m.id)).collect();
+                self.messages.extend(older.into_iter().filter(|m| !known.contains(&(m.source, m.id))));
+                self.message_limit = self.message_limit.max(self.messages.len());
+                self.set_status(format!("Showing {} of {} cached messages", self.messages.len(), total));
+            }
+            Err(e) => self.set_status(format!("Could not load older messages: {}", e)),
+        }
+    }
+
+    fn select_previous(&mut self) {
+        let visible = self.visible_indices();
+        if let Some(selected) = self.selected_message
+            && let Some(&previous) = visible.iter().rev().find(|&&i| i < selected)
+        {
+            self.selected_message = Some(previous);
+        }
+    }
+
+    fn select_first(&mut self) {
+        if let Some(&first) = self.visible_indices().first() {
+            self.selected_message = Some(first);
+        }
+    }
+
+    fn select_last(&mut self) {
+        if let Some(&last) = self.visible_indices().last() {
+            self.selected_message = Some(last);
+        }
+    }
+
+    /// Rows the list showed when last drawn, inside its border
+    fn page_size(&self) -> usize {
+        let lines = self.list_area.get().height.saturating_sub(2) as usize;
+        (if self.wrap_list { lines / 2 } else { lines }).max(1)
+    }
+
+    fn page_down(&mut self) {
+        let visible = self.visible_indices();
+        let position = self.selected_message.and_then(|s| visible.iter().position(|&i| i == s)).unwrap_or(0);
+        if let Some(&target) = visible.get(position + self.page_size()).or(visible.last()) {
+            self.selected_message = Some(target);
+        }
+    }
+
+    fn page_up(&mut self) {
+        let visible = self.visible_indices();
+        let position = self.selected_message.and_then(|s| visible.iter().position(|&i| i == s)).unwrap_or(0);
+        if let Some(&target) = visible.get(position.saturating_sub(self.page_size())) {
+            self.selected_message = Some(target);
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.select_next(),
+            MouseEventKind::ScrollUp => self.select_previous(),
+            MouseEventKind::Down(MouseButton::Left) if self.on_divider(mouse.column, mouse.row) => {
+                self.resizing = true;
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.resizing => {
+                self.resize_panes(mouse.column, mouse.row);
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.resizing = false,
+            MouseEventKind::Down(MouseButton::Left) => {
+                let area = self.list_area.get();
+                // Ignore clicks outside the list or on its border
+                if mouse.column <= area.x
+                    || mouse.column + 1 >= area.x + area.width
+                    || mouse.row <= area.y
+                    || mouse.row + 1 >= area.y + area.height
+                {
+                    return;
+                }
+
+                let mut line = (mouse.row - area.y - 1) as usize;
+                let mut row = self.list_offset.get();
+                let heights = self.list_heights.borrow();
+                while let Some(&height) = heights.get(row)
+                    && line >= height
+                {
+                    line -= height;
+                    row += 1;
+                }
+                drop(heights);
+                // Clicks on date headers select nothing
+                if let Some(&ListRow::Message(index)) = self.list_rows().get(row) {
+                    self.selected_message = Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a position is on the list's border facing the content pane
+    /// or the content pane's border facing the list
+    fn on_divider(&self, column: u16, row: u16) -> bool {
+        let list = self.list_area.get();
+        if self.layout.side_by_side {
+            let edge = list.x + list.width;
+            (edge.saturating_sub(1)..=edge).contains(&column) && (list.y..list.y + list.height).contains(&row)
+        } else {
+            let edge = list.y + list.height;
+            (edge.saturating_sub(1)..=edge).contains(&row) && (list.x..list.x + list.width).contains(&column)
+        }
+    }
+
+    /// Moves the divider to the dragged position, leaving each pane enough
+    /// room for its borders and a few lines (the content pane also holds
+    /// the input box)
+    fn resize_panes(&mut self, column: u16, row: u16) {
+        let area = self.panes_area.get();
+        let (start, length, position, min_list, min_content) = if self.layout.side_by_side {
+            (area.x, area.width, column, 12, 20)
+        } else {
+            (area.y, area.height, row, 3, 6)
+        };
+        if length < min_list + min_content {
+            return;
+        }
+
+        let split = (position + 1).saturating_sub(start).clamp(min_list, length - min_content);
+        let percent = (split as u32 * 100 / length as u32) as u16;
+        self.layout.list_percent = percent.clamp(10, 90);
+    }
+
+    /// Writes every cached message of the selected message's channel to a
+    /// Markdown file in the download directory
+    async fn export_selected_channel(&self) -> Result<(usize, std::path::PathBuf), Box<dyn std::error::Error + Send + Sync>> {
+        let msg = self.get_selected_message().ok_or("No message selected")?;
+        let channel_id = msg.channel_id.as_deref().ok_or("This message isn't part of a channel")?;
+
+        let mut messages = self.cache.get_messages_by_channel(msg.source, channel_id, None).await?;
+        messages.reverse();
+        let title = format!("{:?} {}", msg.source, channel_id);
+        let contents = export::render(&messages, &title, export::Format::Markdown);
+
+        std::fs::create_dir_all(&self.download_dir)?;
+        let filename = safe_file_name(&format!("{:?}-{}-{}", msg.source, channel_id, Utc::now().format("%Y%m%d-%H%M%S")));
+        let path = self.download_dir.join(format!("{}.{}", filename, export::Format::Markdown.extension()));
+        std::fs::write(&path, contents)?;
+        Ok((messages.len(), path))
+    }
+
+    /// Drops the cached messages of the filtered source, or of the selected
+    /// message's source when no filter is active
+    async fn clear_current_source(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(source) = self.source_filter.or_else(|| self.get_selected_message().map(|m| m.source)) else {
+            return Ok(());
+        };
+
+        self.cache.clear_source(source).await?;
+        self.messages.retain(|m| m.source != source);
+        self.unread_counts.remove(&source);
+        self.selected_message = if self.messages.is_empty() { None } else { Some(0) };
+        self.ensure_selection_visible();
+        Ok(())
+    }
+
+    fn cycle_source_filter(&mut self) {
+        self.source_filter = match self.source_filter {
+            None => Some(MessageSource::Telegram),
+            Some(MessageSource::Telegram) => Some(MessageSource::Discord),
+            Some(MessageSource::Discord) => Some(MessageSource::Github),
+            Some(MessageSource::Github) => Some(MessageSource::Jira),
+            Some(MessageSource::Jira) => Some(MessageSource::Reddit),
+            Some(MessageSource::Reddit) => Some(MessageSource::Xmpp),
+            Some(MessageSource::Xmpp) => Some(MessageSource::Teams),
+            Some(MessageSource::Teams) => Some(MessageSource::Sms),
+            Some(MessageSource::Sms) => Some(MessageSource::Rest),
+            Some(MessageSource::Rest) => None,
+        };
+        self.ensure_selection_visible();
+    }
+
+    fn format_timestamp(&self, timestamp: DateTime<Utc>, format: &str) -> String {
+        if self.time.use_local_time {
+            timestamp.with_timezone(&Local).format(format).to_string()
+        } else {
+            timestamp.format(format).to_string()
+        }
+    }
+
+    /// The calendar day of `timestamp` in the display timezone
+    fn local_date(&self, timestamp: DateTime<Utc>) -> NaiveDate {
+        if self.time.use_local_time {
+            timestamp.with_timezone(&Local).date_naive()
+        } else {
+            timestamp.date_naive()
+        }
+    }
+
+    fn day_label(&self, day: NaiveDate) -> String {
+        let today = self.local_date(Utc::now());
+        if day == today {
+            "Today".to_string()
+        } else if today.pred_opt() == Some(day) {
+            "Yesterday".to_string()
+        } else {
+            day.format(&self.time.date_format).to_string()
+        }
+    }
+
+    fn format_time(&self, timestamp: DateTime<Utc>) -> String {
+        self.format_timestamp(timestamp, &self.time.time_format)
+    }
+
+    fn format_date_time(&self, timestamp: DateTime<Utc>) -> String {
+        let format = format!("{} {}", self.time.date_format, self.time.time_format);
+        let formatted = self.format_timestamp(timestamp, &format);
+        if self.time.use_local_time {
+            formatted
+        } else {
+            format!("{} UTC", formatted)
+        }
+    }
+
+    /// Index of the message `message` replies to, if it's loaded
+    fn find_parent(&self, message: &Message) -> Option<usize> {
+        let parent_id = message.reply_to_id?;
+        // Telegram ids are only unique within a chat, so match the channel too
+        self.messages.iter().position(|m| {
+            m.source == message.source && m.id == parent_id && m.channel_id == message.channel_id
+        })
+    }
+
+    fn jump_to_parent(&mut self) {
+        if let Some(parent) = self.get_selected_message().and_then(|msg| self.find_parent(msg)) {
+            self.selected_message = Some(parent);
+            self.ensure_selection_visible();
+        }
+    }
+
+    fn set_status(&mut self, message: String) {
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    /// Copies the selected message's text, or with `url_only` the first link
+    /// in it (falling back to its first attachment)
+    fn copy_selected(&mut self, url_only: bool) {
+        let Some(message) = self.get_selected_message() else {
+            return;
+        };
+
+        let text = if url_only {
+            let url = message
+                .content
+                .split_whitespace()
+                .find(|word| word.starts_with("http://") || word.starts_with("https://"))
+                .map(|word| word.trim_end_matches([')', ']', '>', ',', '.']).to_string())
+                .or_else(|| message.attachments.first().map(|a| a.url.clone()));
+            match url {
+                Some(url) => url,
+                None => {
+                    self.set_status("No URL in this message".to_string());
+                    return;
+                }
+            }
+        } else {
+            message.content.clone()
+        };
+
+        if self.clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(e) => {
+                    self.set_status(format!("Clipboard unavailable: {}", e));
+                    return;
+                }
+            }
+        }
+
+        let result = self.clipboard.as_mut().map(|clipboard| clipboard.set_text(text));
+        match result {
+            Some(Ok(())) if url_only => self.set_status("Copied URL".to_string()),
+            Some(Ok(())) => self.set_status("Copied message".to_string()),
+            Some(Err(e)) => self.set_status(format!("Copy failed: {}", e)),
+            None => {}
+        }
+    }
+
+    /// Downloads one attachment of a message, reusing an earlier download
+    /// while the file is still on disk. Returns where the file is.
+    async fn download_attachment(&mut self, message_index: usize, attachment_index: usize) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let message = &self.messages[message_index];
+        let attachment = &message.attachments[attachment_index];
+
+        let known_path = match &attachment.local_path {
+            Some(path) => Some(path.clone()),
+            None => self.cache.get_attachment_local_path(message.id, &attachment.filename).await?,
+        };
+        if let Some(path) = known_path.filter(|path| std::path::Path::new(path).exists()) {
+            self.messages[message_index].attachments[attachment_index].local_path = Some(path.clone());
+            return Ok(path);
+        }
+
+        let provider = self.integration_manager
+            .find_provider(message.source, message.channel_id.as_deref())
+            .ok_or("No provider for this message")?;
+
+        std::fs::create_dir_all(&self.download_dir)?;
+        let path = self.download_dir
+            .join(attachment_file_name(message.id, &attachment.filename))
+            .to_string_lossy()
+            .to_string();
+
+        provider.download_attachment(attachment, &path).await?;
+        self.cache.set_attachment_local_path(message.id, &attachment.filename, &path).await?;
+        self.messages[message_index].attachments[attachment_index].local_path = Some(path.clone());
+        Ok(path)
+    }
+
+    async fn download_selected_attachments(&mut self) {
+        let Some(message_index) = self.selected_message.filter(|&i| i < self.messages.len()) else {
+            return;
+        };
+        let count = self.messages[message_index].attachments.len();
+        if count == 0 {
+            self.set_status("No attachments on this message".to_string());
+            return;
+        }
+
+        let mut saved = Vec::new();
+        for attachment_index in 0..count {
+            match self.download_attachment(message_index, attachment_index).await {
+                Ok(path) => saved.push(path),
+                Err(e) => {
+                    self.set_status(format!("Download failed: {}", e));
+                    return;
+                }
+            }
+        }
+
+        match saved.as_slice() {
+            [path] => self.set_status(format!("Saved to {}", path)),
+            _ => self.set_status(format!("Saved {} attachments to {}", saved.len(), self.download_dir.display())),
+        }
+    }
+
+    /// Saves every attachment of the messages in view, in the background,
+    /// into a folder per source and channel under the download directory.
+    /// Files already downloaded are skipped.
+    async fn download_visible_attachments(&mut self) {
+        if self.batch_download.is_some() {
+            self.set_status("Already saving attachments".to_string());
+            return;
+        }
+
+        let mut jobs = Vec::new();
+        let mut skipped = 0;
+        for index in self.visible_indices() {
+            let message = &self.messages[index];
+            if message.attachments.is_empty() {
+                continue;
+            }
+            let provider_index = self.integration_manager.find_provider_index(message.source, message.channel_id.as_deref());
+            let folder = self.download_dir
+                .join(format!("{:?}", message.source))
+                .join(safe_file_name(message.channel_id.as_deref().unwrap_or("direct")));
+            for attachment in &message.attachments {
+                if attachment.local_path.as_deref().is_some_and(|path| std::path::Path::new(path).exists()) {
+                    skipped += 1;
+                    continue;
+                }
+                let path = folder.join(attachment_file_name(message.id, &attachment.filename));
+                jobs.push(((message.source, message.id), provider_index, attachment.clone(), path));
+            }
+        }
+        if jobs.is_empty() {
+            self.set_status(match skipped {
+                0 => "No attachments in view".to_string(),
+                _ => format!("All {} attachments in view are already saved", skipped),
+            });
+            return;
+        }
+
+        self.batch_download = Some(BatchDownload { total: jobs.len() + skipped, saved: 0, skipped, failed: 0, last_error: None });
+        let manager = Arc::clone(&self.integration_manager);
+        let cache = self.cache.clone();
+        let results = self.batch_sender.clone();
+        // One at a time, to stay clear of the services' rate limits
+        tokio::spawn(async move {
+            for (key, provider_index, attachment, path) in jobs {
+                let result = async {
+                    let provider_index = provider_index.ok_or("no provider for this message")?;
+                    if let Some(folder) = path.parent() {
+                        std::fs::create_dir_all(folder)?;
+                    }
+                    let path = path.to_string_lossy().to_string();
+                    // Saved by hand earlier, under the same name
+                    if !std::path::Path::new(&path).exists() {
+                        manager.providers[provider_index].download_attachment(&attachment, &path).await?;
+                    }
+                    cache.set_attachment_local_path(key.1, &attachment.filename, &path).await?;
+                    Ok::<_, Box<dyn std::error::Error + Send + Sync>>(path)
+                }
+                .await
+                .map_err(|e| e.to_string());
+                if results.send((key, attachment.filename, result)).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Records finished batch downloads, and sums up once all are done
+    fn process_batch_results(&mut self) {
+        while let Ok((key, filename, result)) = self.batch_results.try_recv() {
+            let Some(batch) = self.batch_download.as_mut() else {
+                continue;
+            };
+            match result {
+                Ok(path) => {
+                    batch.saved += 1;
+                    for message in self.messages.iter_mut().filter(|m| (m.source, m.id) == key) {
+                        for attachment in message.attachments.iter_mut().filter(|a| a.filename == filename) {
+                            attachment.local_path = Some(path.clone());
+                        }
+                    }
+                }
+                Err(e) => {
+                    batch.failed += 1;
+                    batch.last_error = Some(format!("{}: {}", filename, e));
+                }
+            }
+
+            if batch.saved + batch.skipped + batch.failed == batch.total {
+                let mut summary = format!("Saved {} attachments to {}", batch.saved, self.download_dir.display());
+                if batch.skipped > 0 {
+                    summary.push_str(&format!(", {} already saved", batch.skipped));
+                }
+                if let Some(error) = &batch.last_error {
+                    summary.push_str(&format!(", {} failed (last: {})", batch.failed, error));
+                }
+                self.batch_download = None;
+                self.set_status(summary);
+            }
+        }
+    }
+
+    /// Opens the selected message's attachment, asking which one first if
+    /// there are several
+    async fn open_selected_attachment(&mut self) {
+        let Some(message_index) = self.selected_message.filter(|&i| i < self.messages.len()) else {
+            return;
+        };
+        match self.messages[message_index].attachments.len() {
+            0 => self.set_status("No attachments on this message".to_string()),
+            1 => self.open_attachment(message_index, 0).await,
+            _ => {
+                let message = &self.messages[message_index];
+                let filenames = message.attachments.iter().map(|a| a.filename.clone()).collect();
+                self.popup = Some(Popup::Attachments { message: (message.source, message.id), filenames, selected: 0 });
+            }
+        }
+    }
+
+    /// Downloads the attachment if needed and hands it to the system's
+    /// default application
+    async fn open_attachment(&mut self, message_index: usize, attachment_index: usize) {
+        let path = match self.download_attachment(message_index, attachment_index).await {
+            Ok(path) => path,
+            Err(e) => {
+                self.set_status(format!("Can't open attachment: {}", e));
+                return;
+            }
+        };
+
+        let mut opener = if cfg!(target_os = "macos") {
+            std::process::Command::new("open")
+        } else if cfg!(windows) {
+            let mut command = std::process::Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        } else {
+            std::process::Command::new("xdg-open")
+        };
+        // Keep the viewer's chatter from scribbling over the TUI
+        let launched = opener
+            .arg(&path)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        match launched {
+            Ok(_) => self.set_status(format!("Opened {}", path)),
+            Err(e) => self.set_status(format!("Couldn't launch a viewer for {}: {}", path, e)),
+        }
+    }
+
+    fn get_selected_message(&self) -> Option<&Message> {
+        self.selected_message.and_then(|i| self.messages.get(i))
+    }
+    
+    /// The destination picked in the compose popup, or else the provider and
+    /// channel of the selected message
+    fn resolve_send_target(&self) -> Option<SendTarget> {
+        if let Some(target) = &self.compose_target {
+            return Some(target.clone());
+        }
+        
+        match self.get_selected_message() {
+            Some(msg) => self.integration_manager
+                .find_provider_index(msg.source, msg.channel_id.as_deref())
+                .map(|provider_index| SendTarget { provider_index, channel_id: msg.channel_id.clone() }),
+            None if !self.integration_manager.providers.is_empty() => {
+                Some(SendTarget { provider_index: 0, channel_id: None })
+            }
+            None => None,
+        }
+    }
+    
+    fn open_compose_picker(&mut self) {
+        if !self.integration_manager.providers.is_empty() {
+            self.popup = Some(Popup::ProviderPicker { selected: 0 });
+        }
+    }
+    
+    /// Loads the workflow transitions of the selected issue into a popup
+    async fn open_transition_picker(&mut self) {
+        let Some(msg) = self.get_selected_message() else {
+            return;
+        };
+        let Some(channel_id) = msg.channel_id.clone() else {
+            self.set_status("No workflow transitions for this message".to_string());
+            return;
+        };
+        let Some(provider_index) = self.integration_manager.find_provider_index(msg.source, Some(&channel_id)) else {
+            return;
+        };
+
+        let provider = &self.integration_manager.providers[provider_index];
+        match provider.list_transitions(&channel_id).await {
+            Ok(transitions) if transitions.is_empty() => {
+                self.set_status(format!("No transitions available for {}", jira::issue_key(&channel_id)));
+            }
+            Ok(transitions) => {
+                self.popup = Some(Popup::TransitionPicker { provider_index, channel_id, transitions, selected: 0 });
+            }
+            Err(e) => self.set_status(format!("Could not load transitions: {}", e)),
+        }
+    }
+
+    async fn apply_transition(&mut self, provider_index: usize, channel_id: String, transition: Transition) {
+        let provider = &self.integration_manager.providers[provider_index];
+        match provider.apply_transition(&channel_id, &transition.id).await {
+            Ok(()) => {
+                self.set_status(format!("{} moved to {}", jira::issue_key(&channel_id), transition.to_status));
+                // The issue's `updated` time moved, so the next sync re-fetches it
+                self.request_refresh();
+            }
+            Err(e) => self.set_status(format!("Transition failed: {}", e)),
+        }
+    }
+
+    /// Loads the people the selected issue can be assigned to into a popup
+    async fn open_assignee_picker(&mut self) {
+        let Some(msg) = self.get_selected_message() else {
+            return;
+        };
+        let Some(channel_id) = msg.channel_id.clone() else {
+            self.set_status("This message can't be assigned".to_string());
+            return;
+        };
+        let Some(provider_index) = self.integration_manager.find_provider_index(msg.source, Some(&channel_id)) else {
+            return;
+        };
+
+        let provider = &self.integration_manager.providers[provider_index];
+        match provider.list_assignees(&channel_id).await {
+            Ok(people) => {
+                let assignees = std::iter::once(None).chain(people.into_iter().map(Some)).collect();
+                self.popup = Some(Popup::AssigneePicker { provider_index, channel_id, assignees, selected: 0 });
+            }
+            Err(e) => self.set_status(format!("Could not load assignees: {}", e)),
+        }
+    }
+
+    async fn assign(&mut self, provider_index: usize, channel_id: String, assignee: Option<Assignee>) {
+        let provider = &self.integration_manager.providers[provider_index];
+        match provider.assign(&channel_id, assignee.as_ref().map(|a| a.id.as_str())).await {
+            Ok(()) => {
+                match assignee {
+                    Some(assignee) => self.set_status(format!("{} assigned to {}", jira::issue_key(&channel_id), assignee.name)),
+                    None => self.set_status(format!("{} unassigned", jira::issue_key(&channel_id))),
+                }
+                self.request_refresh();
+            }
+            Err(e) => self.set_status(format!("Assigning failed: {}", e)),
+        }
+    }
+
+    fn open_palette(&mut self) {
+        let entries = palette::entries(&self.integration_manager);
+        self.popup = Some(Popup::Palette { entries, query: String::new(), selected: 0 });
+    }
+
+    /// Selects the newest visible message a provider is responsible for
+    fn jump_to_provider(&mut self, provider_index: usize) {
+        let target = self.visible_indices().into_iter().find(|&i| {
+            let msg = &self.messages[i];
+            self.integration_manager.find_provider_index(msg.source, msg.channel_id.as_deref()) == Some(provider_index)
+        });
+        match target {
+            Some(index) => self.selected_message = Some(index),
+            None => {
+                let name = self.integration_manager.providers[provider_index].display_name();
+                self.set_status(format!("No messages from {} are shown", name));
+            }
+        }
+    }
+
+    async fn run_command(&mut self, command: palette::Command) {
+        use palette::Command;
+        match command {
+            Command::Refresh => self.request_refresh(),
+            Command::MarkVisibleRead => self.mark_visible_read().await,
+            Command::MarkAllRead => self.mark_all_read().await,
+            Command::ToggleAutoRefresh => self.toggle_auto_refresh(),
+            Command::ToggleDnd => self.toggle_dnd(),
+            Command::ToggleSnooze => self.toggle_snooze(),
+            Command::Compose => self.open_compose_picker(),
+            Command::Edit => self.start_edit(),
+            Command::Reply => self.start_reply(),
+            Command::ShowSource(source) => {
+                self.source_filter = source;
+                self.ensure_selection_visible();
+            }
+            Command::ToggleBookmarksOnly => self.toggle_bookmarks_only(),
+            Command::Search => self.search_editing = true,
+            Command::JumpToChannel(provider_index) => self.jump_to_provider(provider_index),
+            Command::SaveAttachments => self.download_selected_attachments().await,
+            Command::SaveAllAttachments => self.download_visible_attachments().await,
+            Command::OpenAttachment => self.open_selected_attachment().await,
+            Command::CopyMessage => self.copy_selected(false),
+            Command::CopyUrl => self.copy_selected(true),
+            Command::ClearSource => {
+                if let Err(e) = self.clear_current_source().await {
+                    self.set_status(format!("Error clearing cached messages: {}", e));
+                }
+            }
+            Command::ExportChannel => match self.export_selected_channel().await {
+                Ok((count, path)) => self.set_status(format!("Exported {} messages to {}", count, path.display())),
+                Err(e) => self.set_status(format!("Export failed: {}", e)),
+            },
+            Command::Quit => self.should_quit = true,
+        }
+    }
+
+    async fn handle_popup_key(&mut self, code: KeyCode) {
+        let Some(popup) = self.popup.as_mut() else {
+            return;
+        };
+        
+        match popup {
+            Popup::ProviderPicker { selected } => match code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Down | KeyCode::Char('j') if *selected + 1 < self.integration_manager.providers.len() => {
+                    *selected += 1;
+                }
+                KeyCode::Up | KeyCode::Char('k') => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    let provider_index = *selected;
+                    let channel_id = self.integration_manager.providers[provider_index].channel_id();
+                    self.compose_target = Some(SendTarget { provider_index, channel_id });
+                    self.popup = None;
+                    self.start_input().await;
+                }
+                _ => {}
+            },
+            Popup::TransitionPicker { provider_index, channel_id, transitions, selected } => match code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Down | KeyCode::Char('j') if *selected + 1 < transitions.len() => *selected += 1,
+                KeyCode::Up | KeyCode::Char('k') => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    let (provider_index, channel_id) = (*provider_index, std::mem::take(channel_id));
+                    let transition = transitions.swap_remove(*selected);
+                    self.popup = None;
+                    self.apply_transition(provider_index, channel_id, transition).await;
+                }
+                _ => {}
+            },
+            Popup::Palette { entries, query, selected } => match code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Down if *selected + 1 < palette::filter(entries, query).len() => *selected += 1,
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Backspace => {
+                    query.pop();
+                    *selected = 0;
+                }
+                KeyCode::Char(c) => {
+                    query.push(c);
+                    *selected = 0;
+                }
+                KeyCode::Enter => {
+                    let command = palette::filter(entries, query)
+                        .get(*selected)
+                        .map(|&index| entries[index].command);
+                    self.popup = None;
+                    if let Some(command) = command {
+                        self.run_command(command).await;
+                    }
+                }
+                _ => {}
+            },
+            Popup::Emoji { query, selected } => match code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Down if *selected + 1 < emoji::search(query).len() => *selected += 1,
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Backspace => {
+                    query.pop();
+                    *selected = 0;
+                }
+                KeyCode::Char(c) => {
+                    query.push(c);
+                    *selected = 0;
+                }
+                KeyCode::Enter => {
+                    let emoji = emoji::search(query).get(*selected).map(|&(_, emoji)| emoji);
+                    self.popup = None;
+                    if let Some(emoji) = emoji {
+                        self.input_text.push_str(emoji);
+                        self.draft_changed = Some(Instant::now());
+                    }
+                }
+                _ => {}
+            },
+            Popup::Attachments { message, filenames, selected } => match code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Down | KeyCode::Char('j') if *selected + 1 < filenames.len() => *selected += 1,
+                KeyCode::Up | KeyCode::Char('k') => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    let (key, attachment_index) = (*message, *selected);
+                    self.popup = None;
+                    let message_index = self.messages.iter().position(|m| (m.source, m.id) == key);
+                    match message_index {
+                        Some(message_index) if attachment_index < self.messages[message_index].attachments.len() => {
+                            self.open_attachment(message_index, attachment_index).await;
+                        }
+                        _ => self.set_status("That message is no longer loaded".to_string()),
+                    }
+                }
+                _ => {}
+            },
+            Popup::AssigneePicker { provider_index, channel_id, assignees, selected } => match code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Down | KeyCode::Char('j') if *selected + 1 < assignees.len() => *selected += 1,
+                KeyCode::Up | KeyCode::Char('k') => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    let (provider_index, channel_id) = (*provider_index, std::mem::take(channel_id));
+                    let assignee = assignees.swap_remove(*selected);
+                    self.popup = None;
+                    self.assign(provider_index, channel_id, assignee).await;
+                }
+                _ => {}
+            },
+        }
+    }
+    
+    fn cancel_input(&mut self) {
         self.input_mode = false;
+        self.input_text.clear();
+        self.compose_target = None;
+        self.editing = None;
+        self.replying_to = None;
+        self.autocomplete = None;
+        self.last_typing = None;
+        self.draft_changed = None;
+    }
+
+    /// Opens the input box, bringing back the draft left for the current
+    /// destination if there is one
+    async fn start_input(&mut self) {
+        self.input_mode = true;
+        if !self.input_text.is_empty() {
+            return;
+        }
+        let Some((provider_key, channel_id)) = self.draft_destination() else {
+            return;
+        };
+        if let Ok(Some(draft)) = self.cache.get_draft(&provider_key, channel_id.as_deref()).await {
+            self.input_text = draft;
+            self.set_status("Restored your draft".to_string());
+        }
+    }
+
+    /// The (provider key, channel) a draft of the input belongs to. Edits
+    /// of sent messages aren't drafts.
+    fn draft_destination(&self) -> Option<(String, Option<String>)> {
+        if self.editing.is_some() {
+            return None;
+        }
+        let target = self.resolve_send_target()?;
+        Some((self.integration_manager.providers[target.provider_index].provider_key(), target.channel_id))
+    }
+
+    async fn save_draft(&mut self) {
+        self.draft_changed = None;
+        let Some((provider_key, channel_id)) = self.draft_destination() else {
+            return;
+        };
+        if let Err(e) = self.cache.save_draft(&provider_key, channel_id.as_deref(), &self.input_text).await {
+            eprintln!("Warning: Failed to save draft: {}", e);
+        }
+    }
+
+    /// Saves the draft once typing has paused, rather than on every key
+    async fn save_draft_if_due(&mut self) {
+        if self.draft_changed.is_some_and(|at| at.elapsed() >= DRAFT_SAVE_DELAY) {
+            self.save_draft().await;
+        }
+    }
+
+    /// Loads the selected message into the input box for editing
+    fn start_edit(&mut self) {
+        let Some(msg) = self.get_selected_message().cloned() else {
+            return;
+        };
+        let Some(provider_index) = self.integration_manager.find_provider_index(msg.source, msg.channel_id.as_deref()) else {
+            self.set_status(format!("No provider configured for {:?}", msg.source));
+            return;
+        };
+        if let Some(refusal) = edit_refusal(&msg, self.integration_manager.providers[provider_index].as_ref()) {
+            self.set_status(refusal);
+            return;
+        }
+
+        let target = SendTarget { provider_index, channel_id: msg.channel_id };
+        self.editing = Some((target, (msg.source, msg.id)));
+        self.input_text = msg.content;
+        self.input_mode = true;
+    }
+
+    /// Per-source unread counts like `TG:3 DC:0`, for every configured source
+    fn unread_badge(&self) -> String {
+        let mut sources: Vec<MessageSource> = Vec::new();
+        for provider in &self.integration_manager.providers {
+            if !sources.contains(&provider.source()) {
+                sources.push(provider.source());
+            }
+        }
+        sources
+            .iter()
+            .map(|source| format!("{}:{}", source.abbreviation(), self.unread_counts.get(source).copied().unwrap_or(0)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Updates the on-screen copy of a message being sent
+    fn set_send_status(&mut self, id: u64, status: SendStatus) {
+        if let Some(message) = self.messages.iter_mut().find(|m| m.id == id && m.author == "You") {
+            message.send_status = Some(status);
+        }
+    }
+
+    /// A fresh id for a message that only exists on screen, which can't
+    /// collide with a fetched message or another local one
+    fn next_local_id(&mut self) -> u64 {
+        self.last_local_id -= 1;
+        self.last_local_id
+    }
+
+    /// Whether the last send can still be undone
+    fn can_undo_send(&self) -> bool {
+        self.last_sent.as_ref().is_some_and(|(_, _, _, at)| at.elapsed() < UNDO_WINDOW)
+    }
+
+    /// Deletes the message just sent and puts its text back in the input,
+    /// addressed where it went, so it can be fixed and resent
+    async fn undo_send(&mut self) {
+        if !self.can_undo_send() {
+            return;
+        }
+        let Some((target, id, content, _)) = self.last_sent.take() else {
+            return;
+        };
+
+        let provider = self.integration_manager.providers[target.provider_index].as_ref();
+        let source = provider.source();
+        if let Err(e) = provider.delete_message(target.channel_id.as_deref(), id).await {
+            self.set_status(format!("Couldn't undo the send: {}", e));
+            return;
+        }
+
+        // The refresh after sending may already have shown it
+        self.messages.retain(|m| (m.source, m.id) != (source, id));
+        if self.selected_message.is_some_and(|i| i >= self.messages.len()) {
+            self.selected_message = self.messages.len().checked_sub(1);
+        }
+        if let Err(e) = self.cache.delete_message(id).await {
+            eprintln!("Warning: Failed to remove message from cache: {}", e);
+        }
+
+        self.cancel_input();
+        self.compose_target = Some(target);
+        self.input_text = content;
+        self.input_mode = true;
+        self.set_status("Message unsent".to_string());
+    }
+
+    /// Starts a reply to the selected message: the input opens quoting it
+    /// and addressed to its channel
+    fn start_reply(&mut self) {
+        let Some(msg) = self.get_selected_message().cloned() else {
+            return;
+        };
+        let Some(provider_index) = self.integration_manager.find_provider_index(msg.source, msg.channel_id.as_deref()) else {
+            self.set_status(format!("No provider configured for {:?}", msg.source));
+            return;
+        };
+
+        self.compose_target = Some(SendTarget { provider_index, channel_id: msg.channel_id });
+        self.replying_to = Some((msg.id, msg.author));
+        self.input_text = msg.content.lines().map(|line| format!("> {}\n", line)).collect();
+        self.input_mode = true;
+    }
+
+    /// Saves the edit in progress, or sends the input as a new message
+    async fn submit_input(&mut self) {
+        let Some((target, key)) = self.editing.clone() else {
+            if self.confirm_send && !self.input_text.is_empty() {
+                // The key handler sends once it's confirmed. The target is
+                // pinned so a refresh moving the selection can't change it.
+                self.confirming_send = self.resolve_send_target();
+            }
+            if self.confirming_send.is_none() {
+                self.send_input();
+            }
+            return;
+        };
+        if self.input_text.is_empty() {
+            return;
+        }
+
+        let provider = self.integration_manager.providers[target.provider_index].as_ref();
+        match provider.edit_message(target.channel_id.as_deref(), key.1, &self.input_text).await {
+            Ok(()) => {
+                let new_content = self.input_text.clone();
+                self.cancel_input();
+                if let Some(message) = self.messages.iter_mut().find(|m| (m.source, m.id) == key) {
+                    message.content = new_content;
+                    message.edited = true;
+                    let message = message.clone();
+                    if let Err(e) = self.cache.cache_messages(&[message]).await {
+                        eprintln!("Warning: Failed to cache edited message: {}", e);
+                    }
+                }
+                self.set_status("Message edited".to_string());
+            }
+            // Keep the text so it can be retried or cancelled
+            Err(e) => self.set_status(format!("Edit failed: {}", e)),
+        }
+    }
+
+    /// Opens, narrows or closes the mention list for the word being typed.
+    /// Names come from the authors of loaded messages where the input will
+    /// be sent.
+    fn update_autocomplete(&mut self) {
+        let start = self.input_text.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let Some(prefix) = self.input_text[start..].strip_prefix('@') else {
+            self.autocomplete = None;
+            return;
+        };
+        let prefix = prefix.to_lowercase();
+        let Some(target) = self.resolve_send_target() else {
+            self.autocomplete = None;
+            return;
+        };
+        let source = self.integration_manager.providers[target.provider_index].source();
+        let mut candidates: Vec<String> = Vec::new();
+        let in_channel = self
+            .messages
+            .iter()
+            .chain(self.threads.values().flatten())
+            .filter(|m| m.source == source)
+            .filter(|m| target.channel_id.is_none() || m.channel_id == target.channel_id);
+        for message in in_channel {
+            let author = message.author.as_str();
+            if matches!(author, "You" | "System")
+                || !author.to_lowercase().starts_with(&prefix)
+                || candidates.iter().any(|c| c == author)
+            {
+                continue;
+            }
+            candidates.push(author.to_string());
+            if candidates.len() == MAX_MENTION_CANDIDATES {
+                break;
+            }
+        }
+        if candidates.is_empty() {
+            self.autocomplete = None;
+            return;
+        }
+        // Keep the highlighted name if it still matches
+        let selected = self
+            .autocomplete
+            .as_ref()
+            .and_then(|current| candidates.iter().position(|c| *c == current.candidates[current.selected]))
+            .unwrap_or(0);
+        self.autocomplete = Some(Autocomplete { start, candidates, selected });
+    }
+
+    fn move_autocomplete(&mut self, forward: bool) {
+        if let Some(autocomplete) = &mut self.autocomplete {
+            let len = autocomplete.candidates.len();
+            autocomplete.selected = if forward {
+                (autocomplete.selected + 1) % len
+            } else {
+                (autocomplete.selected + len - 1) % len
+            };
+        }
+    }
+
+    /// Replaces the partial `@mention` with the highlighted name
+    fn accept_autocomplete(&mut self) {
+        let Some(autocomplete) = self.autocomplete.take() else {
+            return;
+        };
+        self.input_text.truncate(autocomplete.start);
+        self.input_text.push('@');
+        self.input_text.push_str(&autocomplete.candidates[autocomplete.selected]);
+        self.input_text.push(' ');
+        self.draft_changed = Some(Instant::now());
+    }
+
+    fn send_input(&mut self) {
+        if let Err(e) = self.send_message_non_blocking() {
+            eprintln!("Error sending message: {}", e);
+        }
+    }
+
+    /// Answers the `CONFIRM_SEND` prompt. Declining keeps the text to fix
+    /// the destination or carry on typing.
+    fn answer_send_confirmation(&mut self, send: bool) {
+        let target = self.confirming_send.take();
+        if send {
+            self.compose_target = target;
+            self.send_input();
+        } else {
+            self.set_status("Not sent".to_string());
+        }
+    }
+
+    /// Where a message to `target` goes, e.g. `Discord #general` or
+    /// `Jira PROJ-12`
+    fn describe_target(&self, target: &SendTarget) -> String {
+        let provider = &self.integration_manager.providers[target.provider_index];
+        match &target.channel_id {
+            // A chat or issue other than the one the provider is set up for
+            Some(channel_id) if provider.channel_id().as_ref() != Some(channel_id) => {
+                format!("{} {}", provider.display_name(), channel_id)
+            }
+            _ => provider.display_name(),
+        }
+    }
+
+    /// Lets the other side see we're typing, at most once per the
+    /// provider's typing interval
+    fn notify_typing(&mut self) {
+        if self.editing.is_some() {
+            return;
+        }
+        let Some(target) = self.resolve_send_target() else {
+            return;
+        };
+        let key = (target.provider_index, target.channel_id.clone());
+        let interval = self.integration_manager.providers[target.provider_index].typing_interval();
+        if let Some((last_key, at)) = &self.last_typing
+            && *last_key == key
+            && at.elapsed() < interval
+        {
+            return;
+        }
+        self.last_typing = Some((key, Instant::now()));
+
+        let manager = Arc::clone(&self.integration_manager);
+        tokio::spawn(async move {
+            // Only a courtesy, so failures aren't worth reporting
+            let _ = manager.providers[target.provider_index]
+                .send_typing(target.channel_id.as_deref())
+                .await;
+        });
+    }
+    
+    fn send_message_non_blocking(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.input_text.is_empty() {
+            return Ok(());
+        }
+        
+        let message_content = self.input_text.clone();
+        let target = self.resolve_send_target();
+        let reply_to = self.replying_to.take().map(|(id, _)| id);
+        self.cancel_input();
+        
+        let Some(target) = target else {
+            // No matching provider available
+            let error_source = self.get_selected_message().map(|m| m.source).unwrap_or(MessageSource::Discord);
+            let error_message = Message {
+                id: self.next_local_id(),
+                source: error_source,
+                content: format!("❌ No provider configured for {:?}: {}", error_source, message_content),
+                timestamp: Utc::now(),
+                author: "System".to_string(),
+                attachments: vec![],
+                reactions: vec![],
+                channel_id: None,
+                reply_to_id: None,
+                edited: false,
+                send_status: None,
+            };
+            self.messages.push(error_message);
+            self.selected_message = Some(self.messages.len() - 1);
+            return Ok(());
+        };
         
         // Add an optimistic "sending..." message immediately for instant UI feedback
         let sending_message = Message {
-            id: (self.messages.len() + 1) as u64,
-            source: MessageSource::Discord, // Default for now
-            content: format!("📤 Sending: {}", message_content),
+            id: self.next_local_id(),
+            source: self.integration_manager.providers[target.provider_index].source(),
+            content: message_content.clone(),
             timestamp: Utc::now(),
             author: "You".to_string(),
             attachments: vec![],
-            channel_id: None,
+            reactions: vec![],
+            channel_id: target.channel_id.clone(),
+            reply_to_id: reply_to,
+            edited: false,
+            send_status: Some(SendStatus::Pending),
         };
+        let optimistic_id = sending_message.id;
         self.messages.insert(0, sending_message);
         self.selected_message = Some(0);
         
-        // TODO: Actually send the message in the background and update the UI
-        // For now, this provides immediate feedback
+        // The event loop dispatches this after drawing the optimistic message
+        self.pending_send = Some(PendingSend {
+            content: message_content,
+            target,
+            reply_to,
+            optimistic_id,
+        });
+        
+        Ok(())
+    }
+    
+    async fn send_message(&mut self, pending: PendingSend) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let PendingSend { content: message_content, target, reply_to, optimistic_id } = pending;
+        let provider = self.integration_manager.providers[target.provider_index].as_ref();
+        let target_source = provider.source();
+        let provider_key = provider.provider_key();
+        
+        let send_result = deliver(provider, target.channel_id.as_deref(), reply_to, &message_content).await;
+
+        // Sent or queued, the draft has served its purpose. A failed send
+        // becomes the draft, so the text is there to retry.
+        let draft = match &send_result {
+            Err(e) if !integrations::is_network_error(e.as_ref()) => message_content.as_str(),
+            _ => "",
+        };
+        if let Err(e) = self.cache.save_draft(&provider_key, target.channel_id.as_deref(), draft).await {
+            eprintln!("Warning: Failed to update draft: {}", e);
+        }
+
+        match send_result {
+            Ok(sent_id) => {
+                if let Some(id) = sent_id {
+                    record_sent(&self.cache, target_source, id, target.channel_id.clone(), reply_to, &message_content).await;
+                }
+                if let Some(id) = sent_id.filter(|_| provider.supports_delete()) {
+                    self.last_sent = Some((target, id, message_content, Instant::now()));
+                }
+                self.set_send_status(optimistic_id, SendStatus::Sent);
+                self.record_reachability(true);
+                // Refresh messages to show the sent message
+                self.request_refresh();
+            }
+            Err(e) if integrations::is_network_error(e.as_ref()) => {
+                // Keep it for the next refresh to retry rather than losing it
+                match self.cache.enqueue_send(&provider_key, target_source, target.channel_id.as_deref(), &message_content).await {
+                    Ok(()) => {
+                        self.queued_sends += 1;
+                        if let Some(message) = self.messages.iter_mut().find(|m| m.id == optimistic_id && m.author == "You") {
+                            message.content = format!("Queued: {}", message_content);
+                        }
+                    }
+                    Err(db_error) => {
+                        self.set_send_status(optimistic_id, SendStatus::Failed);
+                        let id = self.next_local_id();
+                        self.messages.push(Message {
+                            id,
+                            source: target_source,
+                            content: format!("❌ Failed to send or queue: {} (Error: {}; {})", message_content, e, db_error),
+                            timestamp: Utc::now(),
+                            author: "System".to_string(),
+                            attachments: vec![],
+                            reactions: vec![],
+                            channel_id: None,
+                            reply_to_id: None,
+                            edited: false,
+                            send_status: None,
+                        });
+                        self.selected_message = Some(self.messages.len() - 1);
+                    }
+                }
+            }
+            Err(e) => {
+                self.set_send_status(optimistic_id, SendStatus::Failed);
+                
+                // Add a local error message if sending failed
+                let error_message = Message {
+                    id: self.next_local_id(),
+                    source: target_source,
+                    content: format!("❌ Failed to send: {} (Error: {})", message_content, e),
+                    timestamp: Utc::now(),
+                    author: "System".to_string(),
+                    attachments: vec![],
+                    reactions: vec![],
+                    channel_id: None,
+                    reply_to_id: None,
+                    edited: false,
+                    send_status: None,
+                };
+                self.messages.push(error_message);
+                self.selected_message = Some(self.messages.len() - 1);
+            }
+        }
         
         Ok(())
     }
-    
-    async fn send_message(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if self.input_text.is_empty() {
-            return Ok(());
+}
+
+/// Lays styled text out over at most `max_lines` lines of `width` columns,
+/// measured by display width so wide characters and emoji don't overflow.
+/// Text that still doesn't fit ends in an ellipsis.
+fn fit_spans(spans: Vec<Span<'static>>, width: usize, max_lines: usize) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let mut lines: Vec<Vec<(String, Style)>> = vec![Vec::new()];
+    let mut used = 0;
+    let mut overflow = false;
+
+    'spans: for span in &spans {
+        for c in span.content.chars() {
+            // Multi-line messages are flattened onto the row
+            let c = if c == '\n' { ' ' } else { c };
+            let char_width = c.width().unwrap_or(0);
+            if used + char_width > width {
+                if lines.len() == max_lines {
+                    overflow = true;
+                    break 'spans;
+                }
+                lines.push(Vec::new());
+                used = 0;
+            }
+            let line = lines.last_mut().expect("lines starts non-empty");
+            match line.last_mut() {
+                Some((text, style)) if *style == span.style => text.push(c),
+                _ => line.push((c.to_string(), span.style)),
+            }
+            used += char_width;
+        }
+    }
+
+    if overflow {
+        let line = lines.last_mut().expect("lines starts non-empty");
+        // Make room for the ellipsis, which takes one column
+        while used + 1 > width
+            && let Some((text, _)) = line.last_mut()
+        {
+            if let Some(c) = text.pop() {
+                used -= c.width().unwrap_or(0);
+            }
+            if text.is_empty() {
+                line.pop();
+            }
+        }
+        let style = line.last().map(|(_, style)| *style).unwrap_or_default();
+        line.push(("…".to_string(), style));
+    }
+
+    lines
+        .into_iter()
+        .map(|pieces| Line::from(pieces.into_iter().map(|(text, style)| Span::styled(text, style)).collect::<Vec<_>>()))
+        .collect()
+}
+
+fn ui(f: &mut Frame, app: &App) {
+    let outer_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(f.area());
+
+    app.panes_area.set(outer_chunks[0]);
+    let list_percent = app.layout.list_percent;
+    let chunks = Layout::default()
+        .direction(if app.layout.side_by_side { Direction::Horizontal } else { Direction::Vertical })
+        .constraints([Constraint::Percentage(list_percent), Constraint::Percentage(100 - list_percent)].as_ref())
+        .split(outer_chunks[0]);
+        
+    let content_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)].as_ref())
+        .split(chunks[1]);
+
+    let author_palette: Vec<Color> = match app.colors.author_palette {
+        Some(ref names) => names.iter().map(|name| parse_color(name)).collect(),
+        None => DEFAULT_AUTHOR_PALETTE.to_vec(),
+    };
+
+    let rows = app.list_rows();
+    let unread = rows
+        .iter()
+        .filter(|row| matches!(row, ListRow::Message(i) if app.is_unread(&app.messages[*i])))
+        .count();
+    let mut list_title = if unread > 0 { format!("Messages ({} unread)", unread) } else { "Messages".to_string() };
+    let badge = app.unread_badge();
+    if !badge.is_empty() {
+        list_title = format!("{} │ {}", list_title, badge);
+    }
+
+    let row_width = chunks[0].width.saturating_sub(2 + selection_marker(app).len() as u16) as usize;
+    let row_lines = if app.wrap_list { 2 } else { 1 };
+    let items: Vec<ListItem> = rows
+        .iter()
+        .enumerate()
+        .map(|(position, row)| {
+            let i = match row {
+                ListRow::DateHeader(label) => {
+                    return ListItem::new(Line::from(format!("── {} ──", label)))
+                        .style(Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD));
+                }
+                ListRow::Message(i) => *i,
+            };
+            let msg = &app.messages[i];
+            let source_prefix = app.icons.for_source(msg.source);
+            
+            let is_selected = Some(i) == app.selected_message;
+            
+            // The selected row's highlight takes precedence over author and source colors
+            let author_style = if app.colors.author_colors && !is_selected {
+                Style::default().fg(author_color(&msg.author, &author_palette))
+            } else {
+                Style::default()
+            };
+            let source_style = if is_selected {
+                Style::default()
+            } else {
+                Style::default().fg(source_color(msg.source, &app.colors))
+            };
+            
+            // Continues a run from the same author: skip the repeated prefix
+            let continues_run = app.group_by_author
+                && position > 0
+                && matches!(rows[position - 1], ListRow::Message(prev)
+                    if app.messages[prev].author == msg.author && app.messages[prev].source == msg.source);
+            
+            let mut spans = Vec::new();
+            if app.is_unread(msg) {
+                spans.push(Span::styled("● ", Style::default().fg(Color::Cyan)));
+            }
+            if app.is_bookmarked(msg) {
+                spans.push(Span::styled("★ ", Style::default().fg(Color::Yellow)));
+            }
+            if continues_run {
+                spans.push(Span::raw(format!("    {} ({})", msg.content, app.format_time(msg.timestamp))));
+            } else {
+                spans.push(Span::styled(format!("{} ", source_prefix), source_style));
+                spans.push(Span::styled(msg.author.clone(), author_style));
+                spans.push(Span::raw(format!(" - {} ({})", msg.content, app.format_time(msg.timestamp))));
+            }
+            if msg.edited {
+                spans.push(Span::styled(" (edited)", Style::default().fg(Color::DarkGray)));
+            }
+            if let Some(status) = msg.send_status {
+                let color = match status {
+                    SendStatus::Pending => Color::Yellow,
+                    SendStatus::Sent => Color::Green,
+                    SendStatus::Failed => Color::Red,
+                };
+                let style = if is_selected { Style::default() } else { Style::default().fg(color) };
+                spans.push(Span::styled(format!(" {}", status.glyph(app.plain)), style));
+            }
+            let content = Text::from(fit_spans(spans, row_width, row_lines));
+            
+            let style = if is_selected {
+                let mut style = Style::default();
+                if let Some(ref bg_color) = app.colors.selected_bg {
+                    style = style.bg(parse_color(bg_color));
+                } else {
+                    style = style.bg(Color::Blue); // Default
+                }
+                if let Some(ref fg_color) = app.colors.selected_fg {
+                    style = style.fg(parse_color(fg_color));
+                }
+                style
+            } else if app.matches_alert(msg) {
+                let bg = app.colors.alert_bg.as_deref().map(parse_color).unwrap_or(Color::Red);
+                Style::default().bg(bg).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            
+            ListItem::new(content).style(style)
+        })
+        .collect();
+
+    *app.list_heights.borrow_mut() = items.iter().map(ListItem::height).collect();
+    let messages_list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(list_title))
+        .highlight_symbol(selection_marker(app))
+        .style(Style::default());
+
+    let mut list_state = ratatui::widgets::ListState::default();
+    list_state.select(app.selected_message.and_then(|selected| {
+        rows.iter().position(|row| matches!(row, ListRow::Message(i) if *i == selected))
+    }));
+
+    f.render_stateful_widget(messages_list, chunks[0], &mut list_state);
+    app.list_area.set(chunks[0]);
+    app.list_offset.set(list_state.offset());
+
+    let content = if let Some(msg) = app.get_selected_message() {
+        let badge_style = if app.colors.author_colors {
+            // Black or white text, whichever reads better on the author's color
+            let color = author_color(&msg.author, &author_palette);
+            let text_color = match color {
+                Color::Black | Color::Blue | Color::Red | Color::Magenta | Color::DarkGray | Color::Reset => Color::White,
+                _ => Color::Black,
+            };
+            Style::default().bg(color).fg(text_color)
+        } else {
+            Style::default().add_modifier(Modifier::REVERSED)
+        };
+        let badge = if app.plain { format!("[{}]", initials(&msg.author)) } else { format!(" {} ", initials(&msg.author)) };
+        let mut text = Text::from(format!("Source: {:?}", msg.source));
+        text.extend([Line::from(vec![
+            Span::raw("Author: "),
+            Span::styled(badge, badge_style.add_modifier(Modifier::BOLD)),
+            Span::raw(format!(" {}", msg.author)),
+        ])]);
+        text.extend(Text::from(format!(
+            "Time: {}{}\n",
+            app.format_date_time(msg.timestamp),
+            if msg.edited { " (edited)" } else { "" },
+        )));
+        
+        if msg.reply_to_id.is_some() {
+            let quote = match app.find_parent(msg) {
+                Some(parent) => {
+                    let parent = &app.messages[parent];
+                    let first_line = parent.content.lines().next().unwrap_or("");
+                    let excerpt: String = first_line.chars().take(80).collect();
+                    let ellipsis = if excerpt.len() < parent.content.len() { "…" } else { "" };
+                    format!("↪ {}: {}{} (p to jump)", parent.author, excerpt, ellipsis)
+                }
+                None => "↪ Reply to a message that isn't loaded".to_string(),
+            };
+            text.extend(Text::styled(quote, Style::default().fg(Color::DarkGray)));
+        }
+        
+        let shortcodes = |content: &str| {
+            if app.render_shortcodes { emoji::render_shortcodes(content) } else { content.to_string() }
+        };
+        let msg_content = shortcodes(&msg.content);
+        let body = if app.render_markdown {
+            markdown::render(&msg_content)
+        } else {
+            None
+        };
+        text.extend(body.unwrap_or_else(|| Text::from(msg_content)));
+        
+        if !msg.reactions.is_empty() {
+            let reactions: Vec<String> = msg.reactions.iter().map(|r| format!("{} {}", r.emoji, r.count)).collect();
+            text.extend(Text::from(format!("\n{}", reactions.join("  "))));
+        }
+        
+        if !msg.attachments.is_empty() {
+            let mut attachments = String::from("\nAttachments:");
+            for attachment in &msg.attachments {
+                let type_icon = match (&attachment.file_type, app.plain) {
+                    (AttachmentType::Image, false) => "🖼️",
+                    (AttachmentType::Video, false) => "🎥",
+                    (AttachmentType::Audio, false) => "🎵",
+                    (AttachmentType::Document, false) => "📄",
+                    (AttachmentType::Archive, false) => "🗜️",
+                    (AttachmentType::Other, false) => "📎",
+                    (AttachmentType::Image, true) => "[image]",
+                    (AttachmentType::Video, true) => "[video]",
+                    (AttachmentType::Audio, true) => "[audio]",
+                    (AttachmentType::Document, true) => "[document]",
+                    (AttachmentType::Archive, true) => "[archive]",
+                    (AttachmentType::Other, true) => "[file]",
+                };
+                
+                let size_str = if let Some(size) = attachment.size {
+                    format!(" ({}B)", size)
+                } else {
+                    String::new()
+                };
+                
+                let saved = match (&attachment.local_path, app.plain) {
+                    (Some(_), false) => " ✓",
+                    (Some(_), true) => " (saved)",
+                    (None, _) => "",
+                };
+                attachments.push_str(&format!("\n  {} {}{}{}", type_icon, attachment.filename, size_str, saved));
+            }
+            text.extend(Text::from(attachments));
+        }
+        
+        if let Some(replies) = app.threads.get(&(msg.source, msg.id)).filter(|r| !r.is_empty()) {
+            text.extend(Text::from(format!("\nDiscussion ({}):", replies.len())));
+            for reply in replies {
+                text.extend(Text::styled(
+                    format!("┌ {} · {}", reply.author, app.format_date_time(reply.timestamp)),
+                    Style::default().fg(author_color(&reply.author, &author_palette)),
+                ));
+                text.extend(Text::from(shortcodes(&reply.content).lines().map(|line| format!("│ {}", line)).collect::<Vec<_>>().join("\n")));
+            }
+        }
+        
+        text
+    } else {
+        Text::from("No message selected")
+    };
+
+    let content_area = Paragraph::new(content)
+        .block(Block::default().borders(Borders::ALL).title("Content"))
+        .style(Style::default());
+
+    f.render_widget(content_area, content_chunks[0]);
+
+    #[cfg(feature = "image-preview")]
+    render_image_preview(f, app, content_chunks[0]);
+    
+    let input_style = if app.input_mode {
+        let color = if let Some(ref active_color) = app.colors.input_active {
+            parse_color(active_color)
+        } else {
+            Color::Yellow // Default
+        };
+        Style::default().fg(color)
+    } else {
+        let color = if let Some(ref inactive_color) = app.colors.input_inactive {
+            parse_color(inactive_color)
+        } else {
+            Color::DarkGray // Default
+        };
+        Style::default().fg(color)
+    };
+    
+    let input_title = if app.search_editing {
+        match &app.search_error {
+            Some(error) => error.clone(),
+            None => "Search, e.g. author:bot source:discord error or /re fail(ed|ure) (Enter to apply, Esc to clear)".to_string(),
+        }
+    } else if app.editing.is_some() {
+        "Editing message (Tab to save, Esc to cancel)".to_string()
+    } else if let Some((_, author)) = app.replying_to.as_ref().filter(|_| app.input_mode) {
+        format!("Replying to {} (Tab to send, Esc to cancel)", author)
+    } else if let Some(target) = &app.confirming_send {
+        format!("Send to {}? (y/n)", app.describe_target(target))
+    } else if app.input_mode {
+        match app.resolve_send_target() {
+            Some(target) => format!("Input → {} (Tab to send, Esc to cancel)", app.describe_target(&target)),
+            None => "Input (Tab to send, Esc to cancel)".to_string(),
+        }
+    } else {
+        "Input (Enter to type, c to compose, Tab to send)".to_string()
+    };
+    
+    let (input_text, input_style) = if app.search_editing {
+        let color = if app.search_error.is_some() { Color::Red } else { Color::Yellow };
+        (app.search_text.as_str(), Style::default().fg(color))
+    } else {
+        (app.input_text.as_str(), input_style)
+    };
+
+    // Keep the end of the text and the cursor in view when it outgrows the
+    // box, which happens quickly in the narrower side-by-side pane. Only the
+    // line being typed is shown, e.g. below a reply's quote.
+    let line_index = input_text.matches('\n').count();
+    let last_line = input_text.rsplit('\n').next().unwrap_or("");
+    let text_width = last_line.chars().count() as u16;
+    let input_offset = text_width.saturating_sub(content_chunks[1].width.saturating_sub(3));
+    let input_text = match app.dictionary.get() {
+        Some(dictionary) if app.input_mode && !app.search_editing => {
+            underline_misspelled(input_text, &dictionary.misspelled(input_text), input_style)
+        }
+        _ => Text::raw(input_text),
+    };
+    let input_area = Paragraph::new(input_text)
+        .block(Block::default().borders(Borders::ALL).title(input_title))
+        .style(input_style)
+        .scroll((line_index as u16, input_offset));
+
+    f.render_widget(input_area, content_chunks[1]);
+    
+    if app.input_mode || app.search_editing {
+        f.set_cursor_position((
+            content_chunks[1].x + text_width - input_offset + 1,
+            content_chunks[1].y + 1,
+        ));
+    }
+
+    if let Some(autocomplete) = app.autocomplete.as_ref().filter(|_| app.input_mode) {
+        render_autocomplete(f, app, autocomplete, content_chunks[1]);
+    }
+
+    if let Some(popup) = &app.popup {
+        render_popup(f, app, popup);
+    }
+
+    // The clock gets its own column so the long hints never push it off screen
+    let clock = status_clock(app);
+    let status_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(clock.width() as u16)])
+        .split(outer_chunks[1]);
+    f.render_widget(status_bar(app), status_chunks[0]);
+    f.render_widget(
+        Paragraph::new(clock).style(Style::default().bg(Color::DarkGray).fg(Color::White).add_modifier(Modifier::BOLD)),
+        status_chunks[1],
+    );
+
+    if app.plain {
+        strip_styling(f.buffer_mut());
+    }
+}
+
+/// Plain output: drops every color and modifier, and swaps the box drawing
+/// and symbols the UI draws for ASCII, so nothing depends on color or fonts
+fn strip_styling(buffer: &mut Buffer) {
+    for cell in &mut buffer.content {
+        cell.set_style(Style::reset());
+        let ascii = match cell.symbol() {
+            "│" | "║" => "|",
+            "─" | "═" => "-",
+            "┌" | "┐" | "└" | "┘" | "├" | "┤" | "┬" | "┴" | "┼" => "+",
+            "●" | "•" | "·" | "★" => "*",
+            "…" => ".",
+            "→" | "↪" | "📤" => ">",
+            "×" => "x",
+            "⏳" => "~",
+            "❌" => "!",
+            // The refresh spinner
+            symbol if symbol.starts_with(|c| ('\u{2800}'..='\u{28ff}').contains(&c)) => "*",
+            _ => continue,
+        };
+        cell.set_symbol(ascii);
+    }
+}
+
+/// The compose text with each of `misspelled` (byte ranges) underlined in red
+fn underline_misspelled<'a>(text: &'a str, misspelled: &[std::ops::Range<usize>], style: Style) -> Text<'a> {
+    let typo = style.fg(Color::LightRed).add_modifier(Modifier::UNDERLINED);
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let line_end = line_start + line.len();
+        let mut spans = Vec::new();
+        let mut position = line_start;
+        for range in misspelled.iter().filter(|range| range.start >= line_start && range.end <= line_end) {
+            spans.push(Span::raw(&text[position..range.start]));
+            spans.push(Span::styled(&text[range.clone()], typo));
+            position = range.end;
+        }
+        spans.push(Span::raw(&text[position..line_end]));
+        lines.push(Line::from(spans));
+        line_start = line_end + 1;
+    }
+    Text::from(lines)
+}
+
+/// Draws the selected message's first downloaded image over the lower half
+/// of the content pane
+#[cfg(feature = "image-preview")]
+fn render_image_preview(f: &mut Frame, app: &App, content_area: Rect) {
+    let mut preview = app.image_preview.borrow_mut();
+    let Some(preview) = preview.as_mut().filter(|_| !app.plain) else {
+        return;
+    };
+    let Some(path) = app.get_selected_message().and_then(|msg| {
+        msg.attachments
+            .iter()
+            .filter(|a| matches!(a.file_type, AttachmentType::Image))
+            .find_map(|a| a.local_path.as_deref())
+    }) else {
+        return;
+    };
+
+    let half = content_area.height / 2;
+    if half < 4 {
+        return;
+    }
+    let area = Rect {
+        x: content_area.x,
+        y: content_area.y + content_area.height - half,
+        width: content_area.width,
+        height: half,
+    };
+    let block = Block::default().borders(Borders::ALL).title("Preview");
+    let inner = block.inner(area);
+
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+    if !preview.render(f, inner, path) {
+        f.render_widget(Paragraph::new("Can't display this image"), inner);
+    }
+}
+
+/// A rectangle of the given size centered in `area`, clamped to fit
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn render_popup(f: &mut Frame, app: &App, popup: &Popup) {
+    match popup {
+        Popup::ProviderPicker { selected } => {
+            let names: Vec<String> = app.integration_manager.providers
+                .iter()
+                .map(|p| p.display_name())
+                .collect();
+            render_picker(f, app, "Send to (Enter to pick, Esc to cancel)", names, *selected);
         }
-        
-        let message_content = self.input_text.clone();
-        self.input_text.clear();
-        self.input_mode = false;
-        
-        // Determine which provider to use based on the selected message
-        let (target_source, target_channel) = if let Some(selected_msg) = self.get_selected_message() {
-            (Some(selected_msg.source), selected_msg.channel_id.clone())
-        } else {
-            (None, None)
-        };
-        
-        // Find a provider that matches both the target source and channel
-        let providers = &self.integration_manager.providers;
-        let target_provider = if let Some(source) = target_source {
-            providers.iter().find(|p| {
-                p.source() == source && 
-                (target_channel.is_none() || p.channel_id() == target_channel || 
-                 (source == MessageSource::Telegram && p.channel_id().is_none())) // Telegram client handles all chats
-            })
-        } else {
-            providers.first()
+        Popup::TransitionPicker { channel_id, transitions, selected, .. } => {
+            let names: Vec<String> = transitions
+                .iter()
+                .map(|t| if t.name == t.to_status { t.name.clone() } else { format!("{} → {}", t.name, t.to_status) })
+                .collect();
+            render_picker(f, app, &format!("Move {} (Enter to apply, Esc to cancel)", jira::issue_key(channel_id)), names, *selected);
+        }
+        Popup::Palette { entries, query, selected } => {
+            let names: Vec<String> = palette::filter(entries, query)
+                .into_iter()
+                .map(|index| entries[index].label.clone())
+                .collect();
+            render_search_picker(f, app, "Command palette (Enter to run, Esc to cancel)", query, names, *selected);
+        }
+        Popup::Emoji { query, selected } => {
+            let names: Vec<String> = emoji::search(query)
+                .into_iter()
+                .map(|(code, emoji)| format!("{}  :{}:", emoji, code))
+                .collect();
+            render_search_picker(f, app, "Emoji (type to search, Enter to insert, Esc to cancel)", query, names, *selected);
+        }
+        Popup::Attachments { filenames, selected, .. } => {
+            render_picker(f, app, "Open (Enter to open, Esc to cancel)", filenames.clone(), *selected);
+        }
+        Popup::AssigneePicker { channel_id, assignees, selected, .. } => {
+            let names: Vec<String> = assignees
+                .iter()
+                .map(|a| a.as_ref().map_or_else(|| "Unassigned".to_string(), |a| a.name.clone()))
+                .collect();
+            render_picker(f, app, &format!("Assign {} (Enter to pick, Esc to cancel)", jira::issue_key(channel_id)), names, *selected);
+        }
+    }
+}
+
+/// A centered list under a line of search text, as in the command palette
+fn render_search_picker(f: &mut Frame, app: &App, title: &str, query: &str, names: Vec<String>, selected: usize) {
+    let width = (f.area().width * 2 / 3).max(40);
+    let height = (names.len() as u16 + 3).min(f.area().height.saturating_sub(4)).max(4);
+    let area = centered_rect(width, height, f.area());
+    let [input_area, list_area] = Layout::vertical([Constraint::Length(1), Constraint::Min(1)])
+        .areas(Block::default().borders(Borders::ALL).inner(area));
+
+    let items: Vec<ListItem> = names.into_iter().map(ListItem::new).collect();
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(Color::Blue))
+        .highlight_symbol(selection_marker(app));
+    let mut state = ratatui::widgets::ListState::default();
+    state.select(Some(selected));
+
+    f.render_widget(Clear, area);
+    f.render_widget(Block::default().borders(Borders::ALL).title(title.to_string()), area);
+    f.render_widget(Paragraph::new(format!("> {}", query)).style(Style::default().fg(Color::Yellow)), input_area);
+    f.render_stateful_widget(list, list_area, &mut state);
+}
+
+/// Marks the selected row where color can't, in plain output
+fn selection_marker(app: &App) -> &'static str {
+    if app.plain { "> " } else { "" }
+}
+
+/// Draws a centered, selectable list of options
+fn render_picker(f: &mut Frame, app: &App, title: &str, names: Vec<String>, selected: usize) {
+    let width = names
+        .iter()
+        .map(|n| n.chars().count())
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0)
+        .max(30) as u16 + 6;
+    let area = centered_rect(width, names.len() as u16 + 2, f.area());
+
+    let items: Vec<ListItem> = names.into_iter().map(ListItem::new).collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()))
+        .highlight_style(Style::default().bg(Color::Blue))
+        .highlight_symbol(selection_marker(app));
+
+    let mut state = ratatui::widgets::ListState::default();
+    state.select(Some(selected));
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// The `@mention` candidates, in a list just above the input box
+fn render_autocomplete(f: &mut Frame, app: &App, autocomplete: &Autocomplete, input_area: Rect) {
+    let width = autocomplete
+        .candidates
+        .iter()
+        .map(|name| name.width())
+        .max()
+        .unwrap_or(0) as u16
+        + 5;
+    let height = autocomplete.candidates.len() as u16 + 2;
+    let area = Rect {
+        x: input_area.x,
+        y: input_area.y.saturating_sub(height),
+        width: width.min(input_area.width),
+        height: height.min(input_area.y),
+    };
+
+    let items: Vec<ListItem> = autocomplete.candidates.iter().map(|name| ListItem::new(name.as_str())).collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(Style::default().bg(Color::Blue))
+        .highlight_symbol(selection_marker(app));
+
+    let mut state = ratatui::widgets::ListState::default();
+    state.select(Some(autocomplete.selected));
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn status_bar(app: &App) -> Paragraph<'static> {
+    let hints = if app.search_editing {
+        "Enter apply  Esc clear"
+    } else if app.confirming_send.is_some() {
+        "y send  n keep editing"
+    } else if app.autocomplete.is_some() && app.input_mode {
+        "Tab complete  Up/Down choose  Esc close"
+    } else if app.input_mode {
+        "Tab send  Ctrl-E emoji  Esc cancel"
+    } else {
+        "j/k move  g/G top/bottom  Ctrl-F/B page  Ctrl-P palette  / search  Enter type  c compose  > reply  e edit  u undo send  r/R refresh/mark read  a auto-refresh  n/z DND/snooze  f filter  d delete  p parent  t/A transition/assign  b/B bookmark/bookmarks  s/S/o save/save all/open attachment  y/Y copy text/URL  m/M mute channel/author  X clear source  q quit"
+    };
+
+    let mut filter = match app.source_filter {
+        Some(source) => format!("{:?}", source),
+        None => "All".to_string(),
+    };
+    if app.bookmarks_only {
+        filter.push_str(" ★");
+    }
+    if app.search.is_some() {
+        filter.push_str(&format!(" /{}", app.search_text.trim()));
+    }
+
+    let refresh_state = if app.is_refreshing {
+        const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+        let frame = (app.refresh_started.elapsed().as_millis() / 100) as usize % SPINNER.len();
+        let providers = &app.integration_manager.providers;
+        let waiting: Vec<String> = (0..providers.len())
+            .filter(|index| !app.providers_done.contains(index))
+            .map(|index| providers[index].display_name())
+            .collect();
+        match waiting.as_slice() {
+            // Nothing left to wait for, or nothing worth itemising
+            _ if providers.len() < 2 || waiting.is_empty() => format!("{} Refreshing…", SPINNER[frame]),
+            [only] => format!("{} Refreshing {}/{}, waiting for {}", SPINNER[frame], app.providers_done.len(), providers.len(), only),
+            _ => format!("{} Refreshing {}/{}", SPINNER[frame], app.providers_done.len(), providers.len()),
+        }
+    } else if let Some(at) = app.last_refresh_at {
+        format!("Last refresh {}", app.format_time(at))
+    } else {
+        "Not refreshed yet".to_string()
+    };
+
+    let live = if app.live_sources.is_empty() {
+        String::new()
+    } else {
+        let sources: Vec<String> = app.live_sources.iter().map(|s| format!("{:?}", s)).collect();
+        format!("Live: {} │ ", sources.join(", "))
+    };
+
+    // Stays up until the connection is back, unlike the status message
+    let offline = if app.connection_failures.is_empty() {
+        String::new()
+    } else {
+        let names: Vec<String> = app.connection_failures
+            .keys()
+            .map(|key| app.integration_manager.find_provider_by_key(key).map_or_else(|| key.clone(), |provider| provider.display_name()))
+            .collect();
+        format!("Offline: {} │ ", names.join(", "))
+    };
+
+    let auto_refresh = if app.auto_refresh && !app.online {
+        format!("Auto retrying every {}s", app.current_refresh_interval().as_secs())
+    } else if app.auto_refresh {
+        format!("Auto {}s", app.refresh_interval.as_secs())
+    } else {
+        "Auto paused".to_string()
+    };
+
+    let silenced = match app.snoozed_until {
+        _ if app.dnd => "DND │ ".to_string(),
+        Some(until) if Instant::now() < until => {
+            let until = Local::now() + (until - Instant::now());
+            format!("Snoozed until {} │ ", until.format("%H:%M"))
+        }
+        _ => String::new(),
+    };
+
+    let provider_name = app
+        .get_selected_message()
+        .and_then(|msg| app.integration_manager.find_provider(msg.source, msg.channel_id.as_deref()))
+        .map(|provider| format!("{} │ ", provider.display_name()))
+        .unwrap_or_default();
+
+    let undo = if app.can_undo_send() { "Undo (u) │ " } else { "" };
+
+    let batch = match &app.batch_download {
+        Some(batch) => format!("Saving {}/{} │ ", batch.saved + batch.skipped + batch.failed, batch.total),
+        None => String::new(),
+    };
+
+    let queue = if app.queued_sends > 0 {
+        format!("Queued: {} │ ", app.queued_sends)
+    } else {
+        String::new()
+    };
+
+    // Copy feedback replaces the refresh state for a few seconds
+    let refresh_state = match &app.status_message {
+        Some((message, at)) if at.elapsed() < Duration::from_secs(3) => message.clone(),
+        _ => refresh_state,
+    };
+
+    let banner = if app.online { "" } else { " OFFLINE " };
+
+    let line = Line::from(vec![
+        Span::styled(banner, Style::default().bg(Color::Red).fg(Color::White).add_modifier(Modifier::BOLD)),
+        Span::styled(format!(" {} ", hints), Style::default().fg(Color::Gray)),
+        Span::raw("│ "),
+        Span::raw(provider_name),
+        Span::raw(format!("Filter: {} ", filter)),
+        Span::raw("│ "),
+        Span::raw(live),
+        Span::styled(offline, Style::default().fg(Color::LightRed)),
+        Span::styled(undo, Style::default().fg(Color::LightGreen)),
+        Span::styled(queue, Style::default().fg(Color::Yellow)),
+        Span::styled(batch, Style::default().fg(Color::LightCyan)),
+        Span::styled(silenced, Style::default().fg(Color::LightRed)),
+        Span::raw(format!("{} ", auto_refresh)),
+        Span::raw("│ "),
+        Span::raw(refresh_state),
+    ]);
+
+    Paragraph::new(line).style(Style::default().bg(Color::DarkGray).fg(Color::White))
+}
+
+/// How long ago the feed was last refreshed, and the local time. Redrawn
+/// every tick, so both keep counting without input.
+fn status_clock(app: &App) -> String {
+    let now = Local::now().format(&app.time.time_format);
+    let Some(at) = app.last_refresh_at else {
+        return format!("│ {} ", now);
+    };
+    let age = (Utc::now() - at).num_seconds().max(0);
+    let age = match age {
+        0..60 => format!("{}s", age),
+        60..3600 => format!("{}m", age / 60),
+        _ => format!("{}h", age / 3600),
+    };
+    format!("│ Refreshed {} ago │ {} ", age, now)
+}
+
+/// Prompts for the cache passphrase when encryption is on but none is configured
+fn resolve_database_passphrase(database: &mut config::DatabaseConfig) -> io::Result<()> {
+    if !database.encrypt || database.passphrase.is_some() {
+        return Ok(());
+    }
+
+    database.passphrase = Some(read_hidden("Database passphrase: ")?);
+    Ok(())
+}
+
+/// Reads a line from the terminal without echoing it, for passphrases and
+/// secrets. Piped input is read as is.
+fn read_hidden(prompt: &str) -> io::Result<String> {
+    print!("{}", prompt);
+    io::Write::flush(&mut io::stdout())?;
+    if !io::IsTerminal::is_terminal(&io::stdin()) {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    enable_raw_mode()?;
+    let line = read_hidden_keys();
+    disable_raw_mode()?;
+    println!();
+    line
+}
+
+fn read_hidden_keys() -> io::Result<String> {
+    let mut line = String::new();
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
         };
-        
-        if let Some(provider) = target_provider {
-            let send_result = if target_source == Some(MessageSource::Telegram) && target_channel.is_some() {
-                // Special handling for Telegram - send to specific chat
-                if let Some(chat_id) = &target_channel {
-                    // We need to downcast to TelegramProvider to access send_message_to_chat
-                    // For now, let's use a simpler approach and add the chat context to the message
-                    provider.send_message(&format!("Reply to chat {}: {}", chat_id, message_content)).await
-                } else {
-                    provider.send_message(&message_content).await
-                }
-            } else {
-                provider.send_message(&message_content).await
-            };
+        if key.kind != event::KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(line),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "Cancelled"));
+            }
+            KeyCode::Char(c) => line.push(c),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            _ => {}
+        }
+    }
+}
 
-            match send_result {
-                Ok(()) => {
-                    // Refresh messages to show the sent message
-                    if let Err(e) = self.refresh_messages().await {
-                        eprintln!("Error refreshing after sending: {}", e);
-                    }
-                }
-                Err(e) => {
-                    // Add a local error message if sending failed
-                    let error_source = target_source.unwrap_or(MessageSource::Discord);
-                    let error_message = Message {
-                        id: (self.messages.len() + 1) as u64,
-                        source: error_source,
-                        content: format!("❌ Failed to send: {} (Error: {})", message_content, e),
-                        timestamp: Utc::now(),
-                        author: "System".to_string(),
-                        attachments: vec![],
-                        channel_id: None,
-                    };
-                    self.messages.push(error_message);
-                    self.selected_message = Some(self.messages.len() - 1);
-                }
+async fn run_cleanup(scope: cli::CleanupScope) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut config = Config::from_env()?;
+    resolve_database_passphrase(&mut config.database)?;
+
+    let db_path = &config.database.path;
+    let cache = MessageCache::new(&database::database_url(db_path)?, config.database.passphrase.as_deref()).await?;
+
+    let deleted = match scope {
+        cli::CleanupScope::All => cache.clear_all().await?,
+        cli::CleanupScope::OlderThan(age) => {
+            let cutoff = Utc::now().checked_sub_signed(age).ok_or("Invalid age: too far back")?;
+            cache.delete_older_than(cutoff).await?
+        }
+    };
+
+    println!("Deleted {} cached messages from {}", deleted, db_path.display());
+    Ok(())
+}
+
+/// Writes one channel's cached messages, oldest first, to a file or stdout
+async fn run_export(options: cli::ExportOptions) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut config = Config::from_env()?;
+    resolve_database_passphrase(&mut config.database)?;
+    let cache = MessageCache::new(&database::database_url(&config.database.path)?, config.database.passphrase.as_deref()).await?;
+
+    let mut messages = cache.get_messages_by_channel(options.source, &options.channel_id, None).await?;
+    if messages.is_empty() {
+        return Err(format!("No cached {:?} messages in channel {}", options.source, options.channel_id).into());
+    }
+    messages.reverse();
+
+    let format = options.format.unwrap_or_else(|| options.output.as_deref().map_or(export::Format::Markdown, export::Format::for_path));
+    let contents = export::render(&messages, &format!("{:?} {}", options.source, options.channel_id), format);
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, contents)?;
+            eprintln!("Exported {} messages to {}", messages.len(), path.display());
+        }
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+/// Adds the messages of a JSON export to the cache. Messages already cached
+/// are left alone, so importing the same file twice is harmless.
+async fn run_import(path: &std::path::Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let messages = export::from_json(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut config = Config::from_env()?;
+    resolve_database_passphrase(&mut config.database)?;
+    let mut cache = MessageCache::new(&database::database_url(&config.database.path)?, config.database.passphrase.as_deref()).await?;
+    cache.set_retention(config.database.max_cached_messages, config.database.source_max_cached_messages);
+
+    let mut new_messages = Vec::new();
+    let mut already_cached = 0;
+    let mut conflicts = 0;
+    for msg in messages.iter() {
+        match cache.cached_source(msg.id).await? {
+            None => new_messages.push(msg.clone()),
+            Some(source) if source == msg.source => already_cached += 1,
+            // Caching it would replace the other source's message
+            Some(source) => {
+                conflicts += 1;
+                eprintln!("Warning: skipped {:?} message {}: the id is already used by a {:?} message", msg.source, msg.id, source);
             }
-        } else {
-            // No matching provider available
-            let error_source = target_source.unwrap_or(MessageSource::Discord);
-            let error_message = Message {
-                id: (self.messages.len() + 1) as u64,
-                source: error_source,
-                content: format!("❌ No provider configured for {:?}: {}", error_source, message_content),
-                timestamp: Utc::now(),
-                author: "System".to_string(),
-                attachments: vec![],
-                channel_id: None,
-            };
-            self.messages.push(error_message);
-            self.selected_message = Some(self.messages.len() - 1);
         }
-        
-        Ok(())
     }
+    cache.cache_messages(&new_messages).await?;
+
+    print!(
+        "Imported {} messages into {} ({} already cached",
+        new_messages.len(),
+        config.database.path.display(),
+        already_cached
+    );
+    if conflicts > 0 {
+        print!(", {} skipped for clashing ids", conflicts);
+    }
+    println!(")");
+    Ok(())
+}
+
+/// Runs every configured provider's health check and prints a table of results
+async fn run_check() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = Config::from_env()?;
+    let http_client = integrations::http_client(config.proxy_url.as_deref(), &config.user_agent)?;
+
+    if !config.has_any_provider() {
+        println!("No providers configured. Please check your .env file.");
+        return Ok(());
+    }
+
+    // Providers that couldn't even be set up count as failed checks
+    let mut unbuilt: Vec<(String, String)> = Vec::new();
+    let mut providers: Vec<(Box<dyn MessageProvider + Send + Sync>, Duration)> = Vec::new();
+    for telegram in &config.telegram {
+        // Connecting signs in, so a bad session shows up here rather than as a failed check
+        let provider = TelegramProvider::new(telegram, mpsc::unbounded_channel().0).await;
+        match provider {
+            Ok(provider) => providers.push((Box::new(provider), Duration::from_secs(telegram.fetch_timeout_secs))),
+            Err(e) => {
+                let name = match &telegram.name {
+                    Some(account) => format!("Telegram ({})", account),
+                    None => "Telegram".to_string(),
+                };
+                unbuilt.push((name, format!("could not connect: {}", e)));
+            }
+        }
+    }
+    if let Some(discord) = &config.discord {
+        // The token is what's checked, so any channel will do
+        let channel_id = discord.channel_ids.first().cloned().unwrap_or_default();
+        let provider = DiscordProvider::new(discord.user_token.clone(), channel_id, http_client.clone());
+        providers.push((Box::new(provider), Duration::from_secs(discord.fetch_timeout_secs)));
+    }
+    // Nothing listens for live events here; the check only waits to sign in
+    providers.extend(build_providers(&config, &http_client, &mpsc::unbounded_channel().0));
+
+    let results = futures::future::join_all(providers.iter().map(|(provider, timeout)| async move {
+        match tokio::time::timeout(*timeout, provider.health_check()).await {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err(format!("no response within {}s", timeout.as_secs())),
+        }
+    }))
+    .await;
+
+    let mut rows: Vec<(String, Result<(), String>)> = providers
+        .iter()
+        .map(|(provider, _)| provider.display_name())
+        .zip(results)
+        .collect();
+    rows.extend(unbuilt.into_iter().map(|(name, e)| (name, Err(e))));
+
+    let width = rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0).max("Provider".len());
+    println!("{:width$}  Result", "Provider");
+    let mut failures = 0;
+    for (name, result) in &rows {
+        match result {
+            Ok(()) => println!("{:width$}  ok", name),
+            Err(e) => {
+                failures += 1;
+                println!("{:width$}  FAILED: {}", name, e);
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(format!("{} of {} providers failed", failures, rows.len()).into());
+    }
+    Ok(())
+}
+
+fn run_set_secret(reference: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !secrets::ENABLED {
+        return Err(secrets::NOT_BUILT.into());
+    }
+
+    let secret = read_hidden(&format!("Secret for {}: ", reference))?;
+    if secret.is_empty() {
+        return Err("No secret entered".into());
+    }
+
+    secrets::store(reference, &secret)?;
+    println!("Stored. Reference it in .env as keyring:{}", reference.trim_start_matches("keyring:"));
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let config = Config::from_env()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let plain = match cli::parse(&args) {
+        Ok(cli::Command::Tui { plain }) => plain,
+        Ok(cli::Command::Check) => return run_check().await,
+        Ok(cli::Command::Cleanup(scope)) => return run_cleanup(scope).await,
+        Ok(cli::Command::Export(options)) => return run_export(options).await,
+        Ok(cli::Command::Import(path)) => return run_import(&path).await,
+        Ok(cli::Command::SetSecret(reference)) => return run_set_secret(&reference),
+        Ok(cli::Command::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    let mut config = Config::from_env()?;
+    if plain {
+        config.use_plain_output();
+    }
+    
+    for warning in config.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+
+    let problems = config.validate();
+    if !problems.is_empty() {
+        eprintln!("Configuration problems:");
+        for problem in &problems {
+            eprintln!("  - {}", problem);
+        }
+        eprintln!("Please fix these in your .env file.");
+        return Err(format!("{} configuration problem(s)", problems.len()).into());
+    }
     
     if !config.has_any_provider() {
         eprintln!("No providers configured. Please check your .env file.");
@@ -454,23 +3828,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         return Ok(());
     }
 
-    // Handle Telegram authentication before starting TUI
-    let mut telegram_provider = None;
-    if let Some(ref telegram_config) = config.telegram {
-        println!("Initializing Telegram client...");
+    // Ask for the cache passphrase while the terminal is still in cooked mode
+    resolve_database_passphrase(&mut config.database)?;
+
+    // Older versions kept the cache in the working directory
+    if config.database.path != std::path::Path::new(":memory:")
+        && !config.database.path.exists()
+        && std::path::Path::new("messages.db").exists()
+    {
+        eprintln!(
+            "Warning: found messages.db in the current directory; the cache now lives at {} (move it there or set DATABASE_PATH)",
+            config.database.path.display()
+        );
+    }
+
+    // Handle Telegram authentication before starting TUI, one account at a
+    // time so each can prompt for its login code. Providers get the live
+    // channel up front so a lost connection is reported from the start.
+    let (live_sender, live_events) = mpsc::unbounded_channel();
+    let mut telegram_providers = Vec::new();
+    for telegram_config in &config.telegram {
+        match &telegram_config.name {
+            Some(account) => println!("Initializing Telegram client for {}...", account),
+            None => println!("Initializing Telegram client..."),
+        }
         println!("API ID: {}", telegram_config.api_id);
         println!("Phone: {}", telegram_config.phone);
         println!("Session file: {:?}", telegram_config.session_file);
         
-        match TelegramProvider::new(
-            telegram_config.api_id,
-            telegram_config.api_hash.clone(),
-            telegram_config.phone.clone(),
-            telegram_config.session_file.clone(),
-        ).await {
+        match TelegramProvider::new(telegram_config, live_sender.clone()).await {
             Ok(provider) => {
                 println!("Telegram authentication successful!");
-                telegram_provider = Some(provider);
+                telegram_providers.push(provider);
             }
             Err(e) => {
                 eprintln!("Failed to authenticate with Telegram: {}", e);
@@ -483,197 +3872,169 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen)?;
+    // Mouse reporting gets in the way of screen readers' own review keys
+    if !config.plain {
+        execute!(stdout, EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(config, telegram_provider).await?;
+    let mut app = App::new(config, telegram_providers, live_sender, live_events).await?;
 
     loop {
-        // Auto-refresh messages periodically
-        if app.should_refresh() && !app.input_mode {
-            if let Err(e) = app.refresh_messages().await {
-                eprintln!("Error refreshing messages: {}", e);
-            }
+        app.process_live_events().await;
+        app.process_connectivity();
+        app.save_draft_if_due().await;
+        app.process_refresh_results().await;
+        app.process_thread_results();
+        app.process_batch_results();
+        app.load_selected_thread().await;
+        if app.mark_read_on_select {
+            app.mark_selected_read().await;
         }
-        terminal.draw(|f| {
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-                .split(f.area());
-                
-            let content_chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Min(1), Constraint::Length(3)].as_ref())
-                .split(chunks[1]);
 
-            let items: Vec<ListItem> = app
-                .messages
-                .iter()
-                .enumerate()
-                .map(|(i, msg)| {
-                    let source_prefix = match msg.source {
-                        MessageSource::Discord => "🎮",
-                        MessageSource::Telegram => "✈️",
-                        MessageSource::Github => "🐙",
-                        MessageSource::Jira => "📋",
-                    };
-                    
-                    let content = format!(
-                        "{} {} - {} ({})",
-                        source_prefix,
-                        msg.author,
-                        msg.content,
-                        msg.timestamp.format("%H:%M")
-                    );
-                    
-                    let style = if Some(i) == app.selected_message {
-                        let mut style = Style::default();
-                        if let Some(ref bg_color) = app.colors.selected_bg {
-                            style = style.bg(parse_color(bg_color));
-                        } else {
-                            style = style.bg(Color::Blue); // Default
-                        }
-                        if let Some(ref fg_color) = app.colors.selected_fg {
-                            style = style.fg(parse_color(fg_color));
-                        }
-                        style
-                    } else {
-                        Style::default()
-                    };
-                    
-                    ListItem::new(content).style(style)
-                })
-                .collect();
+        // Auto-refresh messages periodically
+        if app.should_refresh() && !app.input_mode && !app.search_editing {
+            app.request_refresh();
+        }
+        terminal.draw(|f| ui(f, &app))?;
 
-            let messages_list = List::new(items)
-                .block(Block::default().borders(Borders::ALL).title("Messages"))
-                .style(Style::default());
+        // Send after drawing so the optimistic message shows immediately
+        if app.should_quit {
+            break;
+        }
 
-            let mut list_state = ratatui::widgets::ListState::default();
-            if let Some(selected) = app.selected_message {
-                list_state.select(Some(selected));
+        if let Some(pending) = app.pending_send.take() {
+            if let Err(e) = app.send_message(pending).await {
+                eprintln!("Error sending message: {}", e);
             }
+            continue;
+        }
 
-            f.render_stateful_widget(messages_list, chunks[0], &mut list_state);
+        // Wake up periodically so auto-refresh fires even without input, and
+        // more often while refreshing to animate the spinner and pick up results
+        let tick = if app.is_refreshing { 100 } else { 250 };
+        if !event::poll(Duration::from_millis(tick))? {
+            continue;
+        }
 
-            let content = if let Some(msg) = app.get_selected_message() {
-                let mut text = format!(
-                    "Source: {:?}\nAuthor: {}\nTime: {}\n\n{}",
-                    msg.source,
-                    msg.author,
-                    msg.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
-                    msg.content
-                );
-                
-                if !msg.attachments.is_empty() {
-                    text.push_str("\n\nAttachments:");
-                    for attachment in &msg.attachments {
-                        let type_icon = match attachment.file_type {
-                            AttachmentType::Image => "🖼️",
-                            AttachmentType::Video => "🎥",
-                            AttachmentType::Audio => "🎵",
-                            AttachmentType::Document => "📄",
-                            AttachmentType::Other => "📎",
-                        };
-                        
-                        let size_str = if let Some(size) = attachment.size {
-                            format!(" ({}B)", size)
-                        } else {
-                            String::new()
-                        };
-                        
-                        text.push_str(&format!("\n  {} {}{}", type_icon, attachment.filename, size_str));
+        let event = event::read()?;
+        if let Event::Mouse(mouse) = event
+            && app.popup.is_none()
+        {
+            app.handle_mouse(mouse);
+        } else if let Event::Key(key) = event {
+            if app.popup.is_some() {
+                app.handle_popup_key(key.code).await;
+            } else if app.search_editing {
+                match key.code {
+                    KeyCode::Enter => app.apply_search().await,
+                    KeyCode::Esc => app.clear_search(),
+                    KeyCode::Backspace => {
+                        app.search_text.pop();
+                        app.update_search();
+                    }
+                    KeyCode::Char(c) => {
+                        app.search_text.push(c);
+                        app.update_search();
                     }
+                    _ => {}
                 }
-                
-                text
-            } else {
-                "No message selected".to_string()
-            };
-
-            let content_area = Paragraph::new(content)
-                .block(Block::default().borders(Borders::ALL).title("Content"))
-                .style(Style::default());
-
-            f.render_widget(content_area, content_chunks[0]);
-            
-            let input_style = if app.input_mode {
-                let color = if let Some(ref active_color) = app.colors.input_active {
-                    parse_color(active_color)
-                } else {
-                    Color::Yellow // Default
-                };
-                Style::default().fg(color)
-            } else {
-                let color = if let Some(ref inactive_color) = app.colors.input_inactive {
-                    parse_color(inactive_color)
-                } else {
-                    Color::DarkGray // Default
-                };
-                Style::default().fg(color)
-            };
-            
-            let input_title = if app.input_mode {
-                "Input (Tab to send, Esc to cancel)"
-            } else {
-                "Input (Enter to type, Tab to send)"
-            };
-            
-            let input_area = Paragraph::new(app.input_text.as_str())
-                .block(Block::default().borders(Borders::ALL).title(input_title))
-                .style(input_style);
-
-            f.render_widget(input_area, content_chunks[1]);
-            
-            if app.input_mode {
-                f.set_cursor_position((
-                    content_chunks[1].x + app.input_text.len() as u16 + 1,
-                    content_chunks[1].y + 1,
-                ));
-            }
-        })?;
-
-        if let Event::Key(key) = event::read()? {
-            if app.input_mode {
+            } else if app.confirming_send.is_some() {
+                app.answer_send_confirmation(matches!(key.code, KeyCode::Char('y' | 'Y') | KeyCode::Enter));
+            } else if app.input_mode {
                 match key.code {
-                    KeyCode::Enter => {
-                        if key.modifiers.contains(KeyModifiers::SHIFT) {
-                            // Shift+Enter to send message (non-blocking)
-                            if let Err(e) = app.send_message_non_blocking() {
-                                eprintln!("Error sending message: {}", e);
-                            }
-                        }
-                        // Regular Enter does nothing in input mode
+                    KeyCode::Enter if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                        // Shift+Enter to send message (non-blocking)
+                        app.submit_input().await;
                     }
+                    // Regular Enter does nothing in input mode
+                    KeyCode::Enter => {}
+                    KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.popup = Some(Popup::Emoji { query: String::new(), selected: 0 });
+                    }
+                    KeyCode::Tab if app.autocomplete.is_some() => app.accept_autocomplete(),
+                    KeyCode::Down if app.autocomplete.is_some() => app.move_autocomplete(true),
+                    KeyCode::Up if app.autocomplete.is_some() => app.move_autocomplete(false),
+                    KeyCode::Esc if app.autocomplete.is_some() => app.autocomplete = None,
                     KeyCode::Esc => {
-                        app.input_mode = false;
-                        app.input_text.clear();
+                        // Keep what was typed in case the cancel was an accident
+                        app.save_draft().await;
+                        app.cancel_input();
                     }
                     KeyCode::Backspace => {
                         app.input_text.pop();
+                        app.draft_changed = Some(Instant::now());
+                        app.update_autocomplete();
                     }
                     KeyCode::Char(c) => {
                         app.input_text.push(c);
+                        app.draft_changed = Some(Instant::now());
+                        app.update_autocomplete();
+                        app.notify_typing();
                     }
                     KeyCode::Tab => {
                         // Alternative: Use Tab to send message in input mode (non-blocking)
-                        if let Err(e) = app.send_message_non_blocking() {
-                            eprintln!("Error sending message: {}", e);
-                        }
+                        app.submit_input().await;
                     }
                     _ => {}
                 }
             } else {
                 match key.code {
                     KeyCode::Char('q') => break,
-                    KeyCode::Down | KeyCode::Char('j') => app.select_next(),
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        app.load_older_messages().await;
+                        app.select_next();
+                    }
                     KeyCode::Up | KeyCode::Char('k') => app.select_previous(),
-                    KeyCode::Char('r') => {
-                        if let Err(e) = app.refresh_messages().await {
-                            eprintln!("Error refreshing messages: {}", e);
+                    KeyCode::Home | KeyCode::Char('g') => app.select_first(),
+                    KeyCode::End | KeyCode::Char('G') => app.select_last(),
+                    KeyCode::PageDown => app.page_down(),
+                    KeyCode::PageUp => app.page_up(),
+                    KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => app.page_down(),
+                    KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => app.page_up(),
+                    KeyCode::Char('r') => app.request_refresh(),
+                    KeyCode::Char('R') => app.mark_visible_read().await,
+                    KeyCode::Char('f') => app.cycle_source_filter(),
+                    KeyCode::Char('/') => app.search_editing = true,
+                    KeyCode::Char('c') => app.open_compose_picker(),
+                    KeyCode::Char('a') => app.toggle_auto_refresh(),
+                    KeyCode::Char('n') => app.toggle_dnd(),
+                    KeyCode::Char('z') => app.toggle_snooze(),
+                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.open_palette(),
+                    KeyCode::Char('p') => app.jump_to_parent(),
+                    KeyCode::Char('t') => app.open_transition_picker().await,
+                    KeyCode::Char('A') => app.open_assignee_picker().await,
+                    KeyCode::Char('b') => {
+                        if let Err(e) = app.toggle_bookmark().await {
+                            eprintln!("Error updating bookmark: {}", e);
+                        }
+                    }
+                    KeyCode::Char('B') => app.toggle_bookmarks_only(),
+                    KeyCode::Char('s') => app.download_selected_attachments().await,
+                    KeyCode::Char('S') => app.download_visible_attachments().await,
+                    KeyCode::Char('o') => app.open_selected_attachment().await,
+                    KeyCode::Char('y') => app.copy_selected(false),
+                    KeyCode::Char('Y') => app.copy_selected(true),
+                    KeyCode::Char('m') => {
+                        if let Err(e) = app.mute_selected_channel().await {
+                            eprintln!("Error muting channel: {}", e);
+                        }
+                    }
+                    KeyCode::Char('M') => {
+                        if let Err(e) = app.mute_selected_author().await {
+                            eprintln!("Error muting author: {}", e);
+                        }
+                    }
+                    KeyCode::Char('X') => {
+                        if let Err(e) = app.clear_current_source().await {
+                            eprintln!("Error clearing cached messages: {}", e);
                         }
                     }
+                    KeyCode::Char('e') => app.start_edit(),
+                    KeyCode::Char('u') => app.undo_send().await,
+                    KeyCode::Char('>') => app.start_reply(),
                     KeyCode::Char('d') => {
                         if let Err(e) = app.delete_selected_message().await {
                             eprintln!("Error deleting message: {}", e);
@@ -681,7 +4042,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                     }
                     KeyCode::Enter => {
                         // Enter to start typing
-                        app.input_mode = true;
+                        app.start_input().await;
                     }
                     _ => {}
                 }
@@ -689,6 +4050,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         }
     }
 
+    if app.input_mode {
+        app.save_draft().await;
+    }
+
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -697,4 +4062,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     )?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integrations::mock::{message, MockProvider};
+
+    #[tokio::test]
+    async fn a_sent_message_stays_editable_after_a_refresh() {
+        let cache = MessageCache::new("sqlite::memory:", None).await.unwrap();
+        let provider = MockProvider::new(MessageSource::Discord, vec![]);
+        let id = provider.send_message("message 7").await.unwrap().unwrap_or(7);
+        record_sent(&cache, MessageSource::Discord, id, None, None, "message 7").await;
+
+        // The service's copy of it knows nothing of the send
+        let mut manager = IntegrationManager::new();
+        manager.add_provider(
+            Box::new(MockProvider::new(MessageSource::Discord, vec![message(id, MessageSource::Discord, 0)])),
+            Duration::from_secs(5),
+        );
+        let (progress, _receiver) = mpsc::unbounded_channel();
+        let messages = fetch_refresh(&manager, &cache, 50, &progress).await;
+
+        let sent = messages.iter().find(|m| m.id == id).unwrap();
+        assert_eq!(sent.send_status, Some(SendStatus::Sent));
+        assert_eq!(edit_refusal(sent, manager.providers[0].as_ref()), None);
+    }
+}
@@ -0,0 +1,163 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+/// Renders the subset of markdown chat platforms commonly use (bold, italic,
+/// strikethrough, inline code, code blocks, links, headings, quotes and
+/// bullet lists) into styled ratatui text.
+///
+/// Returns `None` when the input can't be rendered sensibly (e.g. an
+/// unterminated code block) so callers can fall back to the raw text.
+pub fn render(input: &str) -> Option<Text<'static>> {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+
+    for raw_line in input.lines() {
+        let trimmed = raw_line.trim_start();
+
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+
+        if in_code_block {
+            lines.push(Line::from(vec![
+                Span::styled("│ ", Style::default().fg(Color::DarkGray)),
+                Span::styled(raw_line.to_string(), code_style()),
+            ]));
+            continue;
+        }
+
+        lines.push(render_line(raw_line));
+    }
+
+    if in_code_block {
+        return None;
+    }
+
+    Some(Text::from(lines))
+}
+
+fn code_style() -> Style {
+    Style::default().fg(Color::Yellow)
+}
+
+fn render_line(line: &str) -> Line<'static> {
+    let indent_len = line.len() - line.trim_start().len();
+    let (indent, rest) = line.split_at(indent_len);
+    let mut spans = Vec::new();
+
+    if let Some(heading) = strip_heading(rest) {
+        parse_inline(heading, Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED), &mut spans);
+    } else if let Some(quote) = rest.strip_prefix("> ").or_else(|| rest.strip_prefix('>').filter(|q| q.is_empty())) {
+        spans.push(Span::styled("│ ", Style::default().fg(Color::DarkGray)));
+        parse_inline(quote, Style::default().add_modifier(Modifier::ITALIC), &mut spans);
+    } else if let Some(item) = rest
+        .strip_prefix("- ")
+        .or_else(|| rest.strip_prefix("* "))
+        .or_else(|| rest.strip_prefix("+ "))
+    {
+        spans.push(Span::raw(format!("{}• ", indent)));
+        parse_inline(item, Style::default(), &mut spans);
+    } else {
+        if !indent.is_empty() {
+            spans.push(Span::raw(indent.to_string()));
+        }
+        parse_inline(rest, Style::default(), &mut spans);
+    }
+
+    Line::from(spans)
+}
+
+fn strip_heading(line: &str) -> Option<&str> {
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) {
+        line[hashes..].strip_prefix(' ')
+    } else {
+        None
+    }
+}
+
+/// Parses inline markup, appending styled spans. Unmatched markers are kept
+/// as literal text rather than treated as errors.
+fn parse_inline(text: &str, base: Style, spans: &mut Vec<Span<'static>>) {
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let consumed = match c {
+            '`' => rest[1..].find('`').map(|end| {
+                flush(&mut plain, base, spans);
+                spans.push(Span::styled(rest[1..1 + end].to_string(), code_style()));
+                end + 2
+            }),
+            '*' | '_' | '~' => {
+                let (marker, modifier) = if rest.starts_with("**") || rest.starts_with("__") {
+                    (&rest[..2], Modifier::BOLD)
+                } else if rest.starts_with("~~") {
+                    (&rest[..2], Modifier::CROSSED_OUT)
+                } else if c == '~' {
+                    ("", Modifier::empty())
+                } else {
+                    (&rest[..1], Modifier::ITALIC)
+                };
+
+                // `_` inside identifiers like snake_case is not emphasis
+                let word_boundary = c != '_' || !plain.chars().last().is_some_and(|p| p.is_alphanumeric());
+
+                if marker.is_empty() || !word_boundary {
+                    None
+                } else {
+                    let inner = &rest[marker.len()..];
+                    // An opening marker must hug its text, so `2 * 3` stays literal
+                    let opens = !inner.starts_with(char::is_whitespace);
+                    inner.find(marker).filter(|&end| opens && end > 0).map(|end| {
+                        flush(&mut plain, base, spans);
+                        parse_inline(&inner[..end], base.add_modifier(modifier), spans);
+                        marker.len() * 2 + end
+                    })
+                }
+            }
+            '[' => parse_link(rest).map(|(label, url, len)| {
+                flush(&mut plain, base, spans);
+                let link_style = base.fg(Color::LightBlue).add_modifier(Modifier::UNDERLINED);
+                parse_inline(label, link_style, spans);
+                if label != url {
+                    spans.push(Span::styled(format!(" ({})", url), Style::default().fg(Color::DarkGray)));
+                }
+                len
+            }),
+            _ => None,
+        };
+
+        match consumed {
+            Some(len) => rest = &rest[len..],
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    flush(&mut plain, base, spans);
+}
+
+/// Matches `[label](url)` at the start of `text`, returning the label, the
+/// url and the number of bytes consumed.
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let label = &text[1..label_end];
+    if label.contains('\n') {
+        return None;
+    }
+    let url_start = label_end + 2;
+    let url_end = url_start + text[url_start..].find(')')?;
+    Some((label, &text[url_start..url_end], url_end + 1))
+}
+
+fn flush(plain: &mut String, style: Style, spans: &mut Vec<Span<'static>>) {
+    if !plain.is_empty() {
+        spans.push(Span::styled(std::mem::take(plain), style));
+    }
+}
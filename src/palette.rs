@@ -0,0 +1,98 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use crate::integrations::IntegrationManager;
+use crate::MessageSource;
+
+/// Something the command palette can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Refresh,
+    MarkVisibleRead,
+    MarkAllRead,
+    ToggleAutoRefresh,
+    ToggleDnd,
+    ToggleSnooze,
+    Compose,
+    Edit,
+    Reply,
+    ShowSource(Option<MessageSource>),
+    ToggleBookmarksOnly,
+    Search,
+    /// Select the newest message handled by this provider
+    JumpToChannel(usize),
+    SaveAttachments,
+    /// Save the attachments of every message in view
+    SaveAllAttachments,
+    OpenAttachment,
+    CopyMessage,
+    CopyUrl,
+    ClearSource,
+    ExportChannel,
+    Quit,
+}
+
+pub struct Entry {
+    pub label: String,
+    pub command: Command,
+}
+
+/// Every action plus one "Go to" entry per provider
+pub fn entries(manager: &IntegrationManager) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = [
+        ("Refresh", Command::Refresh),
+        ("Mark shown messages read", Command::MarkVisibleRead),
+        ("Mark all cached messages read", Command::MarkAllRead),
+        ("Toggle auto-refresh", Command::ToggleAutoRefresh),
+        ("Toggle do-not-disturb", Command::ToggleDnd),
+        ("Snooze alerts (or end the snooze)", Command::ToggleSnooze),
+        ("Compose message", Command::Compose),
+        ("Edit selected message", Command::Edit),
+        ("Reply with quote", Command::Reply),
+        ("Show all sources", Command::ShowSource(None)),
+        ("Show only Telegram", Command::ShowSource(Some(MessageSource::Telegram))),
+        ("Show only Discord", Command::ShowSource(Some(MessageSource::Discord))),
+        ("Show only GitHub", Command::ShowSource(Some(MessageSource::Github))),
+        ("Show only Jira", Command::ShowSource(Some(MessageSource::Jira))),
+        ("Show only Reddit", Command::ShowSource(Some(MessageSource::Reddit))),
+        ("Show only XMPP", Command::ShowSource(Some(MessageSource::Xmpp))),
+        ("Show only Teams", Command::ShowSource(Some(MessageSource::Teams))),
+        ("Show only SMS", Command::ShowSource(Some(MessageSource::Sms))),
+        ("Show only REST", Command::ShowSource(Some(MessageSource::Rest))),
+        ("Toggle bookmarks only", Command::ToggleBookmarksOnly),
+        ("Search messages", Command::Search),
+        ("Save attachments", Command::SaveAttachments),
+        ("Save all attachments in view", Command::SaveAllAttachments),
+        ("Open attachment", Command::OpenAttachment),
+        ("Copy message text", Command::CopyMessage),
+        ("Copy message URL", Command::CopyUrl),
+        ("Clear cached messages of current source", Command::ClearSource),
+        ("Export this channel to Markdown", Command::ExportChannel),
+        ("Quit", Command::Quit),
+    ]
+    .into_iter()
+    .map(|(label, command)| Entry { label: label.to_string(), command })
+    .collect();
+
+    entries.extend(manager.providers.iter().enumerate().map(|(index, provider)| Entry {
+        label: format!("Go to {}", provider.display_name()),
+        command: Command::JumpToChannel(index),
+    }));
+    entries
+}
+
+/// Indices of the entries matching `query`, best match first. An empty
+/// query matches everything in its original order.
+pub fn filter(entries: &[Entry], query: &str) -> Vec<usize> {
+    if query.is_empty() {
+        return (0..entries.len()).collect();
+    }
+
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut scored: Vec<(i64, usize)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| Some((matcher.fuzzy_match(&entry.label, query)?, index)))
+        .collect();
+    scored.sort_by_key(|&(score, index)| (std::cmp::Reverse(score), index));
+    scored.into_iter().map(|(_, index)| index).collect()
+}
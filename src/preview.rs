@@ -0,0 +1,46 @@
+use ratatui::{layout::Rect, Frame};
+use ratatui_image::picker::{Picker, ProtocolType};
+use ratatui_image::protocol::StatefulProtocol;
+use ratatui_image::StatefulImage;
+
+/// Draws downloaded image attachments with the terminal's graphics protocol
+/// (kitty, iTerm2 or sixel)
+pub struct ImagePreview {
+    picker: Picker,
+    /// The decoded image currently on screen, by file path
+    current: Option<(String, StatefulProtocol)>,
+}
+
+impl ImagePreview {
+    /// Asks the terminal which graphics protocol it speaks. Must run after
+    /// entering raw mode and before reading any events. Returns `None` for
+    /// terminals without one, which keep the plain filename listing.
+    pub fn detect() -> Option<Self> {
+        let picker = Picker::from_query_stdio().ok()?;
+        if picker.protocol_type() == ProtocolType::Halfblocks {
+            return None;
+        }
+        Some(Self { picker, current: None })
+    }
+
+    /// Renders the image at `path` into `area`, decoding it only when it
+    /// changes. Returns false if the file can't be read as an image.
+    pub fn render(&mut self, f: &mut Frame, area: Rect, path: &str) -> bool {
+        if self.current.as_ref().is_none_or(|(current, _)| current != path) {
+            let Some(image) = image::ImageReader::open(path)
+                .ok()
+                .and_then(|reader| reader.with_guessed_format().ok())
+                .and_then(|reader| reader.decode().ok())
+            else {
+                self.current = None;
+                return false;
+            };
+            self.current = Some((path.to_string(), self.picker.new_resize_protocol(image)));
+        }
+
+        if let Some((_, protocol)) = self.current.as_mut() {
+            f.render_stateful_widget(StatefulImage::default(), area, protocol);
+        }
+        true
+    }
+}
@@ -0,0 +1,122 @@
+use regex::{Regex, RegexBuilder};
+use crate::{Message, MessageSource};
+
+/// One condition of a search; a message must satisfy all of them
+#[derive(Debug)]
+pub enum Term {
+    /// Case-insensitive substring of the content
+    Text(String),
+    /// Free text after a `/re ` prefix
+    Pattern(Regex),
+    /// Case-insensitive substring of the author
+    Author(String),
+    Source(MessageSource),
+    /// Case-insensitive substring of the channel id
+    Channel(String),
+}
+
+#[derive(Debug, Default)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+/// Parses a query like `author:bot source:discord error`. A leading `/re `
+/// turns the free text into case-insensitive regexes; field filters
+/// (`author:`, `source:`, `channel:`) stay plain either way.
+pub fn parse_query(input: &str) -> Result<Query, String> {
+    let (regex, rest) = match input.trim_start().strip_prefix("/re ") {
+        Some(rest) => (true, rest),
+        None => (false, input),
+    };
+
+    let mut terms = Vec::new();
+    for word in rest.split_whitespace() {
+        let term = match word.split_once(':') {
+            Some(("author", value)) if !value.is_empty() => Term::Author(value.to_lowercase()),
+            Some(("channel", value)) if !value.is_empty() => Term::Channel(value.to_lowercase()),
+            Some(("source", value)) => {
+                Term::Source(MessageSource::from_name(value).ok_or_else(|| format!("Unknown source '{}'", value))?)
+            }
+            _ if regex => Term::Pattern(
+                RegexBuilder::new(word)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("Invalid regex '{}': {}", word, error_summary(&e.to_string())))?,
+            ),
+            _ => Term::Text(word.to_lowercase()),
+        };
+        terms.push(term);
+    }
+
+    Ok(Query { terms })
+}
+
+// Regex errors draw a caret diagram over several lines; the last one says what's wrong
+fn error_summary(error: &str) -> &str {
+    error.lines().rfind(|line| !line.trim().is_empty()).unwrap_or(error).trim_start_matches("error: ")
+}
+
+impl Query {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, message: &Message) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Text(text) => message.content.to_lowercase().contains(text),
+            Term::Pattern(regex) => regex.is_match(&message.content),
+            Term::Author(author) => message.author.to_lowercase().contains(author),
+            Term::Source(source) => message.source == *source,
+            Term::Channel(channel) => message
+                .channel_id
+                .as_ref()
+                .is_some_and(|id| id.to_lowercase().contains(channel)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn message(source: MessageSource, author: &str, content: &str) -> Message {
+        Message {
+            id: 1,
+            source,
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            author: author.to_string(),
+            attachments: vec![],
+            reactions: vec![],
+            channel_id: Some("deploys".to_string()),
+            reply_to_id: None,
+            edited: false,
+            send_status: None,
+        }
+    }
+
+    #[test]
+    fn combines_field_scopes_and_text() {
+        let query = parse_query("author:bot source:discord error").unwrap();
+        assert!(query.matches(&message(MessageSource::Discord, "DeployBot", "Build ERROR on main")));
+        assert!(!query.matches(&message(MessageSource::Github, "DeployBot", "Build error on main")));
+        assert!(!query.matches(&message(MessageSource::Discord, "alice", "Build error on main")));
+        assert!(!query.matches(&message(MessageSource::Discord, "DeployBot", "Build passed")));
+    }
+
+    #[test]
+    fn regex_prefix_applies_to_free_text() {
+        let query = parse_query("/re channel:deploy fail(ed|ure)$").unwrap();
+        assert!(query.matches(&message(MessageSource::Jira, "ci", "Deploy FAILED")));
+        assert!(!query.matches(&message(MessageSource::Jira, "ci", "failed twice")));
+    }
+
+    #[test]
+    fn reports_bad_regexes_and_sources() {
+        assert!(parse_query("/re (unclosed").unwrap_err().starts_with("Invalid regex"));
+        assert_eq!(parse_query("source:myspace").unwrap_err(), "Unknown source 'myspace'");
+        // Without the prefix, regex syntax is just text
+        assert!(parse_query("(unclosed").is_ok());
+    }
+}
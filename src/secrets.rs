@@ -0,0 +1,53 @@
+//! Secrets kept in the OS secret store (macOS Keychain, Windows Credential
+//! Manager, Secret Service on Linux) instead of `.env`.
+//!
+//! Any secret setting may hold a reference like `keyring:friend/discord`:
+//! service `friend`, entry `discord`. Without a `/` the service is `friend`.
+
+const PREFIX: &str = "keyring:";
+#[cfg(feature = "keyring")]
+const DEFAULT_SERVICE: &str = "friend";
+
+pub const ENABLED: bool = cfg!(feature = "keyring");
+pub const NOT_BUILT: &str = "friend was built without keyring support; rebuild with `--features keyring`";
+
+/// Splits `service/name` (or just `name`) into its keyring service and entry
+#[cfg(feature = "keyring")]
+fn split_reference(reference: &str) -> (&str, &str) {
+    reference.split_once('/').unwrap_or((DEFAULT_SERVICE, reference))
+}
+
+/// Returns `value` unchanged, or the stored secret if it's a `keyring:` reference
+pub fn resolve(value: String) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    match value.strip_prefix(PREFIX) {
+        Some(reference) => load(reference),
+        None => Ok(value),
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn load(reference: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let (service, name) = split_reference(reference);
+    keyring::Entry::new(service, name)?
+        .get_password()
+        .map_err(|e| format!("Could not read secret '{}' from the keyring: {}", reference, e).into())
+}
+
+#[cfg(not(feature = "keyring"))]
+fn load(reference: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    Err(format!("Can't read '{}{}': {}", PREFIX, reference, NOT_BUILT).into())
+}
+
+/// Stores a secret under `service/name` (or just `name`)
+#[cfg(feature = "keyring")]
+pub fn store(reference: &str, secret: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let reference = reference.strip_prefix(PREFIX).unwrap_or(reference);
+    let (service, name) = split_reference(reference);
+    keyring::Entry::new(service, name)?.set_password(secret)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store(_reference: &str, _secret: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    Err(NOT_BUILT.into())
+}
@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Word list used to flag typos in the compose box. Reads hunspell `.dic`
+/// files (ignoring their affix flags) as well as plain one-word-per-line
+/// lists like `/usr/share/dict/words`.
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// Loads `path`, or else the first dictionary found for `language`
+    /// (e.g. `en_US`) in the usual system locations
+    pub fn load(language: &str, path: Option<&Path>) -> Result<Self, String> {
+        let candidates = match path {
+            Some(path) => vec![path.to_path_buf()],
+            None => dictionary_paths(language),
+        };
+        let path = candidates
+            .iter()
+            .find(|path| path.is_file())
+            .ok_or_else(|| format!("no dictionary found for {} (set SPELLCHECK_DICTIONARY)", language))?;
+        let contents = std::fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        // Older dictionaries are often Latin-1; unknown bytes just won't match
+        Ok(Self::from_words(String::from_utf8_lossy(&contents).lines()))
+    }
+
+    pub fn from_words<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        let words = lines
+            .into_iter()
+            // `word/FLAGS` in hunspell files
+            .map(|line| line.split('/').next().unwrap_or("").trim())
+            // The word count heading a `.dic` file
+            .filter(|word| !word.is_empty() && !word.bytes().all(|b| b.is_ascii_digit()))
+            .map(str::to_lowercase)
+            .collect();
+        Self { words }
+    }
+
+    /// Whether `word` or a common inflection of it is in the dictionary
+    pub fn is_known(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        let word = word.strip_suffix("'s").unwrap_or(&word);
+        if self.words.contains(word) {
+            return true;
+        }
+        // No affix rules are applied, so undo the usual English endings
+        let stem = |suffix: &str, replacement: &str| {
+            word.strip_suffix(suffix).is_some_and(|base| {
+                !base.is_empty() && self.words.contains(&format!("{}{}", base, replacement))
+            })
+        };
+        let doubled = |suffix: &str| {
+            // running -> run, stopped -> stop
+            word.strip_suffix(suffix).is_some_and(|base| {
+                let mut chars = base.char_indices().rev();
+                matches!((chars.next(), chars.next()), (Some((last, a)), Some((_, b))) if a == b && self.words.contains(&base[..last]))
+            })
+        };
+        stem("s", "")
+            || stem("es", "")
+            || stem("ies", "y")
+            || stem("ied", "y")
+            || stem("ed", "")
+            || stem("d", "")
+            || stem("ing", "")
+            || stem("ing", "e")
+            || stem("ly", "")
+            || stem("er", "")
+            || stem("est", "")
+            || doubled("ing")
+            || doubled("ed")
+    }
+
+    /// Byte ranges of the words in `text` that look misspelled. Capitalized
+    /// words (likely names), anything with digits, links, mentions,
+    /// `:shortcodes:`, quoted lines and the word still being typed are left
+    /// alone.
+    pub fn misspelled(&self, text: &str) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut line_start = 0;
+        for line in text.split('\n') {
+            if !line.trim_start().starts_with('>') {
+                for (offset, token) in tokens(line) {
+                    let start = line_start + offset;
+                    let end = start + token.len();
+                    // Not finished yet, so don't nag
+                    if end == text.len() {
+                        continue;
+                    }
+                    if let Some(word) = checkable_word(token)
+                        && !self.is_known(word.as_str(token))
+                    {
+                        ranges.push(start + word.start..start + word.end);
+                    }
+                }
+            }
+            line_start += line.len() + 1;
+        }
+        ranges
+    }
+}
+
+/// Where `language`'s dictionary usually lives on Linux and macOS
+fn dictionary_paths(language: &str) -> Vec<PathBuf> {
+    let file = format!("{}.dic", language);
+    let mut paths: Vec<PathBuf> = ["/usr/share/hunspell", "/usr/share/myspell", "/usr/share/myspell/dicts", "/Library/Spelling"]
+        .iter()
+        .map(|dir| Path::new(dir).join(&file))
+        .collect();
+    if let Ok(home) = std::env::var("HOME") {
+        paths.push(Path::new(&home).join("Library/Spelling").join(&file));
+    }
+    if language.starts_with("en") {
+        paths.push(PathBuf::from("/usr/share/dict/words"));
+    }
+    paths
+}
+
+/// Whitespace-separated tokens of `line` with their byte offsets
+fn tokens(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.split_whitespace()
+        .map(move |token| (token.as_ptr() as usize - line.as_ptr() as usize, token))
+}
+
+/// Where the word sits within its token
+struct Word {
+    start: usize,
+    end: usize,
+}
+
+impl Word {
+    fn as_str<'a>(&self, token: &'a str) -> &'a str {
+        &token[self.start..self.end]
+    }
+}
+
+/// The part of `token` worth checking, without surrounding punctuation
+fn checkable_word(token: &str) -> Option<Word> {
+    if token.contains("://") || token.starts_with(['@', '#', '/', '`', ':', '<']) {
+        return None;
+    }
+    let trimmed = token.trim_matches(|c: char| !c.is_alphanumeric());
+    if trimmed.is_empty()
+        || trimmed.chars().any(|c| c.is_ascii_digit() || c == '_')
+        || trimmed.starts_with(char::is_uppercase)
+    {
+        return None;
+    }
+    let start = token.find(trimmed)?;
+    Some(Word { start, end: start + trimmed.len() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> Dictionary {
+        Dictionary::from_words("3\nship/S\nit\nrun/SG\nmake\nstudy\nthe\nbuild\n".lines())
+    }
+
+    #[test]
+    fn reads_hunspell_entries_and_inflections() {
+        let dictionary = dictionary();
+        assert!(dictionary.is_known("ships"));
+        assert!(dictionary.is_known("running"));
+        assert!(dictionary.is_known("making"));
+        assert!(dictionary.is_known("studies"));
+        assert!(dictionary.is_known("Build's"));
+        assert!(!dictionary.is_known("shpi"));
+        assert!(!dictionary.is_known("3"));
+    }
+
+    #[test]
+    fn flags_unknown_words_but_not_names_links_or_the_current_word() {
+        let dictionary = dictionary();
+        let text = "ship teh build, Alice https://x.io @bob :tada: v2 runn";
+        let flagged: Vec<&str> = dictionary.misspelled(text).into_iter().map(|range| &text[range]).collect();
+        assert_eq!(flagged, vec!["teh"]);
+    }
+
+    #[test]
+    fn skips_quoted_lines() {
+        let dictionary = dictionary();
+        let text = "> qoute frm them\nthe buidl ";
+        let flagged: Vec<&str> = dictionary.misspelled(text).into_iter().map(|range| &text[range]).collect();
+        assert_eq!(flagged, vec!["buidl"]);
+    }
+}